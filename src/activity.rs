@@ -0,0 +1,131 @@
+//! 活动日志
+//!
+//! 发送通知、发送链接卡片、收到回复等事件按行追加到 `~/.gewe-cc/activity.log`，
+//! 通过 `gewe-cc logs` 查看，`--follow` 可在远程监督会话时实时跟踪
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::ConfigManager;
+use crate::output;
+
+/// `--follow` 模式检查新内容的间隔
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn log_file() -> Result<PathBuf> {
+    Ok(ConfigManager::new()?.config_dir().join("activity.log"))
+}
+
+/// 追加一条活动记录（失败时忽略，不影响主流程）
+pub fn log(event: &str) {
+    if let Ok(path) = log_file() {
+        let _ = append(&path, event);
+    }
+}
+
+fn append(path: &Path, event: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "[{}] {}", output::format_time(&Utc::now()), event)?;
+    Ok(())
+}
+
+/// 输出活动日志的最后 `lines` 行
+///
+/// `follow` 为 true 时继续输出新追加的内容（类似 `tail -f`），按 Ctrl+C 结束
+pub fn run(lines: usize, follow: bool) -> Result<()> {
+    let path = log_file()?;
+
+    if !follow && !path.exists() {
+        println!("暂无活动日志（{}）", path.display());
+        return Ok(());
+    }
+
+    let (content, mut position) = read_appended(&path, 0)?;
+    for line in tail_lines(&content, lines) {
+        println!("{}", line);
+    }
+
+    if !follow {
+        return Ok(());
+    }
+
+    loop {
+        std::thread::sleep(FOLLOW_POLL_INTERVAL);
+
+        let (appended, next) = read_appended(&path, position)?;
+        if !appended.is_empty() {
+            print!("{}", appended);
+            std::io::stdout().flush().context("输出日志失败")?;
+        }
+        position = next;
+    }
+}
+
+/// 内容的最后 `n` 行
+fn tail_lines(content: &str, n: usize) -> Vec<&str> {
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(n)..].to_vec()
+}
+
+/// 读取 `position` 之后追加的完整行，返回 `(内容, 新位置)`
+///
+/// 尚未写完的最后一行留到下次读取；文件变短（被清空或轮转）时从头读取，文件不存在时返回空内容
+fn read_appended(path: &Path, position: u64) -> Result<(String, u64)> {
+    let Ok(mut file) = File::open(path) else {
+        return Ok((String::new(), 0));
+    };
+
+    let len = file.metadata().context("读取活动日志失败")?.len();
+    let start = if len < position { 0 } else { position };
+    file.seek(SeekFrom::Start(start)).context("读取活动日志失败")?;
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).context("读取活动日志失败")?;
+
+    let complete = buf.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    Ok((String::from_utf8_lossy(&buf[..complete]).into_owned(), start + complete as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), ["b", "c"]);
+        assert_eq!(tail_lines("a\n", 5), ["a"]);
+        assert!(tail_lines("a\nb\n", 0).is_empty());
+    }
+
+    #[test]
+    fn test_read_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("activity.log");
+
+        assert_eq!(read_appended(&path, 0).unwrap(), (String::new(), 0));
+
+        append(&path, "发送通知").unwrap();
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all("收到".as_bytes()).unwrap();
+
+        // 未写完的行留到下次读取
+        let (content, position) = read_appended(&path, 0).unwrap();
+        assert!(content.ends_with("] 发送通知\n"));
+        assert_eq!(position, content.len() as u64);
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all("回复\n".as_bytes()).unwrap();
+        let (content, next) = read_appended(&path, position).unwrap();
+        assert_eq!(content, "收到回复\n");
+
+        // 文件被清空后从头读取
+        fs::write(&path, "新日志\n").unwrap();
+        assert_eq!(read_appended(&path, next).unwrap(), ("新日志\n".to_string(), 10));
+    }
+}
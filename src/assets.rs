@@ -0,0 +1,72 @@
+//! 静态资源管理
+//!
+//! serve 从 `~/.gewe-cc/assets/` 提供静态资源，send-link 的卡片缩略图即为其中的 `thumb.png`
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+use crate::output::{self, banner};
+use crate::server;
+
+/// 内置的默认卡片缩略图
+const DEFAULT_THUMB: &[u8] = include_bytes!("../assets/thumb.png");
+
+/// 缩略图文件名（send-link 引用 `/assets/thumb.png`）
+const THUMB_FILE: &str = "thumb.png";
+
+/// 初始化默认资源
+pub fn handle_init() -> Result<()> {
+    let assets_dir = server::get_assets_dir()?;
+    let created = write_default_thumb(&assets_dir)?;
+    let thumb_path = assets_dir.join(THUMB_FILE);
+
+    if created {
+        banner("  ✅ 默认缩略图已生成", Color::Green);
+    } else {
+        banner("  ℹ️ 缩略图已存在，已跳过", Color::Cyan);
+    }
+
+    if !output::is_quiet() {
+        println!("  {} {}", "文件:".dimmed(), thumb_path.display());
+        println!();
+    }
+
+    Ok(())
+}
+
+/// 写入默认缩略图，文件已存在时不覆盖
+///
+/// 返回是否创建了文件
+pub fn write_default_thumb(assets_dir: &Path) -> Result<bool> {
+    let thumb_path = assets_dir.join(THUMB_FILE);
+    if thumb_path.exists() {
+        return Ok(false);
+    }
+
+    fs::create_dir_all(assets_dir).context("创建资源目录失败")?;
+    fs::write(&thumb_path, DEFAULT_THUMB).context("写入缩略图失败")?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_default_thumb() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets_dir = dir.path().join("assets");
+
+        assert!(write_default_thumb(&assets_dir).unwrap());
+        let written = fs::read(assets_dir.join(THUMB_FILE)).unwrap();
+        assert!(written.starts_with(b"\x89PNG"));
+
+        // 已存在时不覆盖用户自定义的文件
+        fs::write(assets_dir.join(THUMB_FILE), b"custom").unwrap();
+        assert!(!write_default_thumb(&assets_dir).unwrap());
+        assert_eq!(fs::read(assets_dir.join(THUMB_FILE)).unwrap(), b"custom");
+    }
+}
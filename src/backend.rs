@@ -0,0 +1,633 @@
+//! 微信消息后端
+//!
+//! wechat 渠道的发送与等待回复有两种实现：默认调用 gewe-cli 子进程（`gewe_cli.mode = "cli"`），
+//! 或直接请求 gewe HTTP API（`mode = "api"`），省去每次创建进程的开销，也无需另外安装 gewe-cli
+
+use anyhow::{Context, Result};
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, State},
+    http::StatusCode,
+    routing::post,
+};
+use chrono::Utc;
+use std::io::{ErrorKind, Read};
+use std::net::TcpListener;
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+use crate::http;
+use crate::notify::{self, ReplyTimeout};
+use crate::server;
+
+/// 支持的消息后端
+pub const BACKEND_MODES: &[&str] = &["cli", "api"];
+
+/// gewe API 鉴权请求头
+const API_TOKEN_HEADER: &str = "X-GEWE-TOKEN";
+
+/// gewe API 表示成功的返回码
+const API_RET_OK: i64 = 200;
+
+/// 链接卡片内容
+pub struct LinkCard<'a> {
+    pub title: &'a str,
+    pub desc: &'a str,
+    pub link_url: &'a str,
+    pub thumb_url: &'a str,
+}
+
+/// 发送消息并等待回复的参数
+pub struct WaitRequest<'a> {
+    /// 发送消息并等待其回复的微信 ID
+    pub wxid: &'a str,
+    pub message: &'a str,
+    /// 接收微信回调的监听地址
+    pub listen: &'a str,
+    /// 超时时间（秒），0 表示无限等待
+    pub timeout_secs: u64,
+    /// 等待期间向 stderr 输出进度的间隔
+    pub progress: Option<Duration>,
+}
+
+/// 微信消息后端
+pub trait MessageBackend: Send + Sync {
+    /// 发送文本消息
+    fn send_text(&self, wxid: &str, text: &str) -> Result<()>;
+
+    /// 发送链接卡片
+    fn send_link(&self, wxid: &str, card: &LinkCard) -> Result<()>;
+
+    /// 发送消息并等待回复，返回去除首尾空白的回复原文
+    ///
+    /// 在限定时间内未收到回复时返回 [`ReplyTimeout`]
+    fn wait_reply(&self, request: &WaitRequest) -> Result<String>;
+}
+
+/// 按 `gewe_cli.mode` 创建消息后端
+///
+/// 创建前检查后端是否可用（gewe-cli 已安装、API 参数已配置），以便给出明确的提示
+pub fn from_config(config: &Config) -> Result<Box<dyn MessageBackend>> {
+    let gewe = &config.gewe_cli;
+    match gewe.mode.as_str() {
+        "cli" => {
+            notify::ensure_gewe_cli(&gewe.command)?;
+            Ok(Box::new(CliBackend {
+                command: gewe.command.clone(),
+                max_capture_bytes: gewe.max_capture_bytes,
+            }))
+        }
+        "api" => {
+            let missing = missing_api_fields(config);
+            if !missing.is_empty() {
+                anyhow::bail!("gewe_cli.mode = \"api\" 时需要配置 {}", missing.join("、"));
+            }
+            Ok(Box::new(ApiBackend {
+                base_url: gewe.api_base_url.trim_end_matches('/').to_string(),
+                token: gewe.api_token.clone(),
+                app_id: gewe.app_id.clone(),
+                timeout: Duration::from_secs(config.notification.http_timeout_secs),
+                webhook_token: config.notification.webhook_token.clone(),
+            }))
+        }
+        mode => anyhow::bail!("未知的消息后端「{}」，可选：{}", mode, BACKEND_MODES.join(", ")),
+    }
+}
+
+/// api 后端缺少的必填配置项
+pub fn missing_api_fields(config: &Config) -> Vec<&'static str> {
+    let gewe = &config.gewe_cli;
+    [
+        ("api_base_url", &gewe.api_base_url),
+        ("api_token", &gewe.api_token),
+        ("app_id", &gewe.app_id),
+    ]
+    .into_iter()
+    .filter(|(_, value)| value.trim().is_empty())
+    .map(|(name, _)| name)
+    .collect()
+}
+
+/// 通过 gewe-cli 子进程收发消息
+struct CliBackend {
+    command: String,
+    max_capture_bytes: usize,
+}
+
+impl CliBackend {
+    fn output(&self, args: &[&str]) -> Result<Output> {
+        Command::new(&self.command)
+            .args(args)
+            .output()
+            .context(format!("调用 {} 失败，请确认已安装 gewe-cli", self.command))
+    }
+}
+
+impl MessageBackend for CliBackend {
+    fn send_text(&self, wxid: &str, text: &str) -> Result<()> {
+        let output = self.output(&["message", "send-text", "--to", wxid, "--content", text])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("发送消息失败: {}", stderr);
+        }
+
+        Ok(())
+    }
+
+    fn send_link(&self, wxid: &str, card: &LinkCard) -> Result<()> {
+        let output = self.output(&[
+            "send-link",
+            "--to-wxid",
+            wxid,
+            "--title",
+            card.title,
+            "--desc",
+            card.desc,
+            "--link-url",
+            card.link_url,
+            "--thumb-url",
+            card.thumb_url,
+        ])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("发送链接卡片失败: {}", stderr);
+        }
+
+        Ok(())
+    }
+
+    fn wait_reply(&self, request: &WaitRequest) -> Result<String> {
+        let timeout_secs = request.timeout_secs;
+
+        // 调用 gewe-cli wait-reply
+        let mut cmd = Command::new(&self.command);
+        cmd.args([
+            "wait-reply",
+            "--to-wxid",
+            request.wxid,
+            "--listen",
+            request.listen,
+            "-M",
+            &format!("text:{}", request.message),
+        ]);
+
+        // 如果 timeout_secs 为 0，不传 --timeout 参数（使用 gewe-cli 的默认值：无限等待）
+        if timeout_secs > 0 {
+            cmd.args(["--timeout", &timeout_secs.to_string()]);
+        }
+
+        // 配置了超时时，在其基础上留出余量作为兜底，防止卡死的 gewe-cli 一直不退出
+        let limits = CaptureLimits {
+            progress: request.progress,
+            max_bytes: self.max_capture_bytes,
+            deadline: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs) + WALL_CLOCK_GRACE),
+        };
+
+        let output = match capture_output(cmd, &limits) {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::TimedOut => anyhow::bail!(
+                "gewe-cli 在超时（{}秒）后仍未退出，已强制结束",
+                timeout_secs
+            ),
+            Err(e) => {
+                return Err(e).context(format!("调用 {} 失败，请确认已安装 gewe-cli", self.command));
+            }
+        };
+
+        if !output.status.success() {
+            let exit_code = output.status.code().unwrap_or(-1);
+            match exit_code {
+                1 => {
+                    if timeout_secs > 0 {
+                        return Err(ReplyTimeout(timeout_secs).into());
+                    } else {
+                        anyhow::bail!("等待微信回复超时");
+                    }
+                }
+                2 => anyhow::bail!("发送微信消息失败"),
+                3 => anyhow::bail!("webhook 启动失败，请检查监听地址: {}", request.listen),
+                _ => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    anyhow::bail!("gewe-cli 执行失败 (exit code {}): {}", exit_code, stderr);
+                }
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// 直接请求 gewe HTTP API 收发消息
+///
+/// 回复仍通过微信回调获取：等待期间在 `listen` 地址上接收 gewe 推送的消息
+struct ApiBackend {
+    base_url: String,
+    token: String,
+    app_id: String,
+    timeout: Duration,
+    /// 回调签名校验 token，与 serve 的 `/webhook/reply` 相同（为空时不校验）
+    webhook_token: String,
+}
+
+impl ApiBackend {
+    /// 调用 gewe API 接口，请求体自动附带 appId
+    fn call(&self, path: &str, mut body: serde_json::Value) -> Result<()> {
+        body["appId"] = self.app_id.clone().into();
+        let url = format!("{}/{}", self.base_url, path);
+        let response = http::post_json_with_headers(&url, &[(API_TOKEN_HEADER, &self.token)], &body, self.timeout)?;
+        check_api_response(&response)
+    }
+}
+
+impl MessageBackend for ApiBackend {
+    fn send_text(&self, wxid: &str, text: &str) -> Result<()> {
+        self.call("message/postText", serde_json::json!({ "toWxid": wxid, "content": text }))
+            .context("发送消息失败")
+    }
+
+    fn send_link(&self, wxid: &str, card: &LinkCard) -> Result<()> {
+        let body = serde_json::json!({
+            "toWxid": wxid,
+            "title": card.title,
+            "desc": card.desc,
+            "linkUrl": card.link_url,
+            "thumbUrl": card.thumb_url,
+        });
+        self.call("message/postLink", body).context("发送链接卡片失败")
+    }
+
+    fn wait_reply(&self, request: &WaitRequest) -> Result<String> {
+        // 先开始监听再发送消息，避免错过很快到达的回复
+        let listener = TcpListener::bind(request.listen)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .context(format!("webhook 启动失败，请检查监听地址: {}", request.listen))?;
+
+        self.send_text(request.wxid, request.message)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("创建异步运行时失败")?;
+        runtime.block_on(wait_for_callback(
+            listener,
+            request.wxid,
+            &self.webhook_token,
+            request.timeout_secs,
+            request.progress,
+        ))
+    }
+}
+
+/// 检查 gewe API 的返回码，`ret` 不为 200 时返回包含 `msg` 的错误
+fn check_api_response(response: &serde_json::Value) -> Result<()> {
+    let ret = response.get("ret").and_then(|ret| ret.as_i64());
+    if ret == Some(API_RET_OK) {
+        return Ok(());
+    }
+
+    let msg = response.get("msg").and_then(|msg| msg.as_str()).unwrap_or_default();
+    match ret {
+        Some(ret) => anyhow::bail!("gewe API 返回错误（ret {}）: {}", ret, msg),
+        None => anyhow::bail!("gewe API 响应格式无法识别: {}", response),
+    }
+}
+
+/// 回调服务的共享状态
+#[derive(Clone)]
+struct CallbackState {
+    wxid: String,
+    webhook_token: String,
+    nonces: Arc<server::NonceCache>,
+    replies: mpsc::UnboundedSender<String>,
+}
+
+/// 接收微信回调，把等待对象发来的文本消息转交给等待方
+///
+/// 与 serve 的 `/webhook/reply` 一致：配置了 `webhook_token` 时先校验签名，失败返回 401；
+/// 请求体超过 [`server::MAX_REPLY_BODY_BYTES`] 时由 `DefaultBodyLimit` 返回 413
+async fn callback_handler(
+    State(state): State<CallbackState>,
+    Query(query): Query<server::SignatureQuery>,
+    body: Bytes,
+) -> (StatusCode, &'static str) {
+    if !state.webhook_token.is_empty()
+        && let Err(reason) = server::check_signature(&state.webhook_token, &query, &state.nonces, Utc::now().timestamp())
+    {
+        return (StatusCode::UNAUTHORIZED, reason);
+    }
+
+    if let Some((from, content)) = server::parse_text_message(&body)
+        && from == state.wxid
+    {
+        let _ = state.replies.send(content);
+    }
+    (StatusCode::OK, "OK")
+}
+
+/// 回复到达检查与进度输出的间隔
+const CALLBACK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 在 `listener` 上接收微信回调，直到收到 `wxid` 发来的文本消息或超时（`timeout_secs` 为 0 时不限时）
+async fn wait_for_callback(
+    listener: TcpListener,
+    wxid: &str,
+    webhook_token: &str,
+    timeout_secs: u64,
+    progress: Option<Duration>,
+) -> Result<String> {
+    let listener = tokio::net::TcpListener::from_std(listener).context("webhook 启动失败")?;
+    let (replies, mut received) = mpsc::unbounded_channel();
+    let state = CallbackState {
+        wxid: wxid.to_string(),
+        webhook_token: webhook_token.to_string(),
+        nonces: Arc::default(),
+        replies,
+    };
+    let app = Router::new()
+        .fallback(post(callback_handler).layer(DefaultBodyLimit::max(server::MAX_REPLY_BODY_BYTES)))
+        .with_state(state);
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let progress = progress.filter(|d| !d.is_zero());
+    let started = Instant::now();
+    let mut last_progress = started;
+    let result = loop {
+        if let Ok(reply) = tokio::time::timeout(CALLBACK_POLL_INTERVAL, received.recv()).await {
+            break reply
+                .map(|reply| reply.trim().to_string())
+                .ok_or_else(|| anyhow::anyhow!("webhook 意外停止"));
+        }
+
+        if timeout_secs > 0 && started.elapsed() >= Duration::from_secs(timeout_secs) {
+            break Err(ReplyTimeout(timeout_secs).into());
+        }
+
+        if let Some(interval) = progress
+            && last_progress.elapsed() >= interval
+        {
+            print_progress(started);
+            last_progress = Instant::now();
+        }
+    };
+
+    server.abort();
+    result
+}
+
+/// gewe-cli 超过配置的超时后仍未退出时，再等待的时长
+const WALL_CLOCK_GRACE: Duration = Duration::from_secs(30);
+
+/// 子进程输出采集的限制
+struct CaptureLimits {
+    /// 未结束前每隔该间隔向 stderr 输出一次等待进度
+    progress: Option<Duration>,
+    /// stdout / stderr 各自最多保留的字节数，超出部分丢弃
+    max_bytes: usize,
+    /// 子进程运行的最长时间，超出后强制结束
+    deadline: Option<Duration>,
+}
+
+/// 子进程状态轮询间隔
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 执行命令并以流式方式收集输出
+///
+/// stdout / stderr 各自只保留前 `max_bytes` 字节（超出时丢弃剩余内容并警告，
+/// 但会继续读取以免子进程因管道写满而阻塞）；超过 `deadline` 仍未结束时
+/// 强制结束子进程并返回 `TimedOut` 错误
+fn capture_output(mut cmd: Command, limits: &CaptureLimits) -> std::io::Result<Output> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    let max_bytes = limits.max_bytes;
+    let stdout = child.stdout.take().map(|pipe| std::thread::spawn(move || read_bounded(pipe, max_bytes)));
+    let stderr = child.stderr.take().map(|pipe| std::thread::spawn(move || read_bounded(pipe, max_bytes)));
+
+    let progress = limits.progress.filter(|d| !d.is_zero());
+    let started = Instant::now();
+    let mut next_progress = progress.map(|interval| started + interval);
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if limits.deadline.is_some_and(|deadline| started.elapsed() >= deadline) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(ErrorKind::TimedOut, "子进程超时未退出，已强制结束"));
+        }
+
+        if let (Some(interval), Some(next)) = (progress, next_progress)
+            && Instant::now() >= next
+        {
+            print_progress(started);
+            next_progress = Some(next + interval);
+        }
+
+        std::thread::sleep(CHILD_POLL_INTERVAL);
+    };
+
+    let join = |handle: Option<std::thread::JoinHandle<BoundedRead>>| {
+        handle
+            .map(|h| h.join().map_err(|_| std::io::Error::other("读取子进程输出失败"))?)
+            .unwrap_or_else(|| Ok((Vec::new(), false)))
+    };
+    let (stdout, stdout_truncated) = join(stdout)?;
+    let (stderr, stderr_truncated) = join(stderr)?;
+
+    if stdout_truncated || stderr_truncated {
+        eprintln!("⚠️ 子进程输出超过 {} 字节，超出部分已丢弃", max_bytes);
+    }
+
+    Ok(Output { status, stdout, stderr })
+}
+
+/// 有上限的读取结果：保留的内容及是否被截断
+type BoundedRead = std::io::Result<(Vec<u8>, bool)>;
+
+/// 读取全部内容但最多保留 `max_bytes` 字节
+fn read_bounded(mut reader: impl Read, max_bytes: usize) -> BoundedRead {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        let room = max_bytes.saturating_sub(kept.len());
+        if n > room {
+            truncated = true;
+        }
+        kept.extend_from_slice(&buf[..n.min(room)]);
+    }
+
+    Ok((kept, truncated))
+}
+
+/// 向 stderr 输出已等待的时长
+pub fn print_progress(started: Instant) {
+    eprintln!("⏳ 仍在等待回复... ({}s)", started.elapsed().as_secs());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config_checks_api_fields() {
+        let mut config = Config::default();
+        config.gewe_cli.mode = "api".to_string();
+        config.gewe_cli.api_base_url = "http://127.0.0.1:2531/v2/api/".to_string();
+
+        let err = from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("api_token、app_id"));
+
+        config.gewe_cli.api_token = "token".to_string();
+        config.gewe_cli.app_id = "wx_app".to_string();
+        assert!(from_config(&config).is_ok());
+
+        config.gewe_cli.mode = "grpc".to_string();
+        assert!(from_config(&config).err().unwrap().to_string().contains("未知的消息后端"));
+    }
+
+    #[test]
+    fn test_check_api_response() {
+        assert!(check_api_response(&serde_json::json!({"ret": 200, "msg": "操作成功"})).is_ok());
+
+        let err = check_api_response(&serde_json::json!({"ret": 500, "msg": "appId 无效"})).unwrap_err();
+        assert_eq!(err.to_string(), "gewe API 返回错误（ret 500）: appId 无效");
+
+        assert!(check_api_response(&serde_json::Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_wait_for_callback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let url = format!("http://{}/callback", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            let callback = |from: &str| {
+                serde_json::json!({
+                    "TypeName": "AddMsg",
+                    "Data": {"FromUserName": {"string": from}, "MsgType": 1, "Content": {"string": " 继续 "}},
+                })
+            };
+            // 其他人的消息不算回复
+            for from in ["wxid_other", "wxid_a"] {
+                let _ = http::post_json(&url, &callback(from), Duration::from_secs(5));
+            }
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let reply = runtime.block_on(wait_for_callback(listener, "wxid_a", "", 10, None)).unwrap();
+        assert_eq!(reply, "继续");
+    }
+
+    #[test]
+    fn test_wait_for_callback_verifies_signature() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let url = format!("http://{}/callback", listener.local_addr().unwrap());
+
+        let statuses = std::thread::spawn(move || {
+            let callback = |text: &str| {
+                serde_json::json!({
+                    "TypeName": "AddMsg",
+                    "Data": {"FromUserName": {"string": "wxid_a"}, "MsgType": 1, "Content": {"string": text}},
+                })
+            };
+            let post = |url: &str, body: &serde_json::Value| {
+                http::post_json(url, body, Duration::from_secs(5)).err().map(|e| e.to_string())
+            };
+
+            let timestamp = Utc::now().timestamp().to_string();
+            let signature = server::compute_signature("secret", &timestamp, "n1");
+            let signed = format!("{}?signature={}&timestamp={}&nonce=n1", url, signature, timestamp);
+            let oversized = "x".repeat(server::MAX_REPLY_BODY_BYTES + 1);
+            [
+                // 未签名、请求体过大的回调被拒绝，不会被当作回复
+                post(&url, &callback("伪造")),
+                post(&signed, &callback(&oversized)),
+                post(&signed, &callback("继续")),
+            ]
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let reply = runtime.block_on(wait_for_callback(listener, "wxid_a", "secret", 10, None)).unwrap();
+        assert_eq!(reply, "继续");
+
+        let [unsigned, oversized, signed] = statuses.join().unwrap();
+        assert!(unsigned.unwrap().contains("401"));
+        assert!(oversized.unwrap().contains("413"));
+        assert_eq!(signed, None);
+    }
+
+    #[test]
+    fn test_wait_for_callback_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let err = runtime.block_on(wait_for_callback(listener, "wxid_a", "", 1, None)).unwrap_err();
+        assert!(err.downcast_ref::<ReplyTimeout>().is_some());
+    }
+
+    #[test]
+    fn test_output_with_progress() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 0.3; echo 好的"]);
+
+        let limits = CaptureLimits {
+            progress: Some(Duration::from_millis(100)),
+            max_bytes: 1024,
+            deadline: None,
+        };
+        let output = capture_output(cmd, &limits).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "好的");
+    }
+
+    #[test]
+    fn test_capture_output_truncates() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "head -c 200000 /dev/zero; echo done >&2"]);
+
+        let limits = CaptureLimits {
+            progress: None,
+            max_bytes: 1000,
+            deadline: None,
+        };
+        let output = capture_output(cmd, &limits).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 1000);
+        assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), "done");
+    }
+
+    #[test]
+    fn test_capture_output_kills_after_deadline() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("10");
+
+        let limits = CaptureLimits {
+            progress: None,
+            max_bytes: 1024,
+            deadline: Some(Duration::from_millis(200)),
+        };
+        let started = Instant::now();
+        let err = capture_output(cmd, &limits).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
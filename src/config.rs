@@ -1,23 +1,392 @@
 use anyhow::{Context, Result};
+use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// 配置格式版本，缺失时视为 0（早期版本），加载时自动迁移到 [`CONFIG_VERSION`]
+    #[serde(default)]
+    pub version: u32,
+    /// 展示时间使用的时区（IANA 名称或固定偏移），为空时使用系统本地时区
+    #[serde(default)]
+    pub timezone: String,
+    /// Hook 处理出错时的行为，取值见 [`HOOK_FAIL_MODES`]
+    #[serde(default = "default_hook_fail_mode")]
+    pub hook_fail_mode: String,
     pub remote: RemoteConfig,
     pub notification: NotificationConfig,
     pub gewe_cli: GeweCliConfig,
+    #[serde(default)]
+    pub transcript: TranscriptConfig,
+    /// 按项目目录路由通知对象：路径前缀 -> 微信 ID
+    #[serde(default)]
+    pub routing: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// 根据工作目录选择通知对象
+    ///
+    /// 在 `[routing]` 中查找匹配 `cwd` 的路径前缀，多个前缀匹配时取最长（最具体）的；
+    /// 无匹配时使用默认的 `notification.wxid`
+    pub fn resolve_wxid_for_cwd(&self, cwd: Option<&Path>) -> &str {
+        let Some(cwd) = cwd else {
+            return &self.notification.wxid;
+        };
+
+        let cwd = canonical_path(cwd);
+        self.routing
+            .iter()
+            .map(|(prefix, wxid)| (canonical_path(Path::new(prefix)), wxid))
+            .filter(|(prefix, _)| cwd.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components().count())
+            .map(|(_, wxid)| wxid.as_str())
+            .unwrap_or(&self.notification.wxid)
+    }
+
+    /// 按 `notification.project_name_strategy` 从工作目录提取项目名，用于通知标题和 Stop 提示
+    pub fn resolve_project_name(&self, cwd: Option<&Path>) -> String {
+        cwd.map(|cwd| project_name(cwd, &self.notification.project_name_strategy, dirs::home_dir().as_deref()))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    /// 检查配置是否完整、一致，返回每一项的检查结果
+    pub fn validate(&self) -> Vec<ConfigCheck> {
+        let notification = &self.notification;
+        let channel = notification.channel.as_str();
+
+        let channel_check = if crate::format::SUPPORTED_CHANNELS.contains(&channel) {
+            Ok(())
+        } else {
+            Err(format!(
+                "未知的通知渠道「{}」，可选：{}",
+                channel,
+                crate::format::SUPPORTED_CHANNELS.join(", ")
+            ))
+        };
+
+        let recipient_check = match channel {
+            "wechat" if notification.wxid.trim().is_empty() => Err("wxid 为空".to_string()),
+            "telegram" if notification.wxid.trim().is_empty() => Err("wxid（Telegram chat_id）为空".to_string()),
+            "telegram" if notification.telegram_bot_token.trim().is_empty() => {
+                Err("telegram_bot_token 为空".to_string())
+            }
+            "dingtalk" if notification.dingtalk_webhook.trim().is_empty() => Err("dingtalk_webhook 为空".to_string()),
+            _ => Ok(()),
+        };
+
+        // 与 TcpListener::bind 一致，接受 IP:端口 或 主机名:端口（如 localhost:4399）
+        let listen_check = std::net::ToSocketAddrs::to_socket_addrs(notification.listen.as_str())
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .map(|_| ())
+            .ok_or_else(|| format!("无法解析为 主机:端口：{}", notification.listen));
+
+        let domain_check = validate_transcript_domain(&notification.transcript_domain);
+
+        let timeout_check = if self.gewe_cli.timeout > MAX_SANE_TIMEOUT_SECS {
+            Err(format!(
+                "{} 秒超过上限 {} 秒（如需无限等待请设为 0）",
+                self.gewe_cli.timeout, MAX_SANE_TIMEOUT_SECS
+            ))
+        } else {
+            Ok(())
+        };
+
+        let strategy = notification.project_name_strategy.as_str();
+        let strategy_check = if PROJECT_NAME_STRATEGIES.contains(&strategy) {
+            Ok(())
+        } else {
+            Err(format!(
+                "未知的项目名策略「{}」，可选：{}",
+                strategy,
+                PROJECT_NAME_STRATEGIES.join(", ")
+            ))
+        };
+
+        let quiet_hours_check = parse_quiet_hours(&notification.quiet_hours).map(|_| ());
+
+        let invalid_proxies: Vec<&str> = self
+            .transcript
+            .trusted_proxies
+            .iter()
+            .map(|ip| ip.trim())
+            .filter(|ip| ip.parse::<std::net::IpAddr>().is_err())
+            .collect();
+        let proxies_check = if invalid_proxies.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("不是合法的 IP 地址：{}", invalid_proxies.join(", ")))
+        };
+
+        let fail_mode_check = if HOOK_FAIL_MODES.contains(&self.hook_fail_mode.as_str()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "未知的取值「{}」，可选：{}",
+                self.hook_fail_mode,
+                HOOK_FAIL_MODES.join(", ")
+            ))
+        };
+
+        let mode = self.gewe_cli.mode.as_str();
+        let missing_api_fields = crate::backend::missing_api_fields(self);
+        let backend_check = if !crate::backend::BACKEND_MODES.contains(&mode) {
+            Err(format!(
+                "未知的消息后端「{}」，可选：{}",
+                mode,
+                crate::backend::BACKEND_MODES.join(", ")
+            ))
+        } else if mode == "api" && !missing_api_fields.is_empty() {
+            Err(format!("{} 为空", missing_api_fields.join("、")))
+        } else {
+            Ok(())
+        };
+
+        [
+            ("通知渠道", channel_check),
+            ("接收方", recipient_check),
+            ("监听地址", listen_check),
+            ("Transcript 域名", domain_check),
+            ("超时时间", timeout_check),
+            ("项目名策略", strategy_check),
+            ("免打扰时段", quiet_hours_check),
+            ("消息后端", backend_check),
+            ("Hook 失败模式", fail_mode_check),
+            ("可信代理", proxies_check),
+        ]
+        .into_iter()
+        .map(|(name, result)| ConfigCheck { name, error: result.err() })
+        .collect()
+    }
+}
+
+/// 配置检查中允许的最长等待超时（秒），更长的值多半是误填
+const MAX_SANE_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// 单项配置检查的结果
+#[derive(Debug, Clone)]
+pub struct ConfigCheck {
+    pub name: &'static str,
+    /// 未通过时的原因
+    pub error: Option<String>,
+}
+
+/// 解析免打扰时段 "HH:MM-HH:MM"，为空时返回 None
+pub fn parse_quiet_hours(range: &str) -> std::result::Result<Option<(NaiveTime, NaiveTime)>, String> {
+    let range = range.trim();
+    if range.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = || format!("格式应为 HH:MM-HH:MM（如 23:00-07:00）：{}", range);
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let parse = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| invalid());
+
+    Ok(Some((parse(start)?, parse(end)?)))
+}
+
+/// `time` 是否落在免打扰时段内
+///
+/// 开始晚于结束时视为跨零点（如 23:00-07:00 覆盖 23:00 至次日 07:00）；
+/// 开始等于结束时视为空时段。时段为空或格式错误时返回 false
+pub fn in_quiet_hours(range: &str, time: NaiveTime) -> bool {
+    match parse_quiet_hours(range) {
+        Ok(Some((start, end))) if start <= end => start <= time && time < end,
+        Ok(Some((start, end))) => time >= start || time < end,
+        _ => false,
+    }
+}
+
+/// transcript_domain 为空（不使用链接）或是带主机名的 http(s) 地址
+fn validate_transcript_domain(domain: &str) -> std::result::Result<(), String> {
+    if domain.is_empty() {
+        return Ok(());
+    }
+
+    let url = reqwest::Url::parse(domain).map_err(|e| format!("不是合法的 URL（{}）：{}", e, domain))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!("需要以 http:// 或 https:// 开头并包含域名：{}", domain));
+    }
+    if domain.ends_with('/') {
+        return Err(format!("末尾不应带 /，否则链接会出现 //：{}", domain));
+    }
+
+    Ok(())
+}
+
+/// 当前的配置格式版本
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 配置迁移步骤：`MIGRATIONS[n]` 将版本 n 的配置升级到 n + 1，返回迁移说明
+const MIGRATIONS: &[fn(&mut toml::Table) -> Vec<String>] = &[migrate_v0_to_v1];
+
+/// 版本 0 → 1：补全早期配置缺失的配置段与必填项
+fn migrate_v0_to_v1(table: &mut toml::Table) -> Vec<String> {
+    let defaults = toml::Table::try_from(Config::default()).unwrap_or_default();
+    let mut notes = Vec::new();
+    fill_missing(table, &defaults, "", &mut notes);
+    notes
+}
+
+/// 递归地将 `defaults` 中存在而 `table` 中缺失的键补为默认值
+fn fill_missing(table: &mut toml::Table, defaults: &toml::Table, prefix: &str, notes: &mut Vec<String>) {
+    for (key, default) in defaults {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match (table.get_mut(key), default) {
+            (None, _) => {
+                table.insert(key.clone(), default.clone());
+                notes.push(format!("补全 {}", path));
+            }
+            (Some(toml::Value::Table(sub)), toml::Value::Table(sub_defaults)) => {
+                fill_missing(sub, sub_defaults, &path, notes);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 本进程是否已提示过旧版本配置，避免每次加载都重复输出
+static MIGRATION_NOTICE_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// 一次配置迁移的结果
+#[derive(Debug)]
+pub struct Migration {
+    /// 迁移前的配置版本
+    pub from_version: i64,
+    /// 迁移说明
+    pub notes: Vec<String>,
+}
+
+/// 将配置迁移到当前版本，返回迁移说明；已是当前版本时返回空列表
+///
+/// 版本高于当前程序时不做修改
+fn migrate(table: &mut toml::Table) -> Vec<String> {
+    let version = table
+        .get("version")
+        .and_then(|v| v.as_integer())
+        .unwrap_or(0)
+        .max(0) as usize;
+
+    let mut notes = Vec::new();
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version) {
+        for note in step(table) {
+            notes.push(format!("v{} → v{}: {}", from, from + 1, note));
+        }
+    }
+
+    if version < MIGRATIONS.len() {
+        table.insert("version".to_string(), toml::Value::Integer(CONFIG_VERSION as i64));
+    }
+    notes
+}
+
+/// 规范化路径，路径不存在而无法规范化时按原样返回
+pub fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Hook 处理出错时的行为
+///
+/// * `open` - 放行（默认）
+/// * `closed` - 阻止并返回错误原因（Stop Hook 除外，见 [`crate::hook::HookDecision::on_error`]）
+pub const HOOK_FAIL_MODES: &[&str] = &["open", "closed"];
+
+/// 项目名提取策略
+///
+/// * `dir_name` - 工作目录的目录名（默认）
+/// * `git_repo` - 向上查找 `.git` 所在的仓库根目录，取其目录名；不在仓库中时退回目录名
+/// * `full_path` - 缩短后的绝对路径（见 [`shorten_path`]）
+pub const PROJECT_NAME_STRATEGIES: &[&str] = &["dir_name", "git_repo", "full_path"];
+
+/// `full_path` 策略最多保留的路径层数
+const MAX_PROJECT_PATH_COMPONENTS: usize = 3;
+
+/// 按策略从工作目录提取项目名，未知策略按 `dir_name` 处理
+fn project_name(cwd: &Path, strategy: &str, home: Option<&Path>) -> String {
+    let dir_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().to_string());
+
+    let name = match strategy {
+        "git_repo" => cwd
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .and_then(dir_name)
+            .or_else(|| dir_name(cwd)),
+        "full_path" => Some(shorten_path(cwd, home)),
+        _ => dir_name(cwd),
+    };
+
+    name.unwrap_or_else(|| "unknown".to_string())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 缩短绝对路径：home 目录替换为 `~`，层数过多时只保留最后几层
+fn shorten_path(path: &Path, home: Option<&Path>) -> String {
+    let (prefix, rest) = match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => ("~", rest),
+        None => ("", path),
+    };
+
+    let parts: Vec<String> = rest
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    if parts.len() > MAX_PROJECT_PATH_COMPONENTS {
+        let tail = &parts[parts.len() - MAX_PROJECT_PATH_COMPONENTS..];
+        return format!("{}/{}", crate::util::ELLIPSIS, tail.join("/"));
+    }
+
+    match (prefix, parts.is_empty()) {
+        ("~", true) => "~".to_string(),
+        ("~", false) => format!("~/{}", parts.join("/")),
+        _ => format!("/{}", parts.join("/")),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoteConfig {
     /// 全局远程模式开关
     pub enabled: bool,
+
+    /// 项目目录白名单（路径前缀），非空时仅对其下的任务启用远程模式
+    #[serde(default)]
+    pub project_allowlist: Vec<String>,
+
+    /// lock 文件有效期（秒，0 表示永不过期），过期的 lock 视为远程模式已关闭
+    #[serde(default)]
+    pub lock_ttl_secs: u64,
+
+    /// 远程控制命令前缀，如默认的 `>remote-` 对应 `>remote-on`
+    #[serde(default = "default_command_prefix")]
+    pub command_prefix: String,
+
+    /// 是否允许在会话中通过 `>remote-*` 命令切换远程模式，关闭后只能通过 CLI 切换
+    #[serde(default = "default_allow_inline_commands")]
+    pub allow_inline_commands: bool,
+
+    /// 被关闭远程模式的会话记录保留天数（0 表示永久保留），过期记录在关闭其他会话时清理
+    #[serde(default = "default_disabled_session_ttl_days")]
+    pub disabled_session_ttl_days: u64,
+
+    /// 会话注册表（sessions.json）最多保留的会话数（0 表示不限制），超出时淘汰最早注册的会话
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationConfig {
     /// 通知渠道：wechat, telegram, dingtalk 等
     pub channel: String,
@@ -28,9 +397,192 @@ pub struct NotificationConfig {
     /// 监听地址
     pub listen: String,
 
+    /// 仅接收抄送的微信 ID（收到 send-link 卡片，但不会被等待回复）
+    #[serde(default)]
+    pub info_wxids: Vec<String>,
+
+    /// 向多个接收方发送时的最大并发数，避免触发接口限流
+    #[serde(default = "default_send_concurrency")]
+    pub send_concurrency: usize,
+
+    /// 任务失败（send-link --status failed）时在链接卡片前额外发送一条文本提醒
+    #[serde(default)]
+    pub failure_text_alert: bool,
+
+    /// 在 send-link 消息上附带「继续 / 停止 / 查看」快捷回复按钮（仅 telegram 渠道支持）
+    #[serde(default)]
+    pub quick_replies: bool,
+
     /// Transcript 展示域名
     #[serde(default)]
     pub transcript_domain: String,
+
+    /// HTTP 渠道（telegram、dingtalk）请求的超时时间（秒，0 表示不限制）
+    #[serde(default = "default_http_timeout_secs")]
+    pub http_timeout_secs: u64,
+
+    /// Telegram Bot Token（channel 为 telegram 时使用，wxid 填写 chat_id）
+    #[serde(default)]
+    pub telegram_bot_token: String,
+
+    /// 钉钉机器人 Webhook 地址（channel 为 dingtalk 时使用）
+    #[serde(default)]
+    pub dingtalk_webhook: String,
+
+    /// 未配置 transcript_domain 时，send-link 是否改为发送纯文本通知（否则报错）
+    #[serde(default)]
+    pub allow_linkless_cards: bool,
+
+    /// 纯文本通知（如会话空闲提醒）是否附带 transcript 链接（需配置 transcript_domain）
+    #[serde(default)]
+    pub include_url_in_text: bool,
+
+    /// 回复接收方式：cli（由 gewe-cli 启动 webhook 等待）或 server（由 serve 进程的 /webhook/reply 接收）
+    #[serde(default = "default_reply_mode")]
+    pub reply_mode: String,
+
+    /// 回复 webhook 签名校验 token（reply_mode 为 server 时使用，为空时不校验）
+    #[serde(default)]
+    pub webhook_token: String,
+
+    /// 是否去掉回复中微信引用带来的多余内容（引用块、分隔线）
+    #[serde(default = "default_normalize_replies")]
+    pub normalize_replies: bool,
+
+    /// 自动摘要（send-link --auto-summary）的最大字符数
+    #[serde(default = "default_summary_max_chars")]
+    pub summary_max_chars: usize,
+
+    /// 通知消息超过该字符数时向 stderr 输出警告（0 表示不检查），
+    /// 过长的消息在微信端可能被截断
+    #[serde(default = "default_warn_message_len")]
+    pub warn_message_len: usize,
+
+    /// 项目名提取策略（dir_name / git_repo / full_path），见 [`PROJECT_NAME_STRATEGIES`]
+    #[serde(default = "default_project_name_strategy")]
+    pub project_name_strategy: String,
+
+    /// 通过远程控制命令（如 >remote-on / >remote-off）切换远程模式时是否发送微信确认通知
+    #[serde(default)]
+    pub notify_on_toggle: bool,
+
+    /// 免打扰时段（按 `timezone` 计算，如 "23:00-07:00"，可跨零点），为空时不启用
+    ///
+    /// 时段内跳过不需要回复的通知（挂起提醒、远程模式切换通知、notify 等）
+    #[serde(default)]
+    pub quiet_hours: String,
+
+    /// 免打扰时段内是否仍发送需要回复的消息（send-link、wait-reply 等）
+    #[serde(default = "default_quiet_hours_allow_urgent")]
+    pub quiet_hours_allow_urgent: bool,
+
+    /// send-link 等待回复期间向 stderr 输出进度的间隔（秒，0 表示不输出）
+    #[serde(default = "default_progress_interval_secs")]
+    pub progress_interval_secs: u64,
+
+    /// 等待回复超时后的行为：abort 报错退出（默认），continue 返回 `default_reply_on_timeout`
+    #[serde(default = "default_on_timeout")]
+    pub on_timeout: String,
+
+    /// on_timeout 为 continue 时，超时后代替回复返回的内容
+    #[serde(default = "default_reply_on_timeout")]
+    pub default_reply_on_timeout: String,
+
+    /// send-link 未收到回复时重发提醒的间隔（秒，0 表示不提醒），总超时仍按 timeout 计算
+    #[serde(default)]
+    pub reminder_interval_secs: u64,
+
+    /// 最多重发提醒的次数
+    #[serde(default = "default_max_reminders")]
+    pub max_reminders: u32,
+
+    /// 收到回复后在后台执行的 shell 命令（回复写入 stdin，并提供 GEWE_CC_SESSION_ID / GEWE_CC_REPLY 环境变量，不等待其结束）
+    #[serde(default)]
+    pub on_reply_command: String,
+
+    /// 每个会话保留的回复历史条数（0 表示不限制），超出时丢弃最早的记录
+    #[serde(default = "default_max_reply_history")]
+    pub max_reply_history: usize,
+
+    /// confirm 命令视为「确认」的回复
+    #[serde(default = "default_confirm_yes")]
+    pub confirm_yes: Vec<String>,
+
+    /// confirm 命令视为「拒绝」的回复
+    #[serde(default = "default_confirm_no")]
+    pub confirm_no: Vec<String>,
+
+    /// 表情回复到命令文本的映射：整条回复恰好是某个表情时替换为对应文本（如 👍 → 继续）
+    #[serde(default = "default_emoji_commands")]
+    pub emoji_commands: BTreeMap<String, String>,
+}
+
+fn default_confirm_yes() -> Vec<String> {
+    ["Y", "y", "是"].iter().map(|s| s.to_string()).collect()
+}
+
+/// 默认表情映射；微信自带表情以 `[强]` 这样的文字发送
+fn default_emoji_commands() -> BTreeMap<String, String> {
+    [("👍", "继续"), ("[强]", "继续"), ("🛑", "停止")]
+        .into_iter()
+        .map(|(emoji, command)| (emoji.to_string(), command.to_string()))
+        .collect()
+}
+
+fn default_confirm_no() -> Vec<String> {
+    ["N", "n", "否"].iter().map(|s| s.to_string()).collect()
+}
+
+fn default_on_timeout() -> String {
+    "abort".to_string()
+}
+
+fn default_reply_on_timeout() -> String {
+    "继续".to_string()
+}
+
+fn default_progress_interval_secs() -> u64 {
+    30
+}
+
+fn default_http_timeout_secs() -> u64 {
+    10
+}
+
+fn default_quiet_hours_allow_urgent() -> bool {
+    true
+}
+
+fn default_project_name_strategy() -> String {
+    "dir_name".to_string()
+}
+
+fn default_summary_max_chars() -> usize {
+    120
+}
+
+fn default_warn_message_len() -> usize {
+    1000
+}
+
+fn default_max_reply_history() -> usize {
+    100
+}
+
+fn default_send_concurrency() -> usize {
+    3
+}
+
+fn default_max_reminders() -> u32 {
+    3
+}
+
+fn default_normalize_replies() -> bool {
+    true
+}
+
+fn default_reply_mode() -> String {
+    "cli".to_string()
 }
 
 impl Default for NotificationConfig {
@@ -39,13 +591,45 @@ impl Default for NotificationConfig {
             channel: "wechat".to_string(),
             wxid: String::new(),
             listen: String::new(),
+            info_wxids: Vec::new(),
+            send_concurrency: default_send_concurrency(),
+            failure_text_alert: false,
+            quick_replies: false,
             transcript_domain: String::new(),
+            http_timeout_secs: default_http_timeout_secs(),
+            telegram_bot_token: String::new(),
+            dingtalk_webhook: String::new(),
+            allow_linkless_cards: false,
+            include_url_in_text: false,
+            reply_mode: default_reply_mode(),
+            webhook_token: String::new(),
+            normalize_replies: default_normalize_replies(),
+            summary_max_chars: default_summary_max_chars(),
+            warn_message_len: default_warn_message_len(),
+            project_name_strategy: default_project_name_strategy(),
+            notify_on_toggle: false,
+            quiet_hours: String::new(),
+            quiet_hours_allow_urgent: default_quiet_hours_allow_urgent(),
+            progress_interval_secs: default_progress_interval_secs(),
+            on_timeout: default_on_timeout(),
+            default_reply_on_timeout: default_reply_on_timeout(),
+            reminder_interval_secs: 0,
+            max_reminders: default_max_reminders(),
+            on_reply_command: String::new(),
+            max_reply_history: default_max_reply_history(),
+            confirm_yes: default_confirm_yes(),
+            confirm_no: default_confirm_no(),
+            emoji_commands: default_emoji_commands(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeweCliConfig {
+    /// 消息后端：cli（调用 gewe-cli，默认）/ api（直接请求 gewe HTTP API），见 [`crate::backend`]
+    #[serde(default = "default_gewe_mode")]
+    pub mode: String,
+
     /// gewe-cli 命令路径
     #[serde(default = "default_gewe_cli_command")]
     pub command: String,
@@ -53,12 +637,207 @@ pub struct GeweCliConfig {
     /// 超时设置（秒）
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+
+    /// 采集 gewe-cli 输出时 stdout / stderr 各自保留的最大字节数，超出部分丢弃
+    #[serde(default = "default_max_capture_bytes")]
+    pub max_capture_bytes: usize,
+
+    /// gewe API 地址（mode = "api" 时使用）
+    #[serde(default)]
+    pub api_base_url: String,
+
+    /// gewe API token，通过 X-GEWE-TOKEN 请求头传递
+    #[serde(default)]
+    pub api_token: String,
+
+    /// gewe API 的设备 appId
+    #[serde(default)]
+    pub app_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptConfig {
+    /// 页面标题
+    #[serde(default = "default_page_title")]
+    pub page_title: String,
+
+    /// 页眉 Logo 图片地址（为空时显示默认图标）
+    #[serde(default)]
+    pub logo_url: String,
+
+    /// 页面顶部的提示横幅（如"内部资料，请勿外传"），为空时不显示
+    #[serde(default)]
+    pub page_banner: String,
+
+    /// 额外展示的非消息条目类型（如 summary、system）
+    #[serde(default)]
+    pub entry_types: Vec<String>,
+
+    /// 消息顺序：asc（最早的在前）或 desc（最新的在前）
+    #[serde(default = "default_message_order")]
+    pub message_order: String,
+
+    /// 前端资源加载方式：cdn（默认）、local（从 ~/.gewe-cc/assets/vendor/ 加载）或 inline（自包含）
+    #[serde(default = "default_asset_mode")]
+    pub asset_mode: String,
+
+    /// 代码高亮主题（highlight.js 主题名，如 github-dark、monokai、atom-one-dark）
+    #[serde(default = "default_code_theme")]
+    pub code_theme: String,
+
+    /// 是否压缩输出的 HTML（去除标签间缩进与多余空白，保留代码块内容）
+    #[serde(default)]
+    pub minify_html: bool,
+
+    /// 是否把连续重复的消息（角色与内容相同，忽略空白差异）合并为一条并标注 ×N
+    #[serde(default)]
+    pub dedupe_messages: bool,
+
+    /// 是否展示思考过程，可用请求参数 `?thinking=0` 临时隐藏
+    #[serde(default = "default_show_thinking")]
+    pub show_thinking: bool,
+
+    /// 允许渲染的内容块类型（text / thinking / tool_use / tool_result / image），未列出的类型一律省略
+    #[serde(default = "default_render_blocks")]
+    pub render_blocks: Vec<String>,
+
+    /// 是否展示系统提示（system-reminder、Hook 输出及未识别的内容块），默认隐藏
+    #[serde(default)]
+    pub show_system: bool,
+
+    /// 用户消息显示的角色名（为空时显示 user）
+    #[serde(default)]
+    pub user_label: String,
+
+    /// 助手消息显示的角色名（为空时显示 assistant）
+    #[serde(default)]
+    pub assistant_label: String,
+
+    /// 用户消息的强调色（CSS 颜色，为空时使用默认蓝色）
+    #[serde(default)]
+    pub user_color: String,
+
+    /// 助手消息的强调色（CSS 颜色，为空时使用默认紫色）
+    #[serde(default)]
+    pub assistant_color: String,
+
+    /// 会话页面每个客户端 IP 每分钟允许的请求数（0 表示不限流）
+    #[serde(default = "default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+
+    /// 可信反向代理的 IP，对端为这些地址（或本机）时才按 X-Forwarded-For 识别客户端
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+
+    /// 会话 ID 仅允许 UUID 形式（十六进制与 -）；关闭后也允许字母、数字与 _，但始终拒绝 / 和 .
+    #[serde(default = "default_strict_session_ids")]
+    pub strict_session_ids: bool,
+
+    /// 页面自动刷新间隔（秒，0 表示不刷新）
+    ///
+    /// 仅由请求参数 `?refresh=N` 按需开启，不写入配置文件
+    #[serde(skip)]
+    pub refresh_secs: u64,
+
+    /// 实时模式：通过事件流在 transcript 更新时自动刷新页面
+    ///
+    /// 仅由请求参数 `?live=1` 按需开启，不写入配置文件
+    #[serde(skip)]
+    pub live: bool,
+}
+
+fn default_asset_mode() -> String {
+    "cdn".to_string()
+}
+
+fn default_render_blocks() -> Vec<String> {
+    crate::transcript::RENDER_BLOCK_TYPES.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_show_thinking() -> bool {
+    true
+}
+
+fn default_strict_session_ids() -> bool {
+    true
+}
+
+fn default_rate_limit_per_minute() -> u32 {
+    60
+}
+
+fn default_code_theme() -> String {
+    crate::transcript::DEFAULT_CODE_THEME.to_string()
+}
+
+fn default_message_order() -> String {
+    "asc".to_string()
+}
+
+fn default_page_title() -> String {
+    "会话记录".to_string()
+}
+
+impl Default for TranscriptConfig {
+    fn default() -> Self {
+        Self {
+            page_title: default_page_title(),
+            logo_url: String::new(),
+            page_banner: String::new(),
+            entry_types: Vec::new(),
+            message_order: default_message_order(),
+            asset_mode: default_asset_mode(),
+            code_theme: default_code_theme(),
+            minify_html: false,
+            dedupe_messages: false,
+            show_thinking: default_show_thinking(),
+            render_blocks: default_render_blocks(),
+            show_system: false,
+            user_label: String::new(),
+            assistant_label: String::new(),
+            user_color: String::new(),
+            assistant_color: String::new(),
+            rate_limit_per_minute: default_rate_limit_per_minute(),
+            trusted_proxies: Vec::new(),
+            strict_session_ids: default_strict_session_ids(),
+            refresh_secs: 0,
+            live: false,
+        }
+    }
+}
+
+fn default_gewe_mode() -> String {
+    "cli".to_string()
 }
 
 fn default_gewe_cli_command() -> String {
     "gewe-cli".to_string()
 }
 
+fn default_disabled_session_ttl_days() -> u64 {
+    30
+}
+
+fn default_max_sessions() -> usize {
+    500
+}
+
+pub fn default_command_prefix() -> String {
+    ">remote-".to_string()
+}
+
+fn default_allow_inline_commands() -> bool {
+    true
+}
+
+fn default_hook_fail_mode() -> String {
+    "open".to_string()
+}
+
+fn default_max_capture_bytes() -> usize {
+    1024 * 1024
+}
+
 fn default_timeout() -> u64 {
     0  // 0 表示无限等待（不传 --timeout 给 gewe-cli）
 }
@@ -66,37 +845,116 @@ fn default_timeout() -> u64 {
 impl Default for Config {
     fn default() -> Self {
         Self {
-            remote: RemoteConfig { enabled: false },
+            version: CONFIG_VERSION,
+            timezone: String::new(),
+            hook_fail_mode: default_hook_fail_mode(),
+            remote: RemoteConfig {
+                enabled: false,
+                project_allowlist: Vec::new(),
+                lock_ttl_secs: 0,
+                command_prefix: default_command_prefix(),
+                allow_inline_commands: default_allow_inline_commands(),
+                disabled_session_ttl_days: default_disabled_session_ttl_days(),
+                max_sessions: default_max_sessions(),
+            },
             notification: NotificationConfig::default(),
             gewe_cli: GeweCliConfig {
+                mode: default_gewe_mode(),
                 command: default_gewe_cli_command(),
                 timeout: default_timeout(),
+                max_capture_bytes: default_max_capture_bytes(),
+                api_base_url: String::new(),
+                api_token: String::new(),
+                app_id: String::new(),
             },
+            transcript: TranscriptConfig::default(),
+            routing: BTreeMap::new(),
         }
     }
 }
 
+/// 可通过 `config --unset` 重置为默认值的配置项
+pub const UNSETTABLE_FIELDS: &[&str] = &["transcript_domain", "timeout"];
+
 pub struct ConfigManager {
     config_dir: PathBuf,
     config_file: PathBuf,
     lock_file: PathBuf,
 }
 
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 获取当前用户的 home 目录
+///
+/// 容器、cron 等精简环境可能未设置 HOME，此时返回可操作的错误而不是 panic
+pub fn home_dir() -> Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| anyhow::anyhow!("无法确定 home 目录，请设置 HOME 环境变量"))
+}
+
+/// gewe-cc 的配置目录
+///
+/// 见 [`resolve_config_dir`]
+pub fn config_dir_path() -> Result<PathBuf> {
+    let xdg_config_home = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from);
+    Ok(resolve_config_dir(&home_dir()?, xdg_config_home.as_deref(), cfg!(target_os = "linux")))
+}
+
+/// 解析配置目录
+///
+/// 已存在的 `~/.gewe-cc` 始终优先，老用户无需迁移；否则 Linux 上遵循 XDG 规范使用
+/// `$XDG_CONFIG_HOME/gewe-cc`（未设置、为空或不是绝对路径时为 `~/.config/gewe-cc`），
+/// 其他系统使用 `~/.gewe-cc`
+fn resolve_config_dir(home: &Path, xdg_config_home: Option<&Path>, linux: bool) -> PathBuf {
+    let legacy = home.join(".gewe-cc");
+    if !linux || legacy.exists() {
+        return legacy;
+    }
+
+    xdg_config_home
+        .filter(|dir| dir.is_absolute())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| home.join(".config"))
+        .join("gewe-cc")
+}
+
 impl ConfigManager {
     pub fn new() -> Result<Self> {
-        let config_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("无法获取 home 目录"))?
-            .join(".gewe-cc");
+        Ok(Self::with_dir(config_dir_path()?))
+    }
 
-        Ok(Self {
+    pub(crate) fn with_dir(config_dir: PathBuf) -> Self {
+        Self {
             config_file: config_dir.join("config.toml"),
             lock_file: config_dir.join("remote.lock"),
             config_dir,
-        })
+        }
     }
 
     /// 加载配置
+    ///
+    /// 旧版本配置在内存中迁移，并向 stderr 输出迁移说明（每个进程一次）；
+    /// 迁移结果在运行 `gewe-cc config --migrate` 或下一次 [`Self::save`] 时写回文件
     pub fn load(&self) -> Result<Config> {
+        let (table, migration) = self.read_migrated()?;
+        if migration.from_version < CONFIG_VERSION as i64 && !MIGRATION_NOTICE_SHOWN.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "ℹ️ 配置文件为旧版本 ({})，已按版本 {} 加载，运行 gewe-cc config --migrate 可写回：",
+                migration.from_version, CONFIG_VERSION
+            );
+            for note in &migration.notes {
+                eprintln!("   - {}", note);
+            }
+        }
+        table.try_into().context("解析配置文件失败")
+    }
+
+    /// 读取配置文件并迁移到当前版本，返回迁移后的内容、原始版本与迁移说明
+    fn read_migrated(&self) -> Result<(toml::Table, Migration)> {
         if !self.config_file.exists() {
             anyhow::bail!(
                 "配置文件不存在: {}\n请先运行: gewe-cc init",
@@ -107,24 +965,95 @@ impl ConfigManager {
         let content = fs::read_to_string(&self.config_file)
             .context("读取配置文件失败")?;
 
-        toml::from_str(&content).context("解析配置文件失败")
+        let mut table: toml::Table = toml::from_str(&content).context("解析配置文件失败")?;
+        let from_version = table.get("version").and_then(|v| v.as_integer()).unwrap_or(0);
+        if from_version > CONFIG_VERSION as i64 {
+            eprintln!(
+                "⚠️ 配置文件版本 ({}) 高于当前程序支持的版本 ({})，请升级 gewe-cc",
+                from_version, CONFIG_VERSION
+            );
+        }
+
+        let notes = migrate(&mut table);
+        Ok((table, Migration { from_version, notes }))
+    }
+
+    /// 配置文件是否为需要迁移的旧版本
+    pub fn needs_migration(&self) -> Result<bool> {
+        let (_, migration) = self.read_migrated()?;
+        Ok(migration.from_version < CONFIG_VERSION as i64)
+    }
+
+    /// 将旧版本配置迁移后写回文件，已是当前版本时返回 None
+    ///
+    /// 写回前把原文件备份为 `config.toml.bak`（迁移会丢失注释），
+    /// 新内容先写入同目录的临时文件再重命名，避免写入中断时留下不完整的配置
+    pub fn migrate_file(&self) -> Result<Option<Migration>> {
+        let (table, migration) = self.read_migrated()?;
+        if migration.from_version >= CONFIG_VERSION as i64 {
+            return Ok(None);
+        }
+
+        // 确认迁移结果能被正确解析后再写回
+        let _: Config = table.clone().try_into().context("解析迁移后的配置失败")?;
+        let content = toml::to_string_pretty(&table).context("序列化配置失败")?;
+
+        fs::copy(&self.config_file, self.backup_file()).context("备份配置文件失败")?;
+        self.write_config_file(&content).context("写入迁移后的配置失败")?;
+
+        Ok(Some(migration))
+    }
+
+    /// 先写入同目录的临时文件再重命名为配置文件，避免写入中断时留下不完整的配置
+    fn write_config_file(&self, content: &str) -> Result<()> {
+        let mut tmp_file = tempfile::NamedTempFile::new_in(&self.config_dir).context("创建临时配置文件失败")?;
+        tmp_file.write_all(content.as_bytes()).context("写入临时配置文件失败")?;
+        tmp_file.persist(&self.config_file).context("写入配置文件失败")?;
+        Ok(())
+    }
+
+    /// 配置文件中记录的版本，文件不存在或无法解析时为 None
+    fn stored_version(&self) -> Option<i64> {
+        let content = fs::read_to_string(&self.config_file).ok()?;
+        let table: toml::Table = toml::from_str(&content).ok()?;
+        Some(table.get("version").and_then(|v| v.as_integer()).unwrap_or(0))
+    }
+
+    /// 迁移前的配置备份路径
+    pub fn backup_file(&self) -> PathBuf {
+        self.config_dir.join("config.toml.bak")
     }
 
     /// 保存配置
+    ///
+    /// 保存会把旧版本配置升级到 `config.version`：与 [`Self::migrate_file`] 相同，
+    /// 写回前先把原文件备份为 `config.toml.bak`
     pub fn save(&self, config: &Config) -> Result<()> {
         fs::create_dir_all(&self.config_dir).context("创建配置目录失败")?;
 
         let content = toml::to_string_pretty(config).context("序列化配置失败")?;
 
-        fs::write(&self.config_file, content).context("写入配置文件失败")?;
-
-        Ok(())
+        if let Some(from_version) = self.stored_version().filter(|v| *v < config.version as i64) {
+            fs::copy(&self.config_file, self.backup_file()).context("备份配置文件失败")?;
+            eprintln!(
+                "🔧 配置文件已从版本 {} 升级到 {}，原配置已备份到: {}",
+                from_version,
+                config.version,
+                self.backup_file().display()
+            );
+        }
+        self.write_config_file(&content)
     }
 
     /// 检查远程模式是否启用
     pub fn is_remote_enabled(&self) -> bool {
         // 优先检查 lock 文件
         if self.lock_file.exists() {
+            if self.is_lock_stale() {
+                // 异常退出遗留的过期 lock，清理后视为已关闭
+                let _ = self.disable_remote();
+                return false;
+            }
             return true;
         }
 
@@ -137,37 +1066,77 @@ impl ConfigManager {
     }
 
     /// 启用远程模式
-    pub fn enable_remote(&self) -> Result<()> {
+    ///
+    /// 已处于启用状态时不重写配置文件，返回是否实际发生了变更
+    pub fn enable_remote(&self) -> Result<bool> {
+        let mut changed = false;
+
         // 1. 更新配置文件
         let mut config = self.load().unwrap_or_default();
-        config.remote.enabled = true;
-        self.save(&config)?;
+        if !config.remote.enabled {
+            config.remote.enabled = true;
+            self.save(&config)?;
+            changed = true;
+        }
 
-        // 2. 创建 lock 文件
-        fs::write(&self.lock_file, "").context("创建 lock 文件失败")?;
+        // 2. 创建 lock 文件（写入启用时间，用于判断是否过期）
+        if !self.lock_file.exists() || self.is_lock_stale() {
+            fs::write(&self.lock_file, unix_now().to_string()).context("创建 lock 文件失败")?;
+            changed = true;
+        }
 
-        Ok(())
+        Ok(changed)
+    }
+
+    /// lock 文件是否已超过 `remote.lock_ttl_secs` 有效期
+    ///
+    /// lock 中记录启用时的 Unix 时间戳；旧版本写入的空 lock 以文件修改时间为准
+    fn is_lock_stale(&self) -> bool {
+        let ttl = self.load().map(|c| c.remote.lock_ttl_secs).unwrap_or(0);
+        if ttl == 0 {
+            return false;
+        }
+
+        let created = fs::read_to_string(&self.lock_file)
+            .ok()
+            .and_then(|content| content.trim().parse::<u64>().ok())
+            .or_else(|| {
+                let modified = fs::metadata(&self.lock_file).ok()?.modified().ok()?;
+                Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+            });
+
+        match created {
+            Some(created) => unix_now().saturating_sub(created) > ttl,
+            None => false,
+        }
     }
 
     /// 禁用远程模式
-    pub fn disable_remote(&self) -> Result<()> {
+    ///
+    /// 已处于禁用状态时不重写配置文件，返回是否实际发生了变更
+    pub fn disable_remote(&self) -> Result<bool> {
+        let mut changed = false;
+
         // 1. 更新配置文件
-        if let Ok(mut config) = self.load() {
+        if let Ok(mut config) = self.load()
+            && config.remote.enabled
+        {
             config.remote.enabled = false;
             self.save(&config)?;
+            changed = true;
         }
 
         // 2. 删除 lock 文件
         if self.lock_file.exists() {
             fs::remove_file(&self.lock_file).context("删除 lock 文件失败")?;
+            changed = true;
         }
 
-        Ok(())
+        Ok(changed)
     }
 
     /// 获取配置目录路径
     /// 用于 config 命令和其他需要显示配置位置的场景
-    #[allow(dead_code)]
     pub fn config_dir(&self) -> &Path {
         &self.config_dir
     }
@@ -177,6 +1146,11 @@ impl ConfigManager {
         &self.config_file
     }
 
+    /// 远程模式标记文件路径
+    pub fn lock_file(&self) -> &Path {
+        &self.lock_file
+    }
+
     /// 更新微信配置
     pub fn update_notification(&self, wxid: Option<String>, listen: Option<String>, transcript_domain: Option<String>) -> Result<()> {
         let mut config = self.load()?;
@@ -197,6 +1171,26 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// 将可选配置项重置为默认值
+    ///
+    /// 支持的字段见 [`UNSETTABLE_FIELDS`]，未知字段返回错误
+    pub fn unset_field(&self, field: &str) -> Result<()> {
+        let mut config = self.load()?;
+
+        match field {
+            "transcript_domain" => config.notification.transcript_domain = String::new(),
+            "timeout" => config.gewe_cli.timeout = default_timeout(),
+            _ => anyhow::bail!(
+                "未知的配置项: {}\n可重置的配置项: {}",
+                field,
+                UNSETTABLE_FIELDS.join(", ")
+            ),
+        }
+
+        self.save(&config)?;
+        Ok(())
+    }
+
     /// 禁用指定会话的远程模式（仅该会话）
     pub fn disable_session(&self, session_id: &str) -> Result<()> {
         if session_id.trim().is_empty() {
@@ -204,19 +1198,24 @@ impl ConfigManager {
         }
 
         let mut sessions = self.load_disabled_sessions()?;
-        sessions.insert(session_id.to_string());
+        let ttl_days = self
+            .load()
+            .map(|config| config.remote.disabled_session_ttl_days)
+            .unwrap_or_else(|_| default_disabled_session_ttl_days());
+        prune_disabled_sessions(&mut sessions, ttl_days, unix_now());
+        sessions.insert(session_id.to_string(), unix_now());
         self.save_disabled_sessions(&sessions)?;
         Ok(())
     }
 
-    /// 判断会话是否已被禁用
+    /// 判断会话是否已被禁用（只读取禁用列表，不写文件）
     pub fn is_session_disabled(&self, session_id: &str) -> bool {
         if session_id.trim().is_empty() {
             return false;
         }
 
         self.load_disabled_sessions()
-            .map(|sessions| sessions.contains(session_id))
+            .map(|sessions| sessions.contains_key(session_id))
             .unwrap_or(false)
     }
 
@@ -224,20 +1223,23 @@ impl ConfigManager {
         self.config_dir.join("session_disabled.json")
     }
 
-    fn load_disabled_sessions(&self) -> Result<HashSet<String>> {
+    /// 加载被禁用的会话（会话 ID → 禁用时间）
+    ///
+    /// 旧版本以会话 ID 数组保存，加载时在内存中迁移为映射并以当前时间作为禁用时间；
+    /// 迁移结果与过期清理在下次 [`Self::disable_session`] 时写回
+    fn load_disabled_sessions(&self) -> Result<HashMap<String, u64>> {
         let file_path = self.session_disabled_file();
         if !file_path.exists() {
-            return Ok(HashSet::new());
+            return Ok(HashMap::new());
         }
 
         let content = fs::read_to_string(&file_path)
             .context("读取会话禁用列表失败")?;
-        let sessions: HashSet<String> = serde_json::from_str(&content)
-            .unwrap_or_default();
+        let (sessions, _) = parse_disabled_sessions(&content, unix_now());
         Ok(sessions)
     }
 
-    fn save_disabled_sessions(&self, sessions: &HashSet<String>) -> Result<()> {
+    fn save_disabled_sessions(&self, sessions: &HashMap<String, u64>) -> Result<()> {
         fs::create_dir_all(&self.config_dir)
             .context("创建配置目录失败")?;
         let content = serde_json::to_string_pretty(sessions)
@@ -248,10 +1250,183 @@ impl ConfigManager {
     }
 }
 
+/// 解析会话禁用列表，返回 (会话 ID → 禁用时间, 是否由旧格式迁移)
+fn parse_disabled_sessions(content: &str, now: u64) -> (HashMap<String, u64>, bool) {
+    if let Ok(sessions) = serde_json::from_str::<HashMap<String, u64>>(content) {
+        return (sessions, false);
+    }
+
+    match serde_json::from_str::<Vec<String>>(content) {
+        Ok(ids) => (ids.into_iter().map(|id| (id, now)).collect(), true),
+        Err(_) => (HashMap::new(), false),
+    }
+}
+
+/// 删除禁用时间早于 `ttl_days` 天前的记录，返回是否有记录被删除
+fn prune_disabled_sessions(sessions: &mut HashMap<String, u64>, ttl_days: u64, now: u64) -> bool {
+    if ttl_days == 0 {
+        return false;
+    }
+
+    let before = sessions.len();
+    let cutoff = now.saturating_sub(ttl_days.saturating_mul(24 * 60 * 60));
+    sessions.retain(|_, disabled_at| *disabled_at >= cutoff);
+    sessions.len() != before
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_config_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path();
+        let xdg = home.join("xdg");
+
+        // 非 Linux 始终使用 ~/.gewe-cc
+        assert_eq!(resolve_config_dir(home, Some(&xdg), false), home.join(".gewe-cc"));
+
+        // Linux 优先使用 XDG_CONFIG_HOME，未设置或为相对路径时使用 ~/.config
+        assert_eq!(resolve_config_dir(home, Some(&xdg), true), xdg.join("gewe-cc"));
+        assert_eq!(resolve_config_dir(home, None, true), home.join(".config/gewe-cc"));
+        assert_eq!(resolve_config_dir(home, Some(Path::new("rel")), true), home.join(".config/gewe-cc"));
+
+        // 已存在的 ~/.gewe-cc 优先
+        fs::create_dir(home.join(".gewe-cc")).unwrap();
+        assert_eq!(resolve_config_dir(home, Some(&xdg), true), home.join(".gewe-cc"));
+    }
+
+    #[test]
+    fn test_disabled_sessions_migration_and_ttl() {
+        let now = 100 * 24 * 60 * 60;
+
+        // 旧格式：会话 ID 数组
+        let (sessions, migrated) = parse_disabled_sessions(r#"["a", "b"]"#, now);
+        assert!(migrated);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions["a"], now);
+
+        let (mut sessions, migrated) = parse_disabled_sessions(r#"{"old": 0, "new": 8553600}"#, now);
+        assert!(!migrated);
+        assert!(prune_disabled_sessions(&mut sessions, 30, now));
+        assert!(sessions.contains_key("new"));
+        assert!(!sessions.contains_key("old"));
+
+        // 0 表示永久保留
+        let mut sessions = HashMap::from([("old".to_string(), 0)]);
+        assert!(!prune_disabled_sessions(&mut sessions, 0, now));
+        assert_eq!(sessions.len(), 1);
+
+        // 超大保留天数不会溢出
+        assert!(!prune_disabled_sessions(&mut sessions, u64::MAX, now));
+        assert_eq!(sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_disable_session_migrates_legacy_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+        let file = dir.path().join("session_disabled.json");
+        std::fs::write(&file, r#"["legacy"]"#).unwrap();
+
+        assert!(mgr.is_session_disabled("legacy"));
+        assert!(!mgr.is_session_disabled("other"));
+        // 查询不改写文件
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"["legacy"]"#);
+
+        mgr.disable_session("other").unwrap();
+        assert!(mgr.is_session_disabled("other"));
+
+        let saved: HashMap<String, u64> =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("session_disabled.json")).unwrap()).unwrap();
+        assert_eq!(saved.len(), 2);
+    }
+
+    #[test]
+    fn test_project_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("my_repo");
+        let nested = repo.join("crates/app");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(repo.join(".git")).unwrap();
+
+        assert_eq!(project_name(&nested, "dir_name", None), "app");
+        assert_eq!(project_name(&nested, "unknown", None), "app");
+
+        assert_eq!(project_name(&nested, "git_repo", None), "my_repo");
+        let outside = dir.path().join("plain");
+        fs::create_dir(&outside).unwrap();
+        assert_eq!(project_name(&outside, "git_repo", None), "plain");
+
+        let home = Path::new("/home/alice");
+        assert_eq!(project_name(Path::new("/home/alice/work/app"), "full_path", Some(home)), "~/work/app");
+        assert_eq!(project_name(home, "full_path", Some(home)), "~");
+        assert_eq!(project_name(Path::new("/srv/app"), "full_path", Some(home)), "/srv/app");
+        assert_eq!(
+            project_name(Path::new("/home/alice/a/b/c/d"), "full_path", Some(home)),
+            "…/b/c/d"
+        );
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let at = |s: &str| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+
+        // 跨零点
+        assert!(in_quiet_hours("23:00-07:00", at("23:00")));
+        assert!(in_quiet_hours("23:00-07:00", at("00:00")));
+        assert!(in_quiet_hours("23:00-07:00", at("03:30")));
+        assert!(!in_quiet_hours("23:00-07:00", at("07:00")));
+        assert!(!in_quiet_hours("23:00-07:00", at("12:00")));
+        assert!(!in_quiet_hours("23:00-07:00", at("22:59")));
+
+        // 当天内
+        assert!(in_quiet_hours("12:00-13:30", at("12:45")));
+        assert!(!in_quiet_hours("12:00-13:30", at("13:30")));
+
+        // 空时段与格式错误
+        assert!(!in_quiet_hours("", at("03:00")));
+        assert!(!in_quiet_hours("08:00-08:00", at("08:00")));
+        assert!(!in_quiet_hours("night", at("03:00")));
+        assert!(parse_quiet_hours("23:00-7").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = Config::default();
+        config.notification.wxid = "wxid_test".to_string();
+        config.notification.listen = "0.0.0.0:4399".to_string();
+        config.notification.transcript_domain = "https://transcript.example.com".to_string();
+        assert!(config.validate().iter().all(|check| check.error.is_none()));
+        config.notification.listen = "localhost:4399".to_string();
+        assert!(config.validate().iter().all(|check| check.error.is_none()));
+
+        config.notification.wxid = String::new();
+        config.notification.listen = "localhost".to_string();
+        config.notification.transcript_domain = "transcript.example.com".to_string();
+        config.gewe_cli.timeout = 7 * 24 * 60 * 60;
+        config.notification.channel = "email".to_string();
+        config.notification.project_name_strategy = "basename".to_string();
+        config.notification.quiet_hours = "23点-7点".to_string();
+        config.hook_fail_mode = "block".to_string();
+        config.transcript.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+        let failed: Vec<&str> = config
+            .validate()
+            .into_iter()
+            .filter(|check| check.error.is_some())
+            .map(|check| check.name)
+            .collect();
+        assert_eq!(failed, ["通知渠道", "监听地址", "Transcript 域名", "超时时间", "项目名策略", "免打扰时段", "Hook 失败模式", "可信代理"]);
+
+        config.notification.channel = "wechat".to_string();
+        assert!(config.validate()[1].error.is_some());
+
+        assert!(validate_transcript_domain("").is_ok());
+        assert!(validate_transcript_domain("https://a.example.com/").is_err());
+        assert!(validate_transcript_domain("ftp://a.example.com").is_err());
+    }
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
@@ -260,6 +1435,73 @@ mod tests {
         assert_eq!(config.gewe_cli.timeout, 0);  // 默认无限等待
     }
 
+    #[test]
+    fn test_migrate_legacy_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("config.toml");
+        // 早期版本：没有 version，也没有后来新增的 [gewe_cli] 段
+        std::fs::write(
+            &config_file,
+            "[remote]\nenabled = true\n\n[notification]\nchannel = \"wechat\"\nwxid = \"wxid_old\"\nlisten = \"0.0.0.0:4399\"\n",
+        )
+        .unwrap();
+
+        let original = std::fs::read_to_string(&config_file).unwrap();
+        let mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+
+        let config = mgr.load().unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.remote.enabled);
+        assert_eq!(config.notification.wxid, "wxid_old");
+        assert_eq!(config.gewe_cli.command, "gewe-cli");
+
+        // 加载只在内存中迁移，不改写文件
+        assert_eq!(std::fs::read_to_string(&config_file).unwrap(), original);
+        assert!(mgr.needs_migration().unwrap());
+
+        // 显式迁移：备份原文件后写回，再次加载无需迁移
+        let migration = mgr.migrate_file().unwrap().unwrap();
+        assert_eq!(migration.from_version, 0);
+        assert!(!migration.notes.is_empty());
+        assert_eq!(std::fs::read_to_string(mgr.backup_file()).unwrap(), original);
+        let mut table: toml::Table = toml::from_str(&std::fs::read_to_string(&config_file).unwrap()).unwrap();
+        assert_eq!(table["version"].as_integer(), Some(CONFIG_VERSION as i64));
+        assert!(migrate(&mut table).is_empty());
+        assert!(!mgr.needs_migration().unwrap());
+        assert!(mgr.migrate_file().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_save_backs_up_legacy_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("config.toml");
+        let original = "# 旧配置\n[notification]\nwxid = \"wxid_old\"\n";
+        std::fs::write(&config_file, original).unwrap();
+
+        // 保存会升级文件版本，升级前备份原文件
+        let mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+        let mut config = mgr.load().unwrap();
+        config.notification.wxid = "wxid_new".to_string();
+        mgr.save(&config).unwrap();
+        assert_eq!(std::fs::read_to_string(mgr.backup_file()).unwrap(), original);
+        assert!(!mgr.needs_migration().unwrap());
+        assert_eq!(mgr.load().unwrap().notification.wxid, "wxid_new");
+
+        // 已是当前版本时不再覆盖备份
+        mgr.save(&config).unwrap();
+        assert_eq!(std::fs::read_to_string(mgr.backup_file()).unwrap(), original);
+    }
+
+    #[test]
+    fn test_migrate_notes() {
+        let mut table: toml::Table = toml::from_str("[notification]\nwxid = \"a\"\n").unwrap();
+        let notes = migrate(&mut table);
+        assert!(notes.contains(&"v0 → v1: 补全 gewe_cli".to_string()));
+        assert!(notes.contains(&"v0 → v1: 补全 notification.listen".to_string()));
+        assert!(!notes.iter().any(|n| n.ends_with("notification.wxid")));
+        assert_eq!(table["notification"]["wxid"].as_str(), Some("a"));
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = Config::default();
@@ -284,12 +1526,7 @@ mod tests {
         )
         .unwrap();
 
-        // 模拟 ConfigManager（这里需要创建临时实例）
-        let mgr = ConfigManager {
-            config_dir: config_dir.clone(),
-            config_file: config_file.clone(),
-            lock_file: config_dir.join("remote.lock"),
-        };
+        let mgr = ConfigManager::with_dir(config_dir.clone());
 
         // 测试只更新 wxid
         mgr.update_notification(Some("new_wxid".to_string()), None, None)
@@ -315,4 +1552,98 @@ mod tests {
         assert_eq!(config.notification.wxid, "another_wxid");
         assert_eq!(config.notification.listen, "0.0.0.0:9999");
     }
+
+    #[test]
+    fn test_unset_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+
+        let mut config = Config::default();
+        config.notification.transcript_domain = "https://t.example.com".to_string();
+        config.gewe_cli.timeout = 300;
+        mgr.save(&config).unwrap();
+
+        mgr.unset_field("transcript_domain").unwrap();
+        let config = mgr.load().unwrap();
+        assert!(config.notification.transcript_domain.is_empty());
+        assert_eq!(config.gewe_cli.timeout, 300);
+
+        mgr.unset_field("timeout").unwrap();
+        assert_eq!(mgr.load().unwrap().gewe_cli.timeout, 0);
+
+        let err = mgr.unset_field("wxid").unwrap_err();
+        assert!(err.to_string().contains("未知的配置项"));
+    }
+
+    #[test]
+    fn test_enable_disable_remote_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+        mgr.save(&Config::default()).unwrap();
+
+        assert!(mgr.enable_remote().unwrap());
+        assert!(mgr.is_remote_enabled());
+
+        // 已启用时不重写配置文件
+        let modified = fs::metadata(mgr.config_file()).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(!mgr.enable_remote().unwrap());
+        assert_eq!(fs::metadata(mgr.config_file()).unwrap().modified().unwrap(), modified);
+
+        assert!(mgr.disable_remote().unwrap());
+        assert!(!mgr.is_remote_enabled());
+        assert!(!mgr.disable_remote().unwrap());
+    }
+
+    #[test]
+    fn test_lock_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+        let mut config = Config::default();
+        config.remote.lock_ttl_secs = 3600;
+        mgr.save(&config).unwrap();
+
+        // 新创建的 lock 有效
+        mgr.enable_remote().unwrap();
+        assert!(mgr.is_remote_enabled());
+
+        // 过期的 lock 视为关闭并被清理
+        fs::write(&mgr.lock_file, (unix_now() - 7200).to_string()).unwrap();
+        assert!(!mgr.is_remote_enabled());
+        assert!(!mgr.lock_file.exists());
+        assert!(!mgr.load().unwrap().remote.enabled);
+
+        // ttl 为 0 时永不过期
+        mgr.enable_remote().unwrap();
+        fs::write(&mgr.lock_file, "0").unwrap();
+        let mut config = mgr.load().unwrap();
+        config.remote.lock_ttl_secs = 0;
+        mgr.save(&config).unwrap();
+        assert!(mgr.is_remote_enabled());
+    }
+
+    #[test]
+    fn test_resolve_wxid_for_cwd() {
+        let dir = tempfile::tempdir().unwrap();
+        let clients = dir.path().join("clients");
+        let acme = clients.join("acme");
+        let acme_app = acme.join("app");
+        let personal = dir.path().join("personal");
+        for path in [&acme_app, &personal] {
+            fs::create_dir_all(path).unwrap();
+        }
+
+        let mut config = Config::default();
+        config.notification.wxid = "wxid_me".to_string();
+        config.routing.insert(clients.display().to_string(), "wxid_clients".to_string());
+        config.routing.insert(acme.display().to_string(), "wxid_acme".to_string());
+
+        // 匹配前缀
+        assert_eq!(config.resolve_wxid_for_cwd(Some(&clients.join("other"))), "wxid_clients");
+        // 嵌套前缀取最长的
+        assert_eq!(config.resolve_wxid_for_cwd(Some(&acme_app)), "wxid_acme");
+        // 无匹配时使用默认 wxid
+        assert_eq!(config.resolve_wxid_for_cwd(Some(&personal)), "wxid_me");
+        assert_eq!(config.resolve_wxid_for_cwd(None), "wxid_me");
+    }
 }
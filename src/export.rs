@@ -0,0 +1,286 @@
+//! 会话导出
+//!
+//! 将会话打包为 zip：自包含的 HTML、原始 JSONL 以及 transcript 中的图片，
+//! 解压后双击 HTML 即可离线查看（HTML 通过相对路径引用 `images/` 下的图片，
+//! 并内联 highlight.js 做代码高亮）
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use colored::*;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use zip::write::SimpleFileOptions;
+
+use crate::config::{self, ConfigManager};
+use crate::http;
+use crate::output::{self, banner};
+use crate::server;
+use crate::transcript::{self, ASSET_MODE_INLINE, HIGHLIGHT_CDN_BASE};
+
+/// 从 CDN 下载 highlight.js 的超时
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 导出的图片
+struct ExportedImage {
+    /// zip 内 `images/` 下的文件名
+    name: String,
+    data: Vec<u8>,
+    /// 页面中该图片的 data URI，导出时替换为 zip 内的相对路径
+    data_uri: Option<String>,
+}
+
+/// 内联到导出页面的 highlight.js 脚本与主题样式
+struct HighlightAssets {
+    script: String,
+    style: String,
+}
+
+/// 导出会话为 zip 文件
+pub fn run(session_id: &str, out: &Path) -> Result<()> {
+    let config = ConfigManager::new()?.load().unwrap_or_default();
+
+    let transcript_path = server::find_transcript(session_id)?;
+
+    let raw = transcript::read_transcript(&transcript_path)?;
+    let messages = transcript::parse_transcript(&transcript_path, &config.transcript.entry_types)?;
+
+    let mut options = config.transcript.clone();
+    options.asset_mode = ASSET_MODE_INLINE.to_string();
+    let html = transcript::render_to_html(&messages, session_id, &options);
+
+    let images = extract_images(&raw);
+    let mut html = rewrite_image_sources(&html, &images);
+    if let Some(assets) = load_highlight_assets(&options.code_theme) {
+        html = embed_highlight(&html, &assets);
+    }
+    write_zip(out, &html, &raw, &images)?;
+
+    let size = fs::metadata(out).map(|m| m.len()).unwrap_or(0);
+
+    banner("  📦 会话已导出", Color::Green);
+    println!("  {} {}", "文件:".dimmed(), out.display());
+    println!("  {} {}", "大小:".dimmed(), format_size(size));
+    if !output::is_quiet() {
+        println!("  {} {} 条消息，{} 张图片", "内容:".dimmed(), messages.len(), images.len());
+        println!();
+    }
+
+    Ok(())
+}
+
+/// 写入 zip 包
+fn write_zip(out: &Path, html: &str, raw: &str, images: &[ExportedImage]) -> Result<()> {
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).context("创建输出目录失败")?;
+    }
+
+    let file = fs::File::create(out).context(format!("创建文件失败: {}", out.display()))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file("transcript.html", options)?;
+    zip.write_all(html.as_bytes())?;
+
+    zip.start_file("transcript.jsonl", options)?;
+    zip.write_all(raw.as_bytes())?;
+
+    for image in images {
+        zip.start_file(format!("images/{}", image.name), options)?;
+        zip.write_all(&image.data)?;
+    }
+
+    zip.finish().context("写入 zip 失败")?;
+    Ok(())
+}
+
+/// 把页面中内嵌的 data URI 图片改为引用 zip 内 `images/` 下的文件
+fn rewrite_image_sources(html: &str, images: &[ExportedImage]) -> String {
+    let mut html = html.to_string();
+    for image in images {
+        if let Some(data_uri) = &image.data_uri {
+            html = html.replace(data_uri.as_str(), &format!("images/{}", image.name));
+        }
+    }
+    html
+}
+
+/// 读取代码高亮资源：优先使用本机 `assets/vendor/`（与 asset_mode = "local" 相同的文件），
+/// 不存在时从 CDN 下载；都失败时返回 None，导出的页面不做代码高亮
+fn load_highlight_assets(code_theme: &str) -> Option<HighlightAssets> {
+    let vendor_dir = config::config_dir_path().ok()?.join("assets/vendor");
+    let fetch = |relative: &str| -> Result<String> {
+        let local = vendor_dir.join(relative);
+        if local.is_file() {
+            return fs::read_to_string(&local).context(format!("读取 {} 失败", local.display()));
+        }
+        http::get_text(&format!("{}/{}", HIGHLIGHT_CDN_BASE, relative), DOWNLOAD_TIMEOUT)
+    };
+
+    let script = fetch("highlight.min.js");
+    let style = fetch(&transcript::code_theme_stylesheet(code_theme));
+    match (script, style) {
+        (Ok(script), Ok(style)) => Some(HighlightAssets { script, style }),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("⚠️ 获取 highlight.js 失败，导出的页面将不做代码高亮: {}", e);
+            None
+        }
+    }
+}
+
+/// 把 highlight.js 样式内联到 `</head>` 前，脚本内联到 `</body>` 前并在加载后执行高亮
+fn embed_highlight(html: &str, assets: &HighlightAssets) -> String {
+    let style = format!(
+        "    <style>\n{}\n    </style>\n",
+        assets.style.replace("</style", "<\\/style")
+    );
+    let script = format!(
+        "    <script>\n{}\n    </script>\n    <script>hljs.highlightAll();</script>\n",
+        assets.script.replace("</script", "<\\/script")
+    );
+
+    let mut html = html.to_string();
+    if let Some(pos) = html.rfind("</body>") {
+        html.insert_str(pos, &script);
+    }
+    if let Some(pos) = html.find("</head>") {
+        html.insert_str(pos, &style);
+    }
+    html
+}
+
+/// 从原始 JSONL 中提取 base64 编码的图片
+///
+/// 文件名按出现顺序编号
+fn extract_images(raw: &str) -> Vec<ExportedImage> {
+    let mut images = Vec::new();
+
+    for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(line) {
+            collect_images(&value, &mut images);
+        }
+    }
+
+    images
+}
+
+fn collect_images(value: &serde_json::Value, images: &mut Vec<ExportedImage>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            if map.get("type").and_then(|t| t.as_str()) == Some("image")
+                && let Some(source) = map.get("source")
+                && source.get("type").and_then(|t| t.as_str()) == Some("base64")
+                && let Some(data) = source.get("data").and_then(|d| d.as_str())
+                && let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data)
+            {
+                let media_type = source.get("media_type").and_then(|m| m.as_str()).unwrap_or("");
+                images.push(ExportedImage {
+                    name: format!("image-{}.{}", images.len() + 1, image_extension(media_type)),
+                    data: bytes,
+                    data_uri: transcript::image_data_uri(value),
+                });
+                return;
+            }
+
+            for child in map.values() {
+                collect_images(child, images);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_images(item, images);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn image_extension(media_type: &str) -> &'static str {
+    match media_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "bin",
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_images() {
+        let raw = [
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"image","source":{"type":"base64","media_type":"image/png","data":"aGVsbG8="}},{"type":"text","text":"看图"}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"好的"}]}}"#,
+        ]
+        .join("\n");
+
+        let images = extract_images(&raw);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].name, "image-1.png");
+        assert_eq!(images[0].data, b"hello");
+        assert_eq!(images[0].data_uri.as_deref(), Some("data:image/png;base64,aGVsbG8="));
+    }
+
+    #[test]
+    fn test_exported_html_references_zip_images() {
+        let raw = r#"{"type":"user","message":{"role":"user","content":[{"type":"image","source":{"type":"base64","media_type":"image/png","data":"aGVsbG8="}},{"type":"text","text":"看图"}]}}"#;
+        let messages = transcript::parse_transcript_content(raw, &[], "测试").unwrap();
+        let options = crate::config::TranscriptConfig {
+            asset_mode: ASSET_MODE_INLINE.to_string(),
+            ..Default::default()
+        };
+        let html = transcript::render_to_html(&messages, "abc", &options);
+        assert!(html.contains("data:image/png;base64,aGVsbG8="));
+
+        let html = rewrite_image_sources(&html, &extract_images(raw));
+        assert!(html.contains(r#"<img src="images/image-1.png""#));
+        assert!(!html.contains("base64,"));
+    }
+
+    #[test]
+    fn test_embed_highlight() {
+        let assets = HighlightAssets {
+            script: "var hljs={};'</script>'".to_string(),
+            style: ".hljs{color:red}".to_string(),
+        };
+        let html = embed_highlight("<html><head></head><body><p>x</p></body></html>", &assets);
+
+        assert!(html.contains("<style>\n.hljs{color:red}\n    </style>\n</head>"));
+        assert!(html.contains("hljs.highlightAll();</script>\n</body>"));
+        // 脚本中的 </script> 被转义，不会提前结束标签
+        assert!(html.contains(r"'<\/script>'"));
+    }
+
+    #[test]
+    fn test_write_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("nested/session.zip");
+        let images = vec![ExportedImage {
+            name: "image-1.png".to_string(),
+            data: b"png".to_vec(),
+            data_uri: None,
+        }];
+
+        write_zip(&out, "<html></html>", "{}\n", &images).unwrap();
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&out).unwrap()).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(names, ["images/image-1.png", "transcript.html", "transcript.jsonl"]);
+        assert!(archive.by_name("transcript.html").is_ok());
+    }
+}
@@ -0,0 +1,244 @@
+//! 按渠道格式化通知消息
+//!
+//! 各渠道对消息格式的要求不同：微信为纯文本，Telegram 为 MarkdownV2，
+//! 钉钉为 Markdown。业务逻辑只构造 [`OutgoingMessage`]，由对应的格式化器处理转义
+
+use anyhow::Result;
+
+/// 支持的通知渠道
+pub const SUPPORTED_CHANNELS: &[&str] = &["wechat", "telegram", "dingtalk"];
+
+/// 与渠道无关的逻辑消息
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingMessage {
+    /// 标题（可选）
+    pub title: Option<String>,
+    /// 正文
+    pub body: String,
+    /// 附带链接（可选）
+    pub link: Option<String>,
+    /// 快捷回复按钮（仅 Telegram 以 inline keyboard 展示，其他渠道忽略）
+    pub quick_replies: Vec<String>,
+    /// 需要回复的消息，免打扰时段内仍按 `quiet_hours_allow_urgent` 发送
+    pub urgent: bool,
+}
+
+impl OutgoingMessage {
+    /// 仅包含正文的消息
+    pub fn text(body: impl Into<String>) -> Self {
+        Self {
+            body: body.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// 快捷回复按钮 callback_data 的前缀，用于区分其他来源的回调
+pub const QUICK_REPLY_PREFIX: &str = "reply:";
+
+/// 构造 Telegram 的 inline keyboard
+///
+/// 每个快捷回复对应一个回调按钮，有链接时追加一个打开链接的「查看」按钮；
+/// 没有快捷回复时返回 None
+pub fn telegram_reply_markup(message: &OutgoingMessage) -> Option<serde_json::Value> {
+    if message.quick_replies.is_empty() {
+        return None;
+    }
+
+    let mut buttons: Vec<serde_json::Value> = message
+        .quick_replies
+        .iter()
+        .map(|reply| {
+            serde_json::json!({
+                "text": reply,
+                "callback_data": format!("{}{}", QUICK_REPLY_PREFIX, reply),
+            })
+        })
+        .collect();
+    if let Some(link) = &message.link {
+        buttons.push(serde_json::json!({ "text": "查看", "url": link }));
+    }
+
+    Some(serde_json::json!({ "inline_keyboard": [buttons] }))
+}
+
+/// 解析快捷回复按钮的 callback_data，返回对应的回复文本
+pub fn parse_quick_reply(data: &str) -> Option<&str> {
+    data.strip_prefix(QUICK_REPLY_PREFIX).filter(|reply| !reply.trim().is_empty())
+}
+
+/// 消息格式化器
+pub trait MessageFormatter {
+    /// 将逻辑消息格式化为渠道可直接发送的文本
+    fn format(&self, message: &OutgoingMessage) -> String;
+}
+
+/// 微信：纯文本，标题、正文、链接各占一段
+pub struct WechatFormatter;
+
+impl MessageFormatter for WechatFormatter {
+    fn format(&self, message: &OutgoingMessage) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = &message.title {
+            parts.push(title.clone());
+        }
+        parts.push(message.body.clone());
+        if let Some(link) = &message.link {
+            parts.push(link.clone());
+        }
+        parts.join("\n")
+    }
+}
+
+/// Telegram：MarkdownV2，标题加粗，链接使用 `[文本](地址)`
+pub struct TelegramFormatter;
+
+impl MessageFormatter for TelegramFormatter {
+    fn format(&self, message: &OutgoingMessage) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = &message.title {
+            parts.push(format!("*{}*", escape_markdown_v2(title)));
+        }
+        parts.push(escape_markdown_v2(&message.body));
+        if let Some(link) = &message.link {
+            parts.push(format!("[查看详情]({})", escape_markdown_v2_url(link)));
+        }
+        parts.join("\n\n")
+    }
+}
+
+/// 钉钉：Markdown，标题使用三级标题
+pub struct DingTalkFormatter;
+
+impl MessageFormatter for DingTalkFormatter {
+    fn format(&self, message: &OutgoingMessage) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = &message.title {
+            parts.push(format!("### {}", title));
+        }
+        parts.push(message.body.clone());
+        if let Some(link) = &message.link {
+            parts.push(format!("[查看详情]({})", link));
+        }
+        parts.join("\n\n")
+    }
+}
+
+/// 根据渠道名称选择格式化器
+pub fn formatter_for(channel: &str) -> Result<Box<dyn MessageFormatter>> {
+    match channel {
+        "wechat" => Ok(Box::new(WechatFormatter)),
+        "telegram" => Ok(Box::new(TelegramFormatter)),
+        "dingtalk" => Ok(Box::new(DingTalkFormatter)),
+        _ => anyhow::bail!(
+            "未知的通知渠道: {}\n支持的渠道: {}",
+            channel,
+            SUPPORTED_CHANNELS.join(", ")
+        ),
+    }
+}
+
+/// Telegram MarkdownV2 正文转义
+///
+/// 参见 <https://core.telegram.org/bots/api#markdownv2-style>：
+/// `_*[]()~`>#+-=|{}.!` 以及 `\` 本身都需要前置反斜杠
+pub fn escape_markdown_v2(text: &str) -> String {
+    const SPECIAL: &[char] = &[
+        '\\', '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.',
+        '!',
+    ];
+
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Telegram MarkdownV2 链接地址转义：`(...)` 内只需转义 `)` 和 `\`
+fn escape_markdown_v2_url(url: &str) -> String {
+    url.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> OutgoingMessage {
+        OutgoingMessage {
+            title: Some("任务完成 - my_app".to_string()),
+            body: "修复了 3 个 bug (v1.2.0)!".to_string(),
+            link: Some("https://t.example.com/abc".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_telegram_reply_markup() {
+        assert!(telegram_reply_markup(&sample()).is_none());
+
+        let message = OutgoingMessage {
+            quick_replies: vec!["继续".to_string(), "停止".to_string()],
+            ..sample()
+        };
+        assert_eq!(
+            telegram_reply_markup(&message).unwrap(),
+            serde_json::json!({
+                "inline_keyboard": [[
+                    { "text": "继续", "callback_data": "reply:继续" },
+                    { "text": "停止", "callback_data": "reply:停止" },
+                    { "text": "查看", "url": "https://t.example.com/abc" },
+                ]]
+            })
+        );
+
+        assert_eq!(parse_quick_reply("reply:继续"), Some("继续"));
+        assert_eq!(parse_quick_reply("reply: "), None);
+        assert_eq!(parse_quick_reply("other"), None);
+    }
+
+    #[test]
+    fn test_escape_markdown_v2() {
+        assert_eq!(escape_markdown_v2("a_b*c"), "a\\_b\\*c");
+        assert_eq!(escape_markdown_v2("v1.2.0 (beta)!"), "v1\\.2\\.0 \\(beta\\)\\!");
+        assert_eq!(escape_markdown_v2("a\\b"), "a\\\\b");
+        assert_eq!(escape_markdown_v2("`x` > #1 + -2 = {y} | ~z~ [w]"),
+            "\\`x\\` \\> \\#1 \\+ \\-2 \\= \\{y\\} \\| \\~z\\~ \\[w\\]");
+        assert_eq!(escape_markdown_v2("中文不变"), "中文不变");
+    }
+
+    #[test]
+    fn test_wechat_formatter() {
+        assert_eq!(
+            WechatFormatter.format(&sample()),
+            "任务完成 - my_app\n修复了 3 个 bug (v1.2.0)!\nhttps://t.example.com/abc"
+        );
+        assert_eq!(WechatFormatter.format(&OutgoingMessage::text("hi")), "hi");
+    }
+
+    #[test]
+    fn test_telegram_formatter() {
+        assert_eq!(
+            TelegramFormatter.format(&sample()),
+            "*任务完成 \\- my\\_app*\n\n修复了 3 个 bug \\(v1\\.2\\.0\\)\\!\n\n[查看详情](https://t.example.com/abc)"
+        );
+    }
+
+    #[test]
+    fn test_dingtalk_formatter() {
+        assert_eq!(
+            DingTalkFormatter.format(&sample()),
+            "### 任务完成 - my_app\n\n修复了 3 个 bug (v1.2.0)!\n\n[查看详情](https://t.example.com/abc)"
+        );
+    }
+
+    #[test]
+    fn test_formatter_for_unknown_channel() {
+        assert!(formatter_for("wechat").is_ok());
+        let err = formatter_for("slack").err().unwrap();
+        assert!(err.to_string().contains("支持的渠道"));
+    }
+}
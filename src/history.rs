@@ -0,0 +1,155 @@
+//! 回复历史记录
+//!
+//! 每个会话的回复按行追加到 `~/.gewe-cc/replies/<session_id>.jsonl`，
+//! 用于回顾远程协作过程中的每一次决策
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::config::ConfigManager;
+
+/// 单条回复记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyRecord {
+    /// 收到回复的时间
+    pub timestamp: DateTime<Utc>,
+    /// 回复内容
+    pub text: String,
+}
+
+/// 回复历史存储
+#[derive(Debug, Clone)]
+pub struct ReplyHistory {
+    dir: PathBuf,
+    /// 每个会话保留的最大条数（0 表示不限制）
+    max_records: usize,
+}
+
+impl ReplyHistory {
+    pub fn new() -> Result<Self> {
+        let config_mgr = ConfigManager::new()?;
+        let config_dir = config_mgr.config_file().parent().unwrap().to_path_buf();
+        let max_records = config_mgr
+            .load()
+            .map(|config| config.notification.max_reply_history)
+            .unwrap_or(0);
+        Ok(Self::with_dir(config_dir.join("replies")).with_limit(max_records))
+    }
+
+    pub(crate) fn with_dir(dir: PathBuf) -> Self {
+        Self { dir, max_records: 0 }
+    }
+
+    /// 设置每个会话保留的最大条数（0 表示不限制）
+    pub(crate) fn with_limit(mut self, max_records: usize) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    fn history_file(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", session_id))
+    }
+
+    /// 追加一条回复记录
+    pub fn append(&self, session_id: &str, text: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir).context("创建回复历史目录失败")?;
+
+        let record = ReplyRecord {
+            timestamp: Utc::now(),
+            text: text.to_string(),
+        };
+        let line = serde_json::to_string(&record).context("序列化回复记录失败")?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.history_file(session_id))
+            .context("打开回复历史文件失败")?;
+        writeln!(file, "{}", line).context("写入回复历史失败")?;
+
+        self.truncate(session_id)
+    }
+
+    /// 超出保留条数时只保留最近的记录
+    fn truncate(&self, session_id: &str) -> Result<()> {
+        if self.max_records == 0 {
+            return Ok(());
+        }
+
+        let path = self.history_file(session_id);
+        let content = fs::read_to_string(&path).context("读取回复历史失败")?;
+        let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+        if lines.len() <= self.max_records {
+            return Ok(());
+        }
+
+        let mut kept = lines[lines.len() - self.max_records..].join("\n");
+        kept.push('\n');
+        let tmp_file = path.with_extension("jsonl.tmp");
+        fs::write(&tmp_file, kept).context("写入回复历史失败")?;
+        fs::rename(&tmp_file, &path).context("写入回复历史失败")?;
+
+        Ok(())
+    }
+
+    /// 读取会话的全部回复记录（按时间顺序），忽略无法解析的行
+    pub fn load(&self, session_id: &str) -> Vec<ReplyRecord> {
+        let Ok(content) = fs::read_to_string(self.history_file(session_id)) else {
+            return Vec::new();
+        };
+
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// 会话最近一条回复
+    pub fn last(&self, session_id: &str) -> Option<ReplyRecord> {
+        self.load(session_id).pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ReplyHistory::with_dir(dir.path().join("replies"));
+
+        assert!(history.load("s1").is_empty());
+
+        history.append("s1", "继续").unwrap();
+        history.append("s1", "多行\n回复").unwrap();
+        history.append("s2", "停止").unwrap();
+
+        let records = history.load("s1");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].text, "继续");
+        assert_eq!(records[1].text, "多行\n回复");
+        assert_eq!(history.load("s2").len(), 1);
+        assert_eq!(history.last("s1").unwrap().text, "多行\n回复");
+        assert!(history.last("s3").is_none());
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ReplyHistory::with_dir(dir.path().join("replies")).with_limit(2);
+
+        for text in ["一", "二", "三"] {
+            history.append("s1", text).unwrap();
+        }
+
+        let texts: Vec<String> = history.load("s1").into_iter().map(|r| r.text).collect();
+        assert_eq!(texts, ["二", "三"]);
+        assert_eq!(history.last("s1").unwrap().text, "三");
+    }
+}
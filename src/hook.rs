@@ -1,9 +1,11 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::config::ConfigManager;
+use crate::config::{self, Config, ConfigManager, canonical_path};
+use crate::format::OutgoingMessage;
+use crate::notify;
 use crate::sanitize::{sanitize_wxid, sanitize_listen_addr};
 use crate::server::SessionRegistry;
 
@@ -29,6 +31,10 @@ pub struct HookInput {
     #[serde(default)]
     pub stop_hook_active: bool,
 
+    /// 停止原因（仅 Stop，如 end_turn / max_tokens / refusal），部分版本字段名为 reason
+    #[serde(default, alias = "reason")]
+    pub stop_reason: Option<String>,
+
     /// 用户自定义的提示文本（可用于自定义 hook 提示信息）
     #[serde(default)]
     pub user_prompt: Option<String>,
@@ -47,25 +53,92 @@ impl HookDecision {
         serde_json::to_string(self).context("序列化 HookDecision 失败")
     }
 
-    /// 输出到 stdout（供 Hook 脚本使用）
-    pub fn output(&self) -> Result<()> {
-        println!("{}", self.to_json()?);
+    /// 转换为带缩进的 JSON 字符串，便于手动调试
+    pub fn to_pretty_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("序列化 HookDecision 失败")
+    }
+
+    /// 输出到 stdout（供 Hook 脚本使用），`pretty` 时带缩进
+    pub fn output(&self, pretty: bool) -> Result<()> {
+        let json = if pretty { self.to_pretty_json()? } else { self.to_json()? };
+        println!("{}", json);
         Ok(())
     }
+
+    /// Hook 处理出错时的决策
+    ///
+    /// `closed` 模式阻止并携带错误原因，其他值（默认 `open`）一律放行，
+    /// 保证 gewe-cc 自身出错时 Claude Code 仍能拿到合法的 JSON。
+    /// Stop Hook 始终放行：阻止会让 Claude 继续并再次触发 Stop，持续出错时将无限循环
+    pub fn on_error(err: &anyhow::Error, fail_mode: &str, hook_type: &str) -> Self {
+        match fail_mode {
+            "closed" if hook_type != "stop" => HookDecision::Block {
+                reason: format!("⚠️ gewe-cc Hook 处理失败: {:#}", err),
+            },
+            _ => HookDecision::Approve,
+        }
+    }
 }
 
 pub struct HookHandler;
 
 impl HookHandler {
+    /// 处理 Hook 并输出决策，出错时按 `hook_fail_mode` 输出兜底决策
+    ///
+    /// 提供 `replay` 时从该文件读取输入（重放 `--dump-input` 保存的内容），否则读取 stdin；
+    /// `dump_input` 为 None 时读取 `GEWE_CC_HOOK_DUMP` 环境变量（见 [`dump_input_path`]）。
+    /// 重放用于手动调试，文件缺失或无法解析时直接返回错误，不输出兜底决策
+    pub fn run(hook_type: &str, pretty: bool, dump_input: Option<PathBuf>, replay: Option<&Path>) -> Result<()> {
+        let result = match replay {
+            Some(path) => Self::handle(hook_type, Self::read_input_file(path)?),
+            None => {
+                let dump_input = dump_input_path(dump_input, std::env::var_os(HOOK_DUMP_ENV));
+                Self::handle_from_stdin(hook_type, dump_input.as_deref())
+            }
+        };
+        let decision = result.unwrap_or_else(|e| {
+            eprintln!("❌ Hook 处理失败: {:#}", e);
+            let fail_mode = ConfigManager::new()
+                .and_then(|mgr| mgr.load())
+                .map(|config| config.hook_fail_mode)
+                .unwrap_or_default();
+            HookDecision::on_error(&e, &fail_mode, hook_type)
+        });
+
+        decision.output(pretty)
+    }
+
     /// 从 stdin 读取输入并处理
-    pub fn handle_from_stdin(hook_type: &str) -> Result<HookDecision> {
+    ///
+    /// 提供 `dump_input` 时在解析前把原始输入写入该文件，便于排查解析问题或重放
+    pub fn handle_from_stdin(hook_type: &str, dump_input: Option<&Path>) -> Result<HookDecision> {
         let mut buffer = String::new();
         io::stdin()
             .read_to_string(&mut buffer)
             .context("读取 stdin 失败")?;
 
+        // 写入失败只提示，不影响决策输出（stdout 只输出决策 JSON）
+        if let Some(path) = dump_input
+            && let Err(e) = write_dump(path, &buffer)
+        {
+            eprintln!("⚠️ {:#}", e);
+        }
+
+        Self::handle_raw(hook_type, &buffer)
+    }
+
+    /// 从文件读取并解析 Hook 输入，用于重放抓取到的真实 Hook 输入
+    pub fn read_input_file(path: &Path) -> Result<HookInput> {
+        let buffer = std::fs::read_to_string(path)
+            .context(format!("读取 Hook 输入文件失败: {}", path.display()))?;
+
+        serde_json::from_str(&buffer).context("解析 Hook 输入失败")
+    }
+
+    /// 解析原始 JSON 输入并处理
+    fn handle_raw(hook_type: &str, raw: &str) -> Result<HookDecision> {
         let input: HookInput =
-            serde_json::from_str(&buffer).context("解析 Hook 输入失败")?;
+            serde_json::from_str(raw).context("解析 Hook 输入失败")?;
 
         Self::handle(hook_type, input)
     }
@@ -73,7 +146,7 @@ impl HookHandler {
     /// 处理 Hook 事件
     fn handle(hook_type: &str, input: HookInput) -> Result<HookDecision> {
         match hook_type {
-            "user-prompt-submit" => handle_user_prompt_submit(input),
+            "user-prompt-submit" => handle_user_prompt_submit(&ConfigManager::new()?, input),
             "stop" => handle_stop(input),
             "notification" => handle_notification(input),
             _ => {
@@ -83,37 +156,132 @@ impl HookHandler {
     }
 }
 
-fn handle_user_prompt_submit(input: HookInput) -> Result<HookDecision> {
-    let config_mgr = ConfigManager::new()?;
+/// 指定 Hook 原始输入转储路径的环境变量（未传 --dump-input 时使用）
+pub const HOOK_DUMP_ENV: &str = "GEWE_CC_HOOK_DUMP";
 
-    // 检查是否是远程控制命令
-    match input.prompt.as_deref() {
-        Some(">remote-on") => handle_remote_on(&config_mgr),
-        Some(">remote-off") => handle_remote_off(&config_mgr, &input.session_id),
-        Some(">remote-status") => handle_remote_status(&config_mgr),
-        _ => {
-            // 其他 prompt 正常通过
-            Ok(HookDecision::Approve)
-        }
+/// 原始输入转储路径：命令行参数优先，其次为环境变量，空值视为未设置
+fn dump_input_path(arg: Option<PathBuf>, env: Option<std::ffi::OsString>) -> Option<PathBuf> {
+    arg.or_else(|| env.map(PathBuf::from))
+        .filter(|path| !path.as_os_str().is_empty())
+}
+
+/// 将 Hook 原始输入写入文件，按需创建父目录
+fn write_dump(path: &Path, buffer: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).context("创建转储目录失败")?;
     }
+    std::fs::write(path, buffer).context(format!("写入 Hook 输入转储失败: {}", path.display()))
+}
+
+/// 远程控制命令：(名称, 说明)
+const REMOTE_COMMANDS: &[(&str, &str)] = &[
+    ("on", "启用远程模式"),
+    ("off", "关闭当前会话的远程模式"),
+    ("status", "查看远程模式状态"),
+    ("help", "列出可用命令"),
+];
+
+/// 解析后的远程控制命令
+#[derive(Debug, PartialEq)]
+struct RemoteCommand<'a> {
+    name: &'a str,
+    args: Vec<&'a str>,
+}
+
+/// 解析远程控制命令：去掉前缀后，第一个词为命令名，其余为参数
+///
+/// 不以前缀开头的 prompt 返回 `None`，前缀为空时使用默认前缀
+fn parse_remote_command<'a>(prompt: &'a str, prefix: &str) -> Option<RemoteCommand<'a>> {
+    let default_prefix = config::default_command_prefix();
+    let prefix = if prefix.trim().is_empty() {
+        default_prefix.as_str()
+    } else {
+        prefix
+    };
+
+    let rest = prompt.trim().strip_prefix(prefix)?;
+    let mut words = rest.split_whitespace();
+    let name = words.next()?;
+
+    Some(RemoteCommand {
+        name,
+        args: words.collect(),
+    })
+}
+
+fn handle_user_prompt_submit(config_mgr: &ConfigManager, input: HookInput) -> Result<HookDecision> {
+    // 普通 prompt 不应因配置缺失而报错，读取失败时使用默认前缀并允许会话内命令
+    let (prefix, allow_inline_commands) = config_mgr
+        .load()
+        .map(|config| (config.remote.command_prefix, config.remote.allow_inline_commands))
+        .unwrap_or_else(|_| (config::default_command_prefix(), true));
+
+    // 管理员关闭了会话内命令时，远程控制命令按普通 prompt 放行
+    if !allow_inline_commands {
+        return Ok(HookDecision::Approve);
+    }
+
+    // 检查是否是远程控制命令，其他 prompt 正常通过
+    let Some(command) = input
+        .prompt
+        .as_deref()
+        .and_then(|prompt| parse_remote_command(prompt, &prefix))
+    else {
+        return Ok(HookDecision::Approve);
+    };
+
+    match command.name {
+        "on" => handle_remote_on(config_mgr),
+        "off" => handle_remote_off(config_mgr, &input.session_id),
+        "status" => handle_remote_status(config_mgr),
+        "help" => Ok(remote_help(&prefix, None)),
+        unknown => Ok(remote_help(&prefix, Some(unknown))),
+    }
+}
+
+/// 列出可用的远程控制命令
+fn remote_help(prefix: &str, unknown: Option<&str>) -> HookDecision {
+    let mut reason = String::new();
+    if let Some(name) = unknown {
+        reason.push_str(&format!("❓ 未知命令: {}{}\n\n", prefix, name));
+    }
+
+    reason.push_str("📖 可用的远程控制命令\n");
+    for (name, description) in REMOTE_COMMANDS {
+        reason.push_str(&format!("\n{}{} - {}", prefix, name, description));
+    }
+
+    HookDecision::Block { reason }
 }
 
 fn handle_remote_on(config_mgr: &ConfigManager) -> Result<HookDecision> {
-    config_mgr.enable_remote()?;
+    let changed = config_mgr.enable_remote()?;
 
     let config = config_mgr.load()?;
 
+    let title = if changed {
+        "✅ 远程模式已启用"
+    } else {
+        "ℹ️ 远程模式已经是启用状态"
+    };
+
     let reason = format!(
-        "✅ 远程模式已启用\n\n\
+        "{}\n\n\
          配置信息：\n\
          - 目标微信：{}\n\
          - 监听地址：{}\n\
-         - 标记文件：~/.gewe-cc/remote.lock\n\n\
+         - 标记文件：{}\n\n\
          任务完成后将自动等待微信指令。",
+        title,
         sanitize_wxid(&config.notification.wxid),
-        sanitize_listen_addr(&config.notification.listen)
+        sanitize_listen_addr(&config.notification.listen),
+        config_mgr.lock_file().display()
     );
 
+    if changed && config.notification.notify_on_toggle {
+        notify_toggle("✅ 远程模式已启用\n任务完成后将在微信等待你的指令。".to_string());
+    }
+
     Ok(HookDecision::Block { reason })
 }
 
@@ -133,9 +301,25 @@ fn handle_remote_off(config_mgr: &ConfigManager, session_id: &str) -> Result<Hoo
         session_id, extra
     );
 
+    // 配置无法加载时仍然完成关闭，只是不发送切换通知
+    let notify_on_toggle = config_mgr
+        .load()
+        .map(|config| config.notification.notify_on_toggle)
+        .unwrap_or(false);
+    if notify_on_toggle {
+        notify_toggle(format!("⏹️ 会话远程模式已关闭\n会话 ID: {}", session_id));
+    }
+
     Ok(HookDecision::Block { reason })
 }
 
+/// 发送远程模式切换的确认通知（失败不影响主流程）
+fn notify_toggle(message: String) {
+    if let Err(e) = notify::send_notification(message, None) {
+        eprintln!("⚠️ 发送远程模式切换通知失败: {}", e);
+    }
+}
+
 fn handle_remote_status(config_mgr: &ConfigManager) -> Result<HookDecision> {
     let config = config_mgr.load()?;
     let enabled = config_mgr.is_remote_enabled();
@@ -149,17 +333,18 @@ fn handle_remote_status(config_mgr: &ConfigManager) -> Result<HookDecision> {
              配置：\n\
              - 目标微信：{}\n\
              - 监听地址：{}\n\
-             - 标记文件：~/.gewe-cc/remote.lock",
+             - 标记文件：{}",
             status,
             sanitize_wxid(&config.notification.wxid),
-            sanitize_listen_addr(&config.notification.listen)
+            sanitize_listen_addr(&config.notification.listen),
+            config_mgr.lock_file().display()
         )
     } else {
         format!(
             "📊 远程模式状态\n\n\
              状态：{}\n\n\
-             使用 >remote-on 启用远程模式。",
-            status
+             使用 {}on 启用远程模式。",
+            status, config.remote.command_prefix
         )
     };
 
@@ -184,6 +369,13 @@ fn handle_stop(input: HookInput) -> Result<HookDecision> {
         return Ok(HookDecision::Approve);
     }
 
+    // 2.2 工作目录不在项目白名单内，直接允许停止
+    // 配置缺失或损坏时按默认配置处理，不让每次 Stop 都变成 Hook 错误
+    let config = config_mgr.load().unwrap_or_default();
+    if !is_project_allowed(&config.remote.project_allowlist, input.cwd.as_deref()) {
+        return Ok(HookDecision::Approve);
+    }
+
     // 3. 注册 session（如果提供了 transcript_path）
     if let Some(transcript_path) = &input.transcript_path
         && let Ok(registry) = SessionRegistry::new()
@@ -193,12 +385,9 @@ fn handle_stop(input: HookInput) -> Result<HookDecision> {
     }
 
     // 4. 远程模式已启用，阻止停止并激活 Skill
-    let project = input
-        .cwd
-        .as_ref()
-        .and_then(|p| p.file_name())
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown");
+    let project = config.resolve_project_name(input.cwd.as_deref());
+
+    let stop_reason = stop_reason_line(input.stop_reason.as_deref());
 
     // 如果用户提供了自定义提示文本，使用自定义文本
     let reason = if let Some(custom_prompt) = input.user_prompt {
@@ -208,7 +397,7 @@ fn handle_stop(input: HookInput) -> Result<HookDecision> {
              上下文信息：\n\
              - 项目: {}\n\
              - 目录: {}\n\
-             - 会话: {}",
+             - 会话: {}{}",
             custom_prompt,
             project,
             input
@@ -216,7 +405,8 @@ fn handle_stop(input: HookInput) -> Result<HookDecision> {
                 .as_ref()
                 .map(|p| p.display().to_string())
                 .unwrap_or_else(|| "unknown".to_string()),
-            input.session_id
+            input.session_id,
+            stop_reason
         )
     } else {
         // 使用默认提示 - 强制要求激活 skill
@@ -225,20 +415,58 @@ fn handle_stop(input: HookInput) -> Result<HookDecision> {
              上下文信息：\n\
              - 项目: {}\n\
              - 目录: {}\n\
-             - 会话: {}",
+             - 会话: {}{}",
             project,
             input
                 .cwd
                 .as_ref()
                 .map(|p| p.display().to_string())
                 .unwrap_or_else(|| "unknown".to_string()),
-            input.session_id
+            input.session_id,
+            stop_reason
         )
     };
 
     Ok(HookDecision::Block { reason })
 }
 
+/// Block 提示中的停止原因行，没有停止原因或为正常结束时返回空字符串
+fn stop_reason_line(stop_reason: Option<&str>) -> String {
+    let Some(stop_reason) = stop_reason.map(str::trim).filter(|r| !r.is_empty()) else {
+        return String::new();
+    };
+
+    let note = match stop_reason {
+        "end_turn" | "stop_sequence" => return String::new(),
+        "max_tokens" => "（达到输出上限，可要求继续）",
+        "pause_turn" => "（长时间操作被暂停，可要求继续）",
+        "refusal" => "（模型拒绝了该请求，可调整要求后重试）",
+        _ => "",
+    };
+
+    format!("\n- 停止原因: {}{}", stop_reason, note)
+}
+
+/// 判断工作目录是否在项目白名单内
+///
+/// 白名单为空时不做限制；否则按规范化后的路径前缀（逐级目录）匹配，
+/// 路径不存在而无法规范化时按原样比较
+fn is_project_allowed(allowlist: &[String], cwd: Option<&Path>) -> bool {
+    if allowlist.is_empty() {
+        return true;
+    }
+
+    let Some(cwd) = cwd else {
+        return false;
+    };
+
+    let cwd = canonical_path(cwd);
+
+    allowlist
+        .iter()
+        .any(|prefix| cwd.starts_with(canonical_path(Path::new(prefix))))
+}
+
 fn handle_notification(input: HookInput) -> Result<HookDecision> {
     let config_mgr = ConfigManager::new()?;
 
@@ -250,39 +478,15 @@ fn handle_notification(input: HookInput) -> Result<HookDecision> {
     let config = config_mgr.load()?;
 
     // 获取项目名
-    let project = input
-        .cwd
-        .as_ref()
-        .and_then(|p| p.file_name())
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown");
+    let project = config.resolve_project_name(input.cwd.as_deref());
 
     // 构建通知消息
-    let message = format!(
-        "【Claude Code】\n\
-         ⚠️ 会话可能挂起\n\
-         📁 项目: {}\n\
-         🕐 检测到 60 秒以上无响应\n\n\
-         请检查终端是否在等待输入。\n\
-         会话 ID: {}",
-        project,
-        input.session_id
-    );
-
-    // 使用 gewe-cli 直接发送文本消息（不等待回复）
-    let output = std::process::Command::new(&config.gewe_cli.command)
-        .args([
-            "message",
-            "send-text",
-            "--to",
-            &config.notification.wxid,
-            "--content",
-            &message,
-        ])
-        .output();
+    let message = idle_notification(&config, &project, &input.session_id, 60);
 
+    // 按渠道格式化后发送（不等待回复）
     // 忽略发送失败（兜底功能，不应阻塞流程）
-    if let Err(e) = output {
+    let wxid = config.resolve_wxid_for_cwd(input.cwd.as_deref());
+    if let Err(e) = notify::dispatch_notification(&config, wxid, &message) {
         eprintln!("⚠️ 发送空闲通知失败: {}", e);
     }
 
@@ -290,10 +494,68 @@ fn handle_notification(input: HookInput) -> Result<HookDecision> {
     Ok(HookDecision::Approve)
 }
 
+/// 空闲通知（Notification Hook 与 monitor 命令共用）
+pub fn idle_notification(config: &Config, project: &str, session_id: &str, idle_secs: u64) -> OutgoingMessage {
+    OutgoingMessage {
+        title: Some("【Claude Code】".to_string()),
+        body: idle_notification_body(project, session_id, idle_secs),
+        link: text_notification_link(config, session_id),
+        ..Default::default()
+    }
+}
+
+/// 空闲通知的正文
+fn idle_notification_body(project: &str, session_id: &str, idle_secs: u64) -> String {
+    format!(
+        "⚠️ 会话可能挂起\n\
+         📁 项目: {}\n\
+         🕐 检测到 {} 秒以上无响应\n\n\
+         请检查终端是否在等待输入。\n\
+         会话 ID: {}",
+        project, idle_secs, session_id
+    )
+}
+
+/// 纯文本通知附带的 transcript 链接，未开启 `include_url_in_text` 或未配置域名时为 None
+fn text_notification_link(config: &Config, session_id: &str) -> Option<String> {
+    if !config.notification.include_url_in_text {
+        return None;
+    }
+    notify::build_transcript_url(config, session_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_text_notification_link() {
+        let mut config = Config::default();
+        config.notification.transcript_domain = "https://t.example.com".to_string();
+        assert_eq!(text_notification_link(&config, "abc"), None);
+
+        config.notification.include_url_in_text = true;
+        assert_eq!(text_notification_link(&config, "abc").as_deref(), Some("https://t.example.com/abc"));
+
+        // 未配置域名时不附带链接
+        config.notification.transcript_domain = String::new();
+        assert_eq!(text_notification_link(&config, "abc"), None);
+    }
+
+    #[test]
+    fn test_idle_notification() {
+        let mut config = Config::default();
+        config.notification.transcript_domain = "https://t.example.com".to_string();
+        config.notification.include_url_in_text = true;
+
+        let message = idle_notification(&config, "my_app", "abc", 300);
+        assert_eq!(message.title.as_deref(), Some("【Claude Code】"));
+        assert!(message.body.contains("📁 项目: my_app"));
+        assert!(message.body.contains("300 秒"));
+        assert_eq!(message.link.as_deref(), Some("https://t.example.com/abc"));
+        assert!(!message.urgent);
+    }
+
     #[test]
     fn test_hook_decision_serialization() {
         let decision = HookDecision::Approve;
@@ -306,6 +568,108 @@ mod tests {
         let json = decision.to_json().unwrap();
         assert!(json.contains("block"));
         assert!(json.contains("测试原因"));
+
+        let pretty = decision.to_pretty_json().unwrap();
+        assert!(pretty.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_on_error_fail_open() {
+        let err = anyhow::anyhow!("解析 Hook 输入失败");
+        let json = HookDecision::on_error(&err, "open", "notification").to_json().unwrap();
+        assert_eq!(json, r#"{"decision":"approve"}"#);
+
+        // 未知取值按 open 处理
+        let json = HookDecision::on_error(&err, "", "notification").to_json().unwrap();
+        assert_eq!(json, r#"{"decision":"approve"}"#);
+    }
+
+    #[test]
+    fn test_on_error_fail_closed() {
+        let err = anyhow::anyhow!("底层错误").context("解析 Hook 输入失败");
+        let json = HookDecision::on_error(&err, "closed", "user-prompt-submit").to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["decision"], "block");
+        let reason = value["reason"].as_str().unwrap();
+        assert!(reason.contains("解析 Hook 输入失败"));
+        assert!(reason.contains("底层错误"));
+
+        // Stop Hook 出错时不阻止，避免循环
+        let json = HookDecision::on_error(&err, "closed", "stop").to_json().unwrap();
+        assert_eq!(json, r#"{"decision":"approve"}"#);
+    }
+
+    #[test]
+    fn test_parse_remote_command() {
+        let command = parse_remote_command(">remote-on", ">remote-").unwrap();
+        assert_eq!(command, RemoteCommand { name: "on", args: vec![] });
+
+        let command = parse_remote_command("  >remote-status  verbose ", ">remote-").unwrap();
+        assert_eq!(command.name, "status");
+        assert_eq!(command.args, vec!["verbose"]);
+
+        // 自定义前缀，旧前缀不再触发
+        assert_eq!(parse_remote_command("/gewe help", "/gewe ").unwrap().name, "help");
+        assert!(parse_remote_command(">remote-on", "/gewe ").is_none());
+
+        // 前缀为空时使用默认前缀
+        assert_eq!(parse_remote_command(">remote-off", "").unwrap().name, "off");
+
+        assert!(parse_remote_command("> 引用一段话", ">remote-").is_none());
+        assert!(parse_remote_command(">remote-", ">remote-").is_none());
+    }
+
+    #[test]
+    fn test_remote_help_lists_commands() {
+        let HookDecision::Block { reason } = remote_help("/gewe ", Some("foo")) else {
+            panic!("应返回 block");
+        };
+        assert!(reason.contains("未知命令: /gewe foo"));
+        for (name, _) in REMOTE_COMMANDS {
+            assert!(reason.contains(&format!("/gewe {}", name)));
+        }
+    }
+
+    #[test]
+    fn test_dump_input() {
+        assert_eq!(dump_input_path(None, None), None);
+        assert_eq!(dump_input_path(None, Some("".into())), None);
+        assert_eq!(
+            dump_input_path(None, Some("/tmp/env.json".into())),
+            Some(PathBuf::from("/tmp/env.json"))
+        );
+        assert_eq!(
+            dump_input_path(Some("arg.json".into()), Some("/tmp/env.json".into())),
+            Some(PathBuf::from("arg.json"))
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dumps/stop.json");
+        write_dump(&path, r#"{"session_id":"abc"}"#).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"session_id":"abc"}"#);
+    }
+
+    #[test]
+    fn test_replay_input_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input.json");
+
+        // 文件缺失或无法解析时直接报错，不按 hook_fail_mode 输出兜底决策
+        let err = HookHandler::read_input_file(&path).unwrap_err();
+        assert!(err.to_string().contains("读取 Hook 输入文件失败"));
+        assert!(HookHandler::run("stop", false, None, Some(&path)).is_err());
+
+        std::fs::write(&path, "{not json").unwrap();
+        let err = HookHandler::read_input_file(&path).unwrap_err();
+        assert!(err.to_string().contains("解析 Hook 输入失败"));
+        assert!(HookHandler::run("stop", false, None, Some(&path)).is_err());
+
+        std::fs::write(&path, r#"{"session_id":"abc"}"#).unwrap();
+        assert_eq!(HookHandler::read_input_file(&path).unwrap().session_id, "abc");
     }
 
     #[test]
@@ -314,5 +678,79 @@ mod tests {
         let input: HookInput = serde_json::from_str(json).unwrap();
         assert_eq!(input.session_id, "test-123");
         assert_eq!(input.prompt, Some(">remote-on".to_string()));
+        assert_eq!(input.stop_reason, None);
+
+        let json = r#"{"session_id":"test-123","reason":"max_tokens"}"#;
+        let input: HookInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.stop_reason.as_deref(), Some("max_tokens"));
+    }
+
+    #[test]
+    fn test_inline_commands_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+
+        let mut config = Config::default();
+        config.remote.allow_inline_commands = false;
+        config_mgr.save(&config).unwrap();
+
+        let input = HookInput {
+            session_id: "abc".to_string(),
+            prompt: Some(">remote-on".to_string()),
+            ..Default::default()
+        };
+
+        // 按普通 prompt 放行，不启用远程模式
+        let decision = handle_user_prompt_submit(&config_mgr, input).unwrap();
+        assert!(matches!(decision, HookDecision::Approve));
+        assert!(!config_mgr.is_remote_enabled());
+        assert!(!config_mgr.lock_file().exists());
+    }
+
+    #[test]
+    fn test_remote_off_without_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_mgr = ConfigManager::with_dir(dir.path().to_path_buf());
+
+        let decision = handle_remote_off(&config_mgr, "abc").unwrap();
+        assert!(matches!(decision, HookDecision::Block { .. }));
+        assert!(config_mgr.is_session_disabled("abc"));
+    }
+
+    #[test]
+    fn test_stop_reason_line() {
+        assert_eq!(stop_reason_line(None), "");
+        assert_eq!(stop_reason_line(Some("end_turn")), "");
+        assert_eq!(
+            stop_reason_line(Some("max_tokens")),
+            "\n- 停止原因: max_tokens（达到输出上限，可要求继续）"
+        );
+        assert_eq!(stop_reason_line(Some("tool_error")), "\n- 停止原因: tool_error");
+    }
+
+    #[test]
+    fn test_project_allowlist_matching() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        let repo = work.join("repo");
+        let scratch = dir.path().join("scratch");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::create_dir_all(&scratch).unwrap();
+
+        let allowlist = vec![work.display().to_string()];
+
+        assert!(is_project_allowed(&allowlist, Some(&repo)));
+        assert!(is_project_allowed(&allowlist, Some(&work)));
+        // 经过 .. 的路径规范化后再匹配
+        assert!(is_project_allowed(&allowlist, Some(&scratch.join("../work/repo"))));
+
+        assert!(!is_project_allowed(&allowlist, Some(&scratch)));
+        // 前缀按目录层级匹配，而不是字符串前缀
+        assert!(!is_project_allowed(&allowlist, Some(&dir.path().join("workspace"))));
+        assert!(!is_project_allowed(&allowlist, None));
+
+        // 白名单为空时不做限制
+        assert!(is_project_allowed(&[], Some(&scratch)));
+        assert!(is_project_allowed(&[], None));
     }
 }
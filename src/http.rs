@@ -0,0 +1,143 @@
+//! 出站 HTTP 请求
+//!
+//! Telegram、钉钉等 HTTP 渠道统一通过这里发送请求，所有请求都带有
+//! `notification.http_timeout_secs` 超时（为 0 时不限制），避免接口无响应时阻塞 hook
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+/// HTTP 请求超时
+///
+/// 以独立类型返回，便于与连接失败区分：请求可能已被处理，不应重试
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP 请求超时（{}秒）: {url}", .timeout.as_secs_f64())]
+pub struct HttpTimeout {
+    pub url: String,
+    pub timeout: Duration,
+}
+
+/// 无法建立连接（含连接超时），此时请求尚未发出，可以安全重试
+#[derive(Debug, thiserror::Error)]
+#[error("无法连接 {url}: {reason}")]
+pub struct HttpConnectError {
+    pub url: String,
+    pub reason: String,
+}
+
+/// 判断错误是否为连接失败
+pub fn is_connect_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<HttpConnectError>().is_some()
+}
+
+/// 创建带超时的客户端，`timeout` 为 0 时不限制
+fn client(timeout: Duration) -> Result<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if !timeout.is_zero() {
+        builder = builder.timeout(timeout);
+    }
+    builder.build().context("创建 HTTP 客户端失败")
+}
+
+/// 把发送失败转换为 [`HttpConnectError`]、[`HttpTimeout`] 或一般错误
+fn send_error(e: reqwest::Error, url: &str, timeout: Duration) -> anyhow::Error {
+    if e.is_connect() {
+        anyhow::Error::new(HttpConnectError {
+            url: redact_url(url),
+            reason: e.without_url().to_string(),
+        })
+    } else if e.is_timeout() {
+        anyhow::Error::new(HttpTimeout {
+            url: redact_url(url),
+            timeout,
+        })
+    } else {
+        anyhow::Error::new(e.without_url()).context(format!("HTTP 请求失败: {}", redact_url(url)))
+    }
+}
+
+/// 以 JSON 发送 POST 请求，返回响应体 JSON
+///
+/// 超时返回 [`HttpTimeout`]，非 2xx 状态码返回包含响应内容的错误
+pub fn post_json(url: &str, body: &serde_json::Value, timeout: Duration) -> Result<serde_json::Value> {
+    post_json_with_headers(url, &[], body, timeout)
+}
+
+/// 同 [`post_json`]，附带额外的请求头（如鉴权 token）
+pub fn post_json_with_headers(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &serde_json::Value,
+    timeout: Duration,
+) -> Result<serde_json::Value> {
+    let mut request = client(timeout)?.post(url).json(body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let response = request.send().map_err(|e| send_error(e, url, timeout))?;
+
+    let status = response.status();
+    let text = response.text().context("读取 HTTP 响应失败")?;
+    if !status.is_success() {
+        anyhow::bail!("HTTP 请求返回 {}: {}", status, text);
+    }
+
+    Ok(serde_json::from_str(&text).unwrap_or(serde_json::Value::Null))
+}
+
+/// 发送 GET 请求，返回响应文本
+///
+/// 超时返回 [`HttpTimeout`]，非 2xx 状态码返回错误
+pub fn get_text(url: &str, timeout: Duration) -> Result<String> {
+    let response = client(timeout)?.get(url).send().map_err(|e| send_error(e, url, timeout))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("HTTP 请求返回 {}: {}", status, redact_url(url));
+    }
+
+    response.text().context("读取 HTTP 响应失败")
+}
+
+/// 错误信息中只保留协议和主机，避免泄露路径或参数中的 token
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or_default()),
+        Err(_) => "<无效地址>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_post_json_timeout() {
+        // 接受连接但始终不响应的慢速服务
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/send", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let _conn = listener.accept();
+            std::thread::sleep(Duration::from_secs(5));
+        });
+
+        let err = post_json(&url, &serde_json::json!({"text": "hi"}), Duration::from_millis(300)).unwrap_err();
+        assert!(err.downcast_ref::<HttpTimeout>().is_some());
+        assert!(err.to_string().contains("超时"));
+        assert!(!err.to_string().contains("/send"));
+    }
+
+    #[test]
+    fn test_post_json_connect_error() {
+        // 绑定后立即释放端口，连接会被拒绝
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/send", listener.local_addr().unwrap())
+        };
+
+        let err = post_json(&url, &serde_json::json!({"text": "hi"}), Duration::from_secs(2)).unwrap_err();
+        assert!(is_connect_error(&err));
+        assert!(err.downcast_ref::<HttpTimeout>().is_none());
+    }
+}
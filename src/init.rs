@@ -4,14 +4,16 @@ use dialoguer::{Confirm, Input};
 use std::fs;
 use std::process::Command;
 
-use crate::config::{Config, ConfigManager};
+use crate::config::{self, Config, ConfigManager};
+use crate::output;
 
 pub fn run() -> Result<()> {
-    print_banner();
-
-    println!("正在检查环境...\n");
+    if !output::is_quiet() {
+        print_banner();
+        println!("正在检查环境...\n");
+    }
 
-    let deps = check_dependencies();
+    let deps = check_dependencies()?;
 
     if !deps.all_satisfied() {
         print_installation_guide(&deps);
@@ -59,7 +61,7 @@ impl DependencyStatus {
     }
 }
 
-fn check_dependencies() -> DependencyStatus {
+fn check_dependencies() -> Result<DependencyStatus> {
     let mut status = DependencyStatus {
         gewe_cli: None,
         claude_code: None,
@@ -96,9 +98,7 @@ fn check_dependencies() -> DependencyStatus {
 
     // 检查 plugin
     print!("  检查 gewe-cc-plugin... ");
-    let settings_file = dirs::home_dir()
-        .unwrap()
-        .join(".claude/settings.json");
+    let settings_file = config::home_dir()?.join(".claude/settings.json");
 
     if settings_file.exists() {
         if let Ok(content) = fs::read_to_string(&settings_file) {
@@ -116,7 +116,7 @@ fn check_dependencies() -> DependencyStatus {
     }
 
     println!();
-    status
+    Ok(status)
 }
 
 fn print_installation_guide(deps: &DependencyStatus) {
@@ -304,10 +304,12 @@ fn create_config() -> Result<()> {
 }
 
 fn print_success_message() {
-    println!("{}", "═══════════════════════════════════════".green());
-    println!("{}", "  ✅ 初始化完成！".green().bold());
-    println!("{}", "═══════════════════════════════════════".green());
-    println!();
+    output::banner("  ✅ 初始化完成！", Color::Green);
+
+    if output::is_quiet() {
+        return;
+    }
+
     println!("{}", "下一步操作:".bright_white().bold());
     println!();
     println!("  {} 启用全局远程模式:", "1.".bright_cyan());
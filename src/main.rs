@@ -1,14 +1,30 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::builder::PossibleValuesParser;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::io::IsTerminal;
 
+mod activity;
+mod assets;
+mod backend;
 mod config;
+mod export;
+mod format;
+mod history;
 mod hook;
+mod http;
 mod init;
+mod monitor;
 mod notify;
+mod output;
+mod ratelimit;
 mod remote;
+mod render;
 mod sanitize;
 mod server;
+mod sessions;
 mod transcript;
+mod util;
+mod watch;
 
 use hook::HookHandler;
 
@@ -16,6 +32,10 @@ use hook::HookHandler;
 #[command(name = "gewe-cc")]
 #[command(version, about = "Claude Code 远程协作模式命令行工具", long_about = None)]
 struct Cli {
+    /// 静默模式：不输出横幅等装饰性内容
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -55,6 +75,18 @@ enum Commands {
         /// Transcript 展示域名
         #[arg(long)]
         transcript_domain: Option<String>,
+
+        /// 将指定配置项重置为默认值（可多次指定，如 transcript_domain）
+        #[arg(long, value_name = "FIELD")]
+        unset: Vec<String>,
+
+        /// 检查配置是否完整、一致（有未通过项时退出码为 1）
+        #[arg(long, conflicts_with_all = ["wxid", "listen", "timeout", "transcript_domain", "unset"])]
+        validate: bool,
+
+        /// 将旧版本配置文件迁移到当前版本并写回（原文件备份为 config.toml.bak）
+        #[arg(long, conflicts_with_all = ["wxid", "listen", "timeout", "transcript_domain", "unset", "validate"])]
+        migrate: bool,
     },
 
     /// 启动 HTTP 服务器（用于展示 transcript）
@@ -62,6 +94,34 @@ enum Commands {
         /// 监听端口
         #[arg(short, long, default_value = "4400")]
         port: u16,
+
+        /// 启用 /metrics 指标接口（Prometheus 格式）
+        #[arg(long)]
+        metrics: bool,
+
+        /// 监听配置文件变化并自动重新加载
+        #[arg(long)]
+        watch: bool,
+
+        /// 在根路径 / 提供会话索引页（会公开所有会话 ID，仅在可信网络中开启）
+        #[arg(long)]
+        index: bool,
+
+        /// 使用 HTTPS（需同时指定 --cert 和 --key）
+        #[arg(long, requires_all = ["cert", "key"])]
+        tls: bool,
+
+        /// TLS 证书文件（PEM 格式，可包含完整证书链）
+        #[arg(long, requires = "tls")]
+        cert: Option<std::path::PathBuf>,
+
+        /// TLS 私钥文件（PEM 格式）
+        #[arg(long, requires = "tls")]
+        key: Option<std::path::PathBuf>,
+
+        /// 启动后在默认浏览器中打开本地地址（无图形界面时跳过）
+        #[arg(long)]
+        open: bool,
     },
 
     /// 发送链接卡片并等待回复
@@ -71,8 +131,74 @@ enum Commands {
         session_id: String,
 
         /// 任务摘要
+        #[arg(long, required_unless_present = "auto_summary")]
+        summary: Option<String>,
+
+        /// 从 transcript 中提取最后一条助手消息作为摘要
+        #[arg(long, conflicts_with = "summary")]
+        auto_summary: bool,
+
+        /// 可选：任务状态，显示在卡片描述的首行；failed 时卡片标题改为「任务失败」
+        #[arg(long, value_enum)]
+        status: Option<notify::TaskStatus>,
+
+        /// 可选：任务耗时（秒），显示在卡片描述的首行
+        #[arg(long)]
+        duration: Option<u64>,
+
+        /// 可选：等待回复的超时时间（秒），不指定时使用配置中的默认值
+        #[arg(short, long, conflicts_with = "no_wait")]
+        timeout: Option<u64>,
+
+        /// 可选：临时覆盖配置中的超时行为（abort 报错退出，continue 返回 default_reply_on_timeout）
+        #[arg(long, value_parser = PossibleValuesParser::new(notify::ON_TIMEOUT_MODES), conflicts_with = "no_wait")]
+        on_timeout: Option<String>,
+
+        /// 只发送卡片并输出 transcript 地址，不等待回复（适合仅作告知的进度通知）
+        #[arg(long)]
+        no_wait: bool,
+
+        /// 回复的输出格式：text 只输出回复内容，json 输出 {"reply","sender","timed_out"}
+        #[arg(long, value_enum, default_value_t = output::OutputFormat::Text, conflicts_with = "no_wait")]
+        output: output::OutputFormat,
+
+        /// 可选：临时覆盖配置中的通知渠道
+        #[arg(long, value_parser = PossibleValuesParser::new(format::SUPPORTED_CHANNELS))]
+        channel: Option<String>,
+    },
+
+    /// 监视进行中的会话，停止时发送链接卡片并等待回复
+    Watch {
+        /// Session ID
+        #[arg(long)]
+        session_id: String,
+
+        /// transcript 文件持续多少秒无变化视为会话已停止
+        #[arg(long, default_value = "30")]
+        idle_secs: u64,
+
+        /// 可选：等待回复的超时时间（秒），不指定时使用配置中的默认值
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        /// 可选：临时覆盖配置中的通知渠道
+        #[arg(long, value_parser = PossibleValuesParser::new(format::SUPPORTED_CHANNELS))]
+        channel: Option<String>,
+    },
+
+    /// 监测会话空闲，transcript 持续无变化时发送空闲通知（无需配置 Notification Hook）
+    Monitor {
+        /// Session ID
         #[arg(long)]
-        summary: String,
+        session_id: String,
+
+        /// transcript 文件持续多少秒无变化时发送通知
+        #[arg(long, default_value = "60")]
+        idle_secs: u64,
+
+        /// 可选：临时覆盖配置中的通知渠道
+        #[arg(long, value_parser = PossibleValuesParser::new(format::SUPPORTED_CHANNELS))]
+        channel: Option<String>,
     },
 
     /// 发送消息并等待回复
@@ -92,9 +218,36 @@ enum Commands {
         /// 可选：超时时间（秒）
         #[arg(long, short = 't')]
         timeout: Option<u64>,
+
+        /// 可选：会话 ID（提供时记录到该会话的回复历史）
+        #[arg(long)]
+        session_id: Option<String>,
+
+        /// 可选：临时覆盖配置中的超时行为（abort 报错退出，continue 返回 default_reply_on_timeout）
+        #[arg(long, value_parser = PossibleValuesParser::new(notify::ON_TIMEOUT_MODES))]
+        on_timeout: Option<String>,
+
+        /// 可选：临时覆盖配置中的通知渠道（等待回复目前仅支持 wechat）
+        #[arg(long, value_parser = PossibleValuesParser::new(notify::WAIT_REPLY_CHANNELS))]
+        channel: Option<String>,
+
+        /// 回复的输出格式：text 只输出回复内容，json 输出 {"reply","sender","timed_out"}
+        #[arg(long, value_enum, default_value_t = output::OutputFormat::Text)]
+        output: output::OutputFormat,
     },
 
-    /// 发送通知（不等待回复）
+    /// 发送确认请求并等待「是/否」回复（拒绝时退出码为 1）
+    Confirm {
+        /// 需要确认的操作描述
+        #[arg(short = 'M', long)]
+        message: String,
+
+        /// 可选：超时时间（秒）
+        #[arg(long, short = 't')]
+        timeout: Option<u64>,
+    },
+
+    /// 发送通知（默认不等待回复，--wait 时等同于 wait-reply）
     Notify {
         /// 消息内容
         #[arg(short = 'M', long)]
@@ -103,18 +256,160 @@ enum Commands {
         /// 可选：临时覆盖配置中的目标微信 ID
         #[arg(long)]
         to_wxid: Option<String>,
+
+        /// 发送后等待回复并输出
+        #[arg(long)]
+        wait: bool,
+
+        /// 可选：临时覆盖配置中的监听地址（需配合 --wait）
+        #[arg(long, requires = "wait")]
+        listen: Option<String>,
+
+        /// 可选：超时时间（秒，需配合 --wait）
+        #[arg(long, short = 't', requires = "wait")]
+        timeout: Option<u64>,
+
+        /// 可选：会话 ID（需配合 --wait，提供时记录到该会话的回复历史）
+        #[arg(long, requires = "wait")]
+        session_id: Option<String>,
+
+        /// 可选：临时覆盖配置中的通知渠道
+        #[arg(long, value_parser = PossibleValuesParser::new(format::SUPPORTED_CHANNELS))]
+        channel: Option<String>,
+
+        /// 可选：任务状态，在消息开头显示「✅ 成功」或「❌ 失败」
+        #[arg(long, value_enum)]
+        status: Option<notify::TaskStatus>,
+    },
+
+    /// 导出会话为 zip（自包含 HTML + 原始 JSONL + 图片）
+    Export {
+        /// Session ID
+        #[arg(long)]
+        session_id: String,
+
+        /// 输出文件路径
+        #[arg(long, short = 'o')]
+        out: std::path::PathBuf,
+    },
+
+    /// 渲染 transcript 页面预览（调整样式、主题时使用）
+    Render {
+        /// 渲染内置的示例 transcript（包含各类内容块）
+        #[arg(long, required_unless_present = "session_id", conflicts_with = "session_id")]
+        sample: bool,
+
+        /// 渲染指定会话的 transcript
+        #[arg(long)]
+        session_id: Option<String>,
+
+        /// 代码高亮主题（highlight.js 主题名），覆盖配置中的 code_theme
+        #[arg(long)]
+        theme: Option<String>,
+
+        /// 输出 HTML 文件路径，不指定时输出到 stdout
+        #[arg(long, short = 'o')]
+        out: Option<std::path::PathBuf>,
+    },
+
+    /// 输出会话最近一条微信回复（中断后恢复最新指令）
+    LastReply {
+        /// Session ID
+        #[arg(long)]
+        session_id: String,
+    },
+
+    /// 输出会话解析到的 transcript 文件路径（排查页面 404，未找到时退出码为 1）
+    Resolve {
+        /// Session ID
+        #[arg(long)]
+        session_id: String,
+    },
+
+    /// 静态资源管理
+    Assets {
+        #[command(subcommand)]
+        action: AssetsCommand,
+    },
+
+    /// 会话相关操作
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsCommand,
+    },
+
+    /// 查看活动日志（发送通知、收到回复等）
+    Logs {
+        /// 输出最后 N 行
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+
+        /// 持续输出新追加的日志（类似 tail -f），按 Ctrl+C 结束
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// 生成 shell 补全脚本（输出到 stdout）
+    Completions {
+        /// Shell 类型
+        shell: clap_complete::Shell,
     },
 
     /// 处理 Hook 事件（由 plugin 调用，非用户命令）
     Hook {
         /// Hook 类型：user-prompt-submit 或 stop
         hook_type: String,
+
+        /// 以带缩进的格式输出决策 JSON（便于手动调试）
+        #[arg(long, overrides_with = "compact")]
+        pretty: bool,
+
+        /// 以紧凑格式输出决策 JSON（默认）
+        #[arg(long, overrides_with = "pretty")]
+        compact: bool,
+
+        /// 调试：解析前把 stdin 原始输入写入该文件（也可通过 GEWE_CC_HOOK_DUMP 环境变量指定）
+        #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+        dump_input: Option<std::path::PathBuf>,
+
+        /// 调试：从文件读取输入代替 stdin（重放 --dump-input 保存的内容）。
+        /// 与真实 Hook 一样会产生副作用（发送通知、等待回复、修改会话状态）
+        #[arg(long, value_name = "FILE")]
+        replay: Option<std::path::PathBuf>,
+    },
+}
+
+/// 根据 NO_COLOR 环境变量和 stdout 是否为终端决定是否输出颜色
+fn configure_color() {
+    let no_color = std::env::var("NO_COLOR").ok();
+    let enabled = output::should_colorize(no_color.as_deref(), std::io::stdout().is_terminal());
+    colored::control::set_override(enabled);
+}
+
+#[derive(Subcommand)]
+enum AssetsCommand {
+    /// 生成默认的卡片缩略图 thumb.png（已存在时跳过）
+    Init,
+}
+
+#[derive(Subcommand)]
+enum SessionsCommand {
+    /// 列出已注册的会话，等待回复的会话排在前面
+    List,
+
+    /// 查看会话的回复历史
+    Replies {
+        /// Session ID
+        session_id: String,
     },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    output::set_quiet(cli.quiet);
+    configure_color();
+
     match cli.command {
         Commands::Init => {
             init::run()?;
@@ -128,36 +423,187 @@ fn main() -> Result<()> {
         Commands::Status => {
             remote::handle_status()?;
         }
-        Commands::Config { wxid, listen, timeout, transcript_domain } => {
-            remote::handle_config(wxid, listen, timeout, transcript_domain)?;
+        Commands::Config { migrate: true, .. } => {
+            remote::handle_migrate()?;
+        }
+        Commands::Config { validate: true, .. } => {
+            if !remote::handle_validate()? {
+                std::process::exit(1);
+            }
         }
-        Commands::Serve { port } => {
+        Commands::Config { wxid, listen, timeout, transcript_domain, unset, .. } => {
+            remote::handle_config(wxid, listen, timeout, transcript_domain, unset)?;
+        }
+        Commands::Serve {
+            port,
+            metrics,
+            watch,
+            index,
+            tls,
+            cert,
+            key,
+            open,
+        } => {
+            let options = server::ServeOptions {
+                port,
+                metrics,
+                watch,
+                index,
+                tls: if tls { cert.zip(key) } else { None },
+                open,
+            };
+
             // 使用 tokio 运行时启动 HTTP 服务器
             let runtime = tokio::runtime::Runtime::new()?;
             runtime.block_on(async {
-                server::start_server(port).await
+                server::start_server(options).await
             })?;
         }
-        Commands::SendLink { session_id, summary } => {
-            let reply = notify::send_link_and_wait(session_id, summary)?;
-            println!("{}", reply);
+        Commands::SendLink {
+            session_id,
+            summary,
+            auto_summary,
+            status,
+            duration,
+            timeout,
+            on_timeout,
+            no_wait,
+            channel,
+            output,
+        } => {
+            if let Some(channel) = channel {
+                notify::set_channel_override(channel)?;
+            }
+            if let Some(mode) = on_timeout {
+                notify::set_on_timeout_override(mode)?;
+            }
+            let summary = match summary {
+                Some(summary) if !auto_summary => summary,
+                _ => notify::summary_from_transcript(&session_id)?,
+            };
+            let description = notify::build_card_description(status, duration, &summary);
+            if no_wait {
+                match notify::send_link_only(&session_id, &description, status)? {
+                    Some(url) => println!("{}", url),
+                    None => println!("✅ 通知已发送（未配置 transcript_domain，无在线链接）"),
+                }
+                return Ok(());
+            }
+            notify::Reply::print_result(notify::send_link_and_wait(session_id, description, status, timeout), output)?;
+        }
+        Commands::Watch {
+            session_id,
+            idle_secs,
+            timeout,
+            channel,
+        } => {
+            if let Some(channel) = channel {
+                notify::set_channel_override(channel)?;
+            }
+            watch::run(&session_id, idle_secs, timeout)?;
+        }
+        Commands::Monitor {
+            session_id,
+            idle_secs,
+            channel,
+        } => {
+            if let Some(channel) = channel {
+                notify::set_channel_override(channel)?;
+            }
+            monitor::run(&session_id, idle_secs)?;
         }
         Commands::WaitReply {
             message,
             to_wxid,
             listen,
             timeout,
+            session_id,
+            on_timeout,
+            channel,
+            output,
         } => {
-            let reply = notify::wait_reply(message, to_wxid, listen, timeout)?;
-            println!("{}", reply);
+            if let Some(channel) = channel {
+                notify::set_channel_override(channel)?;
+            }
+            if let Some(mode) = on_timeout {
+                notify::set_on_timeout_override(mode)?;
+            }
+            let result = notify::notify_impl(message, to_wxid, true, listen, timeout, session_id.as_deref());
+            if let Some(reply) = result.transpose() {
+                notify::Reply::print_result(reply, output)?;
+            }
         }
-        Commands::Notify { message, to_wxid } => {
-            notify::send_notification(message, to_wxid)?;
-            println!("✅ 消息已发送");
+        Commands::Confirm { message, timeout } => {
+            if notify::wait_confirm(message, timeout)? {
+                println!("✅ 已确认");
+            } else {
+                println!("❌ 已拒绝");
+                std::process::exit(1);
+            }
         }
-        Commands::Hook { hook_type } => {
-            let decision = HookHandler::handle_from_stdin(&hook_type)?;
-            decision.output()?;
+        Commands::Notify {
+            message,
+            to_wxid,
+            wait,
+            listen,
+            timeout,
+            session_id,
+            channel,
+            status,
+        } => {
+            if let Some(channel) = channel {
+                notify::set_channel_override(channel)?;
+            }
+            let message = match status {
+                Some(_) => notify::build_card_description(status, None, &message),
+                None => message,
+            };
+            match notify::notify_impl(message, to_wxid, wait, listen, timeout, session_id.as_deref())? {
+                Some(reply) => println!("{}", reply.text),
+                None => println!("✅ 消息已发送"),
+            }
+        }
+        Commands::Export { session_id, out } => {
+            export::run(&session_id, &out)?;
+        }
+        Commands::Render { session_id, theme, out, .. } => {
+            render::run(session_id.as_deref(), theme, out.as_deref())?;
+        }
+        Commands::LastReply { session_id } => {
+            sessions::handle_last_reply(&session_id)?;
+        }
+        Commands::Resolve { session_id } => {
+            if !sessions::handle_resolve(&session_id)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Assets { action } => match action {
+            AssetsCommand::Init => {
+                assets::handle_init()?;
+            }
+        },
+        Commands::Sessions { action } => match action {
+            SessionsCommand::List => {
+                sessions::handle_list()?;
+            }
+            SessionsCommand::Replies { session_id } => {
+                sessions::handle_replies(&session_id)?;
+            }
+        },
+        Commands::Logs { lines, follow } => {
+            activity::run(lines, follow)?;
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "gewe-cc", &mut std::io::stdout());
+        }
+        Commands::Hook {
+            hook_type,
+            pretty,
+            compact,
+            dump_input,
+            replay,
+        } => {
+            HookHandler::run(&hook_type, pretty && !compact, dump_input, replay.as_deref())?;
         }
     }
 
@@ -0,0 +1,130 @@
+//! 前台监测会话空闲
+//!
+//! 不依赖 Claude Code 的 Notification Hook：轮询 transcript 文件的修改时间，
+//! 持续一段时间没有变化时发送空闲通知，适用于未配置 Hook 的环境
+
+use anyhow::Result;
+use colored::*;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::hook;
+use crate::notify;
+use crate::output::{self, banner};
+use crate::server;
+
+/// 轮询 transcript 文件的最大间隔（monitor 与 watch 共用）
+pub(crate) const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 监测会话，transcript 持续 `idle_secs` 秒无变化时发送空闲通知
+///
+/// 每段空闲只通知一次，文件再次变化后重新计时；按 Ctrl+C 结束
+pub fn run(session_id: &str, idle_secs: u64) -> Result<()> {
+    let transcript_path = server::infer_transcript_path(session_id)
+        .ok_or_else(|| anyhow::anyhow!("未找到会话的 transcript: {}", session_id))?;
+    let idle = Duration::from_secs(idle_secs.max(1));
+    let poll_interval = idle.min(MAX_POLL_INTERVAL);
+
+    banner("  ⏳ 正在监测会话空闲", Color::Cyan);
+    if !output::is_quiet() {
+        println!("  {} {}", "会话 ID:".dimmed(), session_id);
+        println!("  {} {}", "Transcript:".dimmed(), transcript_path.display());
+        println!("  {} {}s 无变化时发送通知", "判定:".dimmed(), idle.as_secs());
+        println!();
+    }
+
+    let mut tracker = IdleTracker::new(modified(&transcript_path), Instant::now());
+
+    loop {
+        std::thread::sleep(poll_interval);
+
+        if !tracker.observe(modified(&transcript_path), Instant::now(), idle) {
+            continue;
+        }
+
+        println!("💤 会话已空闲 {}s，发送通知...", idle.as_secs());
+        if let Err(e) = send_idle_notification(session_id, idle.as_secs()) {
+            eprintln!("⚠️ 发送空闲通知失败: {}", e);
+        }
+    }
+}
+
+/// 发送空闲通知，消息内容与收件人的解析方式与 Notification Hook 一致
+fn send_idle_notification(session_id: &str, idle_secs: u64) -> Result<()> {
+    let config = notify::load_config()?;
+    let cwd = std::env::current_dir().ok();
+    let project = config.resolve_project_name(cwd.as_deref());
+    let message = hook::idle_notification(&config, &project, session_id, idle_secs);
+    let wxid = config.resolve_wxid_for_cwd(cwd.as_deref());
+    notify::dispatch_notification(&config, wxid, &message)
+}
+
+/// transcript 文件的修改时间，文件不存在时为 None
+pub(crate) fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 根据 transcript 修改时间判断会话何时进入空闲（monitor 与 watch 共用）
+pub(crate) struct IdleTracker {
+    last_modified: Option<SystemTime>,
+    changed_at: Instant,
+    /// 本段空闲是否已通知过
+    notified: bool,
+}
+
+impl IdleTracker {
+    pub(crate) fn new(modified: Option<SystemTime>, now: Instant) -> Self {
+        Self {
+            last_modified: modified,
+            changed_at: now,
+            notified: false,
+        }
+    }
+
+    /// 记录一次观察结果，本段空闲达到 `idle` 且尚未通知时返回 true
+    pub(crate) fn observe(&mut self, modified: Option<SystemTime>, now: Instant, idle: Duration) -> bool {
+        if modified != self.last_modified {
+            self.last_modified = modified;
+            self.changed_at = now;
+            self.notified = false;
+            return false;
+        }
+
+        if self.notified || modified.is_none() || now.duration_since(self.changed_at) < idle {
+            return false;
+        }
+
+        self.notified = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_tracker() {
+        let idle = Duration::from_secs(60);
+        let start = Instant::now();
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let t1 = t0 + Duration::from_secs(1);
+        let mut tracker = IdleTracker::new(Some(t0), start);
+
+        assert!(!tracker.observe(Some(t0), start + Duration::from_secs(30), idle));
+        assert!(tracker.observe(Some(t0), start + Duration::from_secs(60), idle));
+
+        // 同一段空闲只通知一次
+        assert!(!tracker.observe(Some(t0), start + Duration::from_secs(120), idle));
+
+        // 文件变化后重新计时
+        assert!(!tracker.observe(Some(t1), start + Duration::from_secs(130), idle));
+        assert!(!tracker.observe(Some(t1), start + Duration::from_secs(150), idle));
+        assert!(tracker.observe(Some(t1), start + Duration::from_secs(190), idle));
+
+        // 文件不存在时不通知
+        let mut missing = IdleTracker::new(None, start);
+        assert!(!missing.observe(None, start + Duration::from_secs(600), idle));
+    }
+}
@@ -1,7 +1,172 @@
 use anyhow::{Context, Result};
-use std::process::Command;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-use crate::config::ConfigManager;
+use crate::activity;
+use crate::backend::{self, LinkCard, WaitRequest};
+use crate::config::{self, Config, ConfigManager};
+use crate::format::{self, OutgoingMessage};
+use crate::history::ReplyHistory;
+use crate::http;
+use crate::output::{self, OutputFormat};
+use crate::sanitize::sanitize_wxid;
+use crate::server::{self, PendingReplyRegistry, SessionRegistry};
+use crate::transcript;
+use crate::util::{truncate_chars, truncate_graphemes};
+
+/// 本次运行临时指定的通知渠道（由 `--channel` 参数设置）
+static CHANNEL_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// 临时覆盖配置中的通知渠道，仅对当前进程生效
+pub fn set_channel_override(channel: String) -> Result<()> {
+    format::formatter_for(&channel)?;
+    CHANNEL_OVERRIDE
+        .set(channel)
+        .map_err(|_| anyhow::anyhow!("通知渠道已被覆盖"))
+}
+
+/// wait-reply 可用的通知渠道：直接等待回复通过微信消息后端收发，只支持 wechat
+pub const WAIT_REPLY_CHANNELS: &[&str] = &["wechat"];
+
+/// 检查 send-link / watch 能否接收当前渠道的回复，需在发送任何消息之前调用
+///
+/// wechat 渠道可直接等待回复；其他渠道的回复只能由 serve 进程的 webhook 接收
+/// （目前只有 telegram 提供 `/webhook/telegram`），需设置 `reply_mode = "server"`
+fn ensure_replies_supported(config: &Config) -> Result<()> {
+    let channel = config.notification.channel.as_str();
+    match channel {
+        "wechat" => Ok(()),
+        "telegram" if config.notification.reply_mode == "server" => Ok(()),
+        "telegram" => anyhow::bail!(
+            "telegram 渠道的回复需由 serve 进程接收\n\
+             请在配置中设置 reply_mode = \"server\"，并将 Bot 的 webhook 设置为 https://<域名>/webhook/telegram"
+        ),
+        _ => anyhow::bail!("{} 渠道暂不支持接收回复，请使用 wechat 或 telegram，或加上 --no-wait 只发送通知", channel),
+    }
+}
+
+/// 等待回复超时后的行为（见 `notification.on_timeout`）
+pub const ON_TIMEOUT_MODES: &[&str] = &["abort", "continue"];
+
+/// 本次运行临时指定的超时行为（由 `--on-timeout` 参数设置）
+static ON_TIMEOUT_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// 临时覆盖配置中的超时行为，仅对当前进程生效
+pub fn set_on_timeout_override(mode: String) -> Result<()> {
+    ON_TIMEOUT_OVERRIDE
+        .set(mode)
+        .map_err(|_| anyhow::anyhow!("超时行为已被覆盖"))
+}
+
+/// 加载配置，并应用 `--channel` 等命令行覆盖
+pub(crate) fn load_config() -> Result<Config> {
+    let mut config = ConfigManager::new()?.load()?;
+    if let Some(channel) = CHANNEL_OVERRIDE.get() {
+        config.notification.channel = channel.clone();
+    }
+    if let Some(mode) = ON_TIMEOUT_OVERRIDE.get() {
+        config.notification.on_timeout = mode.clone();
+    }
+    Ok(config)
+}
+
+/// 等待回复的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reply {
+    /// 回复内容（超时按 continue 处理时为 `default_reply_on_timeout`）
+    #[serde(rename = "reply")]
+    pub text: String,
+    /// 回复来自的微信 ID，即消息的接收方（只接受它发来的回复）；超时未收到回复时为 None
+    pub sender: Option<String>,
+    /// 是否因超时而使用了默认回复
+    pub timed_out: bool,
+}
+
+impl Reply {
+    /// 按输出格式打印到 stdout：text 为回复原文，json 为单行 JSON（回复中的换行会被转义）
+    pub fn print(&self, format: OutputFormat) -> Result<()> {
+        match format {
+            OutputFormat::Text => println!("{}", self.text),
+            OutputFormat::Json => println!("{}", serde_json::to_string(self).context("序列化回复失败")?),
+        }
+        Ok(())
+    }
+
+    /// 打印等待回复的结果
+    ///
+    /// `on_timeout = abort` 下超时时，json 格式先输出 `{"reply":null,"sender":null,"timed_out":true}`，
+    /// 再原样返回错误，使进程以非零状态退出；其他错误不输出任何内容
+    pub fn print_result(result: Result<Reply>, format: OutputFormat) -> Result<()> {
+        match result {
+            Ok(reply) => reply.print(format),
+            Err(e) => {
+                if format == OutputFormat::Json && e.downcast_ref::<ReplyTimeout>().is_some() {
+                    println!("{}", timed_out_json());
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+/// abort 模式下超时的 json 输出
+fn timed_out_json() -> serde_json::Value {
+    serde_json::json!({"reply": null, "sender": null, "timed_out": true})
+}
+
+/// 按 `notification.on_timeout` 处理等待回复的结果
+///
+/// continue 模式下把 [`ReplyTimeout`] 换成 `default_reply_on_timeout`，
+/// 让调用方（如 remote-control skill）收到明确的指令而不是报错；其他错误原样返回
+fn apply_timeout_policy(result: Result<String>, sender: &str, config: &Config) -> Result<Reply> {
+    let reply = |text: String, timed_out: bool| Reply {
+        text,
+        sender: (!timed_out).then(|| sender.to_string()),
+        timed_out,
+    };
+
+    match result {
+        Err(e) if config.notification.on_timeout == "continue" && e.downcast_ref::<ReplyTimeout>().is_some() => {
+            let text = config.notification.default_reply_on_timeout.clone();
+            eprintln!("⏰ {}，按 on_timeout = continue 返回默认回复「{}」", e, text);
+            Ok(reply(text, true))
+        }
+        result => result.map(|text| reply(text, false)),
+    }
+}
+
+/// notify 与 wait-reply 命令的共同实现
+///
+/// `wait` 为 false 时仅发送通知并返回 None；为 true 时等待回复并返回回复内容，
+/// 超时按 `notification.on_timeout` 处理，其余参数含义同 [`wait_reply`]（仅在等待时生效）
+pub fn notify_impl(
+    message: String,
+    to_wxid: Option<String>,
+    wait: bool,
+    listen: Option<String>,
+    timeout: Option<u64>,
+    session_id: Option<&str>,
+) -> Result<Option<Reply>> {
+    if wait {
+        let config = load_config()?;
+        // 直接等待回复时消息通过微信消息后端发送，其他渠道无法在这里接收回复
+        if let Some(channel) = CHANNEL_OVERRIDE.get().filter(|c| !WAIT_REPLY_CHANNELS.contains(&c.as_str())) {
+            anyhow::bail!("{} 渠道暂不支持等待回复，--wait 目前仅支持 wechat", channel);
+        }
+        let sender = to_wxid.clone().unwrap_or_else(|| config.notification.wxid.clone());
+        let result = wait_reply(message, to_wxid, listen, timeout, session_id);
+        apply_timeout_policy(result, &sender, &config).map(Some)
+    } else {
+        send_notification(message, to_wxid).map(|_| None)
+    }
+}
 
 /// 发送消息并等待回复
 ///
@@ -11,6 +176,7 @@ use crate::config::ConfigManager;
 /// * `to_wxid` - 可选的目标微信ID，如果不提供则使用配置文件中的默认值
 /// * `listen` - 可选的监听地址，如果不提供则使用配置文件中的默认值
 /// * `timeout` - 可选的超时时间（秒），如果不提供则使用配置文件中的默认值
+/// * `session_id` - 可选的会话 ID，提供时回复会记录到该会话的回复历史
 ///
 /// # 返回
 ///
@@ -20,9 +186,109 @@ pub fn wait_reply(
     to_wxid: Option<String>,
     listen: Option<String>,
     timeout: Option<u64>,
+    session_id: Option<&str>,
+) -> Result<String> {
+    wait_reply_with_progress(message, to_wxid, listen, timeout, session_id, None)
+}
+
+/// 确认 gewe-cli 可执行文件存在
+///
+/// `command` 含路径分隔符时按路径检查，否则在 PATH 中查找。
+/// 预先检查可以给出明确的提示，而不是调用时含糊的系统错误
+pub fn ensure_gewe_cli(command: &str) -> Result<PathBuf> {
+    find_command(command, std::env::var_os("PATH").as_deref())
+        .ok_or_else(|| anyhow::anyhow!("未找到 gewe-cli（命令：{}），请运行 gewe-cc init 查看安装指引", command))
+}
+
+fn find_command(command: &str, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        path.is_file() || (cfg!(windows) && path.extension().is_none() && path.with_extension("exe").is_file())
+    };
+
+    if command.is_empty() {
+        return None;
+    }
+
+    let path = Path::new(command);
+    if path.components().count() > 1 || path.is_absolute() {
+        return is_executable(path).then(|| path.to_path_buf());
+    }
+
+    std::env::split_paths(path_var?)
+        .map(|dir| dir.join(command))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// 在限定时间内未收到回复
+///
+/// 单独定义错误类型，便于调用方区分超时与其他失败（如重发提醒后继续等待）
+#[derive(Debug)]
+pub struct ReplyTimeout(pub u64);
+
+impl std::fmt::Display for ReplyTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "等待微信回复超时（{}秒）", self.0)
+    }
+}
+
+impl std::error::Error for ReplyTimeout {}
+
+/// 未收到回复时重发的提醒
+const REMINDER_MESSAGE: &str = "还在等你的回复～";
+
+/// 分段等待回复，每段超时后重发提醒，直到收到回复、提醒次数用完或总超时
+///
+/// `wait` 负责发送消息并在给定秒数内等待回复（0 表示不限时），
+/// 超时时应返回 [`ReplyTimeout`]
+fn wait_with_reminders(
+    prompt: String,
+    timeout_secs: u64,
+    interval_secs: u64,
+    max_reminders: u32,
+    mut wait: impl FnMut(String, u64) -> Result<String>,
+) -> Result<String> {
+    let started = Instant::now();
+    let mut message = prompt;
+    let mut reminders = 0;
+
+    loop {
+        let remaining = (timeout_secs > 0).then(|| timeout_secs.saturating_sub(started.elapsed().as_secs()));
+        if remaining == Some(0) {
+            return Err(ReplyTimeout(timeout_secs).into());
+        }
+
+        let reminding = interval_secs > 0
+            && reminders < max_reminders
+            && remaining.is_none_or(|remaining| remaining > interval_secs);
+        let window = if reminding { interval_secs } else { remaining.unwrap_or(0) };
+
+        match wait(message, window) {
+            Err(e) if e.downcast_ref::<ReplyTimeout>().is_some() => {
+                if !reminding {
+                    return Err(ReplyTimeout(timeout_secs).into());
+                }
+                reminders += 1;
+                message = REMINDER_MESSAGE.to_string();
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 发送消息并等待回复，等待期间按 `progress` 间隔向 stderr 输出进度
+fn wait_reply_with_progress(
+    message: String,
+    to_wxid: Option<String>,
+    listen: Option<String>,
+    timeout: Option<u64>,
+    session_id: Option<&str>,
+    progress: Option<Duration>,
 ) -> Result<String> {
-    let config_mgr = ConfigManager::new()?;
-    let config = config_mgr.load()?;
+    let config = load_config()?;
+
+    // 直接等待回复始终通过微信消息后端收发（与 notification.channel 无关）
+    let backend = backend::from_config(&config)?;
+    ensure_not_quiet(&config)?;
 
     // 使用参数或配置文件中的值
     let wxid = to_wxid.unwrap_or(config.notification.wxid);
@@ -40,137 +306,705 @@ pub fn wait_reply(
         );
     }
 
-    // 调用 gewe-cli wait-reply
-    let mut cmd = Command::new(&config.gewe_cli.command);
-    cmd.args([
-        "wait-reply",
-        "--to-wxid",
-        &wxid,
-        "--listen",
-        &listen_addr,
-        "-M",
-        &format!("text:{}", message),
-    ]);
-
-    // 如果 timeout_secs 为 0，不传 --timeout 参数（使用 gewe-cli 的默认值：无限等待）
-    if timeout_secs > 0 {
-        cmd.args(["--timeout", &timeout_secs.to_string()]);
-    }
-
-    let output = cmd
-        .output()
-        .context(format!(
-            "调用 {} 失败，请确认已安装 gewe-cli",
-            config.gewe_cli.command
-        ))?;
-
-    if !output.status.success() {
-        let exit_code = output.status.code().unwrap_or(-1);
-        match exit_code {
-            1 => {
-                if timeout_secs > 0 {
-                    anyhow::bail!("等待微信回复超时（{}秒）", timeout_secs);
-                } else {
-                    anyhow::bail!("等待微信回复超时");
-                }
-            }
-            2 => anyhow::bail!("发送微信消息失败"),
-            3 => anyhow::bail!("webhook 启动失败，请检查监听地址: {}", listen_addr),
-            _ => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                anyhow::bail!("gewe-cli 执行失败 (exit code {}): {}", exit_code, stderr);
-            }
-        }
+    // 预先检查监听端口，避免等待时才以含糊的错误失败
+    check_listen_available(&listen_addr)?;
+
+    let mut reply = backend.wait_reply(&WaitRequest {
+        wxid: &wxid,
+        message: &message,
+        listen: &listen_addr,
+        timeout_secs,
+        progress,
+    })?;
+
+    // 按配置去掉引用内容
+    if config.notification.normalize_replies {
+        reply = normalize_reply(&reply);
+    }
+    reply = map_emoji_command(&reply, &config.notification.emoji_commands);
+
+    if let Some(session_id) = session_id {
+        record_reply(session_id, &reply);
     }
+    log_reply(session_id, &reply);
+    run_reply_callback(&config.notification.on_reply_command, session_id, &reply);
 
-    // 返回用户回复（去除首尾空白）
-    let reply = String::from_utf8_lossy(&output.stdout).trim().to_string();
     Ok(reply)
 }
 
+/// 发送确认请求并等待「是/否」回复
+///
+/// 回复按配置的 `confirm_yes` / `confirm_no` 识别；无法识别时重新询问，
+/// 最多重新询问 [`MAX_CONFIRM_RETRIES`] 次，超时时间为整个确认过程的总时长
+///
+/// # 参数
+///
+/// * `prompt` - 需要确认的操作描述
+/// * `timeout` - 可选的超时时间（秒），如果不提供则使用配置文件中的默认值
+///
+/// # 返回
+///
+/// 确认返回 true，拒绝返回 false
+pub fn wait_confirm(prompt: String, timeout: Option<u64>) -> Result<bool> {
+    let config = load_config()?;
+    let yes = &config.notification.confirm_yes;
+    let no = &config.notification.confirm_no;
+    let hint = format!(
+        "回复 {} 确认，{} 取消",
+        yes.first().map(String::as_str).unwrap_or("Y"),
+        no.first().map(String::as_str).unwrap_or("N")
+    );
+
+    // 0 表示无限等待
+    let total_secs = timeout.unwrap_or(config.gewe_cli.timeout);
+    let deadline = (total_secs > 0).then(|| Instant::now() + Duration::from_secs(total_secs));
+
+    let mut message = format!("⚠️ {}\n确认执行？{}", prompt, hint);
+    for _ in 0..=MAX_CONFIRM_RETRIES {
+        let round_timeout = match remaining_secs(deadline, Instant::now()) {
+            Some(0) => return Err(ReplyTimeout(total_secs).into()),
+            remaining => remaining.or(timeout),
+        };
+
+        let reply = wait_reply(message, None, None, round_timeout, None)?;
+        if let Some(confirmed) = parse_confirmation(&reply, yes, no) {
+            return Ok(confirmed);
+        }
+        message = format!("无法识别的回复「{}」，请{}", truncate_chars(&reply, 20), hint);
+    }
+
+    anyhow::bail!("连续 {} 次无法识别回复，已取消确认", MAX_CONFIRM_RETRIES + 1)
+}
+
+/// 确认请求收到无法识别的回复时最多重新询问的次数
+const MAX_CONFIRM_RETRIES: usize = 3;
+
+/// 距离截止时间剩余的秒数（不足 1 秒按 1 秒计，已过期为 0），没有截止时间时返回 None
+fn remaining_secs(deadline: Option<Instant>, now: Instant) -> Option<u64> {
+    let remaining = deadline?.saturating_duration_since(now);
+    Some(if remaining.is_zero() { 0 } else { remaining.as_secs().max(1) })
+}
+
+/// 去掉微信引用回复带来的多余内容，只保留用户新输入的文本
+///
+/// 微信的引用回复会把被引用的消息拼在回复前面，常见形式：
+/// - `「昵称：原消息」` 后接分隔线 `- - - - -` 再接回复
+/// - `「昵称：原消息」` 后直接换行接回复
+///
+/// 只处理开头带昵称、且 `」` 后紧跟换行的引用块，紧接在引用块之后的分隔线一并去掉；
+/// 不符合该形式的回复（如 `「好的」然后继续`、正文中的 Markdown 分隔线）按原文返回。
+/// 去掉引用后为空时保留原文
+pub fn normalize_reply(reply: &str) -> String {
+    let reply = reply.trim();
+
+    let is_separator = |line: &str| {
+        let line = line.trim();
+        line.chars().filter(|c| *c == '-' || *c == '—').count() >= 4
+            && line.chars().all(|c| c == '-' || c == '—' || c.is_whitespace())
+    };
+
+    let Some(rest) = strip_quote(reply) else {
+        return reply.to_string();
+    };
+
+    // 分隔线只在紧跟引用块时才视为引用的一部分
+    let rest = rest.trim_start_matches(['\n', '\r']);
+    let rest = match rest.split_once('\n') {
+        Some((first, after)) if is_separator(first) => after,
+        None if is_separator(rest) => "",
+        _ => rest,
+    };
+
+    let stripped = rest.trim();
+    if stripped.is_empty() {
+        reply.to_string()
+    } else {
+        stripped.to_string()
+    }
+}
+
+/// 去掉开头的 `「昵称：原消息」` 引用块，返回 `」` 之后换行后的内容
+///
+/// 引用块需以昵称加冒号开头，且 `」` 后（忽略行尾空白）紧跟换行，否则返回 None
+fn strip_quote(reply: &str) -> Option<&str> {
+    let quote = reply.strip_prefix('「')?;
+    let nickname_end = quote.find(['：', ':'])?;
+    if nickname_end == 0 || quote[..nickname_end].contains(['\n', '」']) {
+        return None;
+    }
+
+    quote.match_indices('」').find_map(|(pos, close)| {
+        let after = quote[pos + close.len()..].trim_start_matches([' ', '\t', '\r']);
+        after.strip_prefix('\n')
+    })
+}
+
+/// 按 `emoji_commands` 将表情回复替换为命令文本
+///
+/// 只有整条回复恰好是某个表情（忽略首尾空白）时才替换，其他回复按原文返回
+pub fn map_emoji_command(reply: &str, emoji_commands: &BTreeMap<String, String>) -> String {
+    emoji_commands
+        .get(reply.trim())
+        .cloned()
+        .unwrap_or_else(|| reply.to_string())
+}
+
+/// 将回复识别为确认（true）或拒绝（false），无法识别时返回 None
+fn parse_confirmation(reply: &str, yes: &[String], no: &[String]) -> Option<bool> {
+    let reply = reply.trim();
+    if yes.iter().any(|token| token == reply) {
+        Some(true)
+    } else if no.iter().any(|token| token == reply) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// 检查监听地址是否可用
+///
+/// 尝试绑定后立即释放；仅在端口已被占用时报错，其他错误交由 gewe-cli 处理
+fn check_listen_available(listen_addr: &str) -> Result<()> {
+    if listen_addr.is_empty() {
+        return Ok(());
+    }
+
+    match TcpListener::bind(listen_addr) {
+        Err(e) if e.kind() == ErrorKind::AddrInUse => {
+            anyhow::bail!("监听地址 {} 已被占用，请检查是否有其他 wait-reply 或服务正在运行", listen_addr)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 记录回复历史（失败不影响主流程）
+fn record_reply(session_id: &str, reply: &str) {
+    if let Err(e) = ReplyHistory::new().and_then(|history| history.append(session_id, reply)) {
+        eprintln!("⚠️ 记录回复历史失败: {}", e);
+    }
+}
+
+/// 在活动日志中记录收到的回复
+fn log_reply(session_id: Option<&str>, reply: &str) {
+    let session = session_id.map(|id| format!("（会话 {}）", id)).unwrap_or_default();
+    activity::log(&format!("收到回复{}: {}", session, truncate_chars(reply, 50)));
+}
+
+/// 执行收到回复后的回调命令（失败不影响主流程）
+///
+/// 命令通过 shell 在后台执行，回复内容写入 stdin，
+/// 同时通过 `GEWE_CC_SESSION_ID` / `GEWE_CC_REPLY` 环境变量提供。
+/// 不等待命令结束，耗时的回调不会推迟回复返回给 Stop Hook 或 send-link
+fn run_reply_callback(command: &str, session_id: Option<&str>, reply: &str) {
+    if command.trim().is_empty() {
+        return;
+    }
+
+    if let Err(e) = spawn_reply_callback(command, session_id, reply) {
+        eprintln!("⚠️ 执行回复回调失败: {}", e);
+    }
+}
+
+fn spawn_reply_callback(command: &str, session_id: Option<&str>, reply: &str) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+
+    let mut child = cmd
+        .env("GEWE_CC_SESSION_ID", session_id.unwrap_or_default())
+        .env("GEWE_CC_REPLY", reply)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context(format!("启动回调命令失败: {}", command))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // 回调不读取 stdin 时写入可能失败，忽略即可；写完后关闭 stdin
+        let _ = stdin.write_all(reply.as_bytes());
+    }
+
+    Ok(())
+}
+
+/// 任务状态（send-link / notify --status）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TaskStatus {
+    Success,
+    Failed,
+}
+
+impl TaskStatus {
+    /// 卡片描述首行的状态标记
+    fn label(self) -> &'static str {
+        match self {
+            TaskStatus::Success => "✅ 成功",
+            TaskStatus::Failed => "❌ 失败",
+        }
+    }
+}
+
+/// 快捷回复按钮对应的回复内容（见 `notification.quick_replies`）
+const QUICK_REPLIES: &[&str] = &["继续", "停止"];
+
+/// 链接卡片标题：失败的任务使用醒目的标题，便于在手机上快速分辨
+fn card_title(status: Option<TaskStatus>, project: &str) -> String {
+    match status {
+        Some(TaskStatus::Failed) => format!("🚨 任务失败 - {}", project),
+        _ => format!("📝 任务完成 - {}", project),
+    }
+}
+
+/// 链接卡片标题的最大字素数
+const CARD_TITLE_MAX_CHARS: usize = 64;
+
+/// 链接卡片描述的最大字素数
+const CARD_DESC_MAX_CHARS: usize = 256;
+
+/// 清理链接卡片的标题或描述
+///
+/// 卡片只能显示单行文本，换行与制表符合并为一个空格，其余控制字符直接去掉，
+/// 超出 `max` 个字素时截断，避免卡片显示错乱或被接口拒绝
+fn sanitize_card_field(text: &str, max: usize) -> String {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !c.is_control() || c.is_whitespace())
+        .collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate_graphemes(&collapsed, max)
+}
+
+/// 组合链接卡片的描述：首行为状态与耗时，其后为摘要
+///
+/// 未提供状态和耗时时直接返回摘要
+pub fn build_card_description(status: Option<TaskStatus>, duration_secs: Option<u64>, summary: &str) -> String {
+    let mut header = Vec::new();
+    if let Some(status) = status {
+        header.push(status.label().to_string());
+    }
+    if let Some(secs) = duration_secs {
+        header.push(format!("⏱️ {}", format_duration(secs)));
+    }
+
+    let summary = summary.trim();
+    match (header.is_empty(), summary.is_empty()) {
+        (true, _) => summary.to_string(),
+        (false, true) => header.join(" · "),
+        (false, false) => format!("{}\n{}", header.join(" · "), summary),
+    }
+}
+
+/// 将秒数格式化为「1小时2分3秒」形式，省略为 0 的高位单位
+fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{}秒", seconds),
+        (0, _) => format!("{}分{}秒", minutes, seconds),
+        _ => format!("{}小时{}分{}秒", hours, minutes, seconds),
+    }
+}
+
 /// 发送链接卡片并等待回复
 ///
 /// # 参数
 ///
 /// * `session_id` - Session ID（用于构建 transcript URL）
 /// * `summary` - 任务摘要
+/// * `status` - 可选的任务状态，失败时使用醒目的标题
+/// * `timeout` - 可选的超时时间（秒），如果不提供则使用配置文件中的默认值
 ///
 /// # 返回
 ///
 /// 返回用户的回复内容
-pub fn send_link_and_wait(session_id: String, summary: String) -> Result<String> {
-    let config_mgr = ConfigManager::new()?;
-    let config = config_mgr.load()?;
+pub fn send_link_and_wait(
+    session_id: String,
+    summary: String,
+    status: Option<TaskStatus>,
+    timeout: Option<u64>,
+) -> Result<Reply> {
+    let config = load_config()?;
+    ensure_replies_supported(&config)?;
+    let wxid = deliver_link(&config, &session_id, &summary, status, true)?;
+
+    // 等待回复（期间在会话索引中标记为等待回复）
+    let _awaiting = AwaitingGuard::mark(&session_id);
+    let prompt = REPLY_PROMPT.to_string();
+    let progress = Some(Duration::from_secs(config.notification.progress_interval_secs));
+    let timeout_secs = timeout.unwrap_or(config.gewe_cli.timeout);
+    let result = wait_with_reminders(
+        prompt,
+        timeout_secs,
+        config.notification.reminder_interval_secs,
+        config.notification.max_reminders,
+        |message, window| {
+            if config.notification.reply_mode == "server" {
+                wait_reply_via_server(&session_id, &wxid, message, Some(window), progress)
+            } else {
+                wait_reply_with_progress(message, Some(wxid.clone()), None, Some(window), Some(&session_id), progress)
+            }
+        },
+    );
+    apply_timeout_policy(result, &wxid, &config)
+}
+
+/// 检查 send-link / watch 能否接收当前渠道的回复（见 [`ensure_replies_supported`]）
+pub fn check_replies_supported() -> Result<()> {
+    ensure_replies_supported(&load_config()?)
+}
+
+/// send-link 等待回复时的提示
+const REPLY_PROMPT: &str = "回复任何内容继续，回复「停止」结束远程模式。";
+
+/// 未配置 transcript_domain 时代替链接卡片发送的纯文本正文
+///
+/// 未开启 `allow_linkless_cards` 时报错；`urgent`（发送后等待回复）时附带回复提示
+fn linkless_body(config: &Config, session_id: &str, summary: &str, urgent: bool) -> Result<String> {
+    if !config.notification.allow_linkless_cards {
+        anyhow::bail!(
+            "Transcript 域名未配置\n\
+             请运行: gewe-cc config --transcript-domain <域名>\n\
+             或在配置中设置 allow_linkless_cards = true 改为发送纯文本通知"
+        );
+    }
+
+    let mut body = format!(
+        "{}\n\n会话 ID: {}\n（未配置 transcript_domain，暂无法在线查看完整对话记录）",
+        summary, session_id
+    );
+    if urgent {
+        body.push_str(&format!("\n\n{}", REPLY_PROMPT));
+    }
+    Ok(body)
+}
+
+/// 只发送链接卡片，不等待回复（send-link --no-wait）
+///
+/// 卡片不需要回复，免打扰时段内跳过发送。返回 transcript 地址，
+/// 未配置 transcript_domain（改为发送纯文本通知）时返回 None
+pub fn send_link_only(session_id: &str, summary: &str, status: Option<TaskStatus>) -> Result<Option<String>> {
+    let config = load_config()?;
+
+    if suppressed_by_quiet_hours(&config, false) {
+        eprintln!("🌙 当前处于免打扰时段（{}），已跳过发送", config.notification.quiet_hours);
+    } else {
+        deliver_link(&config, session_id, summary, status, false)?;
+    }
+
+    Ok(build_transcript_url(&config, session_id))
+}
+
+/// 会话的 transcript 地址，未配置 transcript_domain 时返回 None
+pub fn build_transcript_url(config: &Config, session_id: &str) -> Option<String> {
+    let domain = &config.notification.transcript_domain;
+    (!domain.is_empty()).then(|| format!("{}/{}", domain, session_id))
+}
+
+/// 向主接收方发送链接卡片并抄送给 `info_wxids`，返回主接收方
+///
+/// `urgent` 表示发送后会等待回复（见 [`OutgoingMessage::urgent`]）
+fn deliver_link(
+    config: &Config,
+    session_id: &str,
+    summary: &str,
+    status: Option<TaskStatus>,
+    urgent: bool,
+) -> Result<String> {
+    let cwd = std::env::current_dir().ok();
+
+    // 按项目目录选择通知对象（见 [routing]），未匹配时使用默认 wxid
+    let wxid = config.resolve_wxid_for_cwd(cwd.as_deref()).to_string();
 
     // 验证配置
-    if config.notification.wxid.is_empty() {
+    if wxid.is_empty() {
         anyhow::bail!(
             "目标微信 ID 不能为空\n\
              请运行: gewe-cc config --wxid <微信ID>"
         );
     }
 
-    if config.notification.transcript_domain.is_empty() {
-        anyhow::bail!(
-            "Transcript 域名未配置\n\
-             请运行: gewe-cc config --transcript-domain <域名>"
-        );
+    if urgent {
+        ensure_not_quiet(config)?;
     }
 
-    // 构建链接 URL
-    let transcript_url = format!("{}/{}", config.notification.transcript_domain, session_id);
+    // 未配置 transcript 域名时，允许的话退化为纯文本通知
+    let linkless_text = if config.notification.transcript_domain.is_empty() {
+        Some(linkless_body(config, session_id, summary, urgent)?)
+    } else {
+        None
+    };
+
+    // 微信渠道预先检查消息后端（gewe-cli 是否安装、API 是否配置）
+    let backend = if config.notification.channel == "wechat" {
+        Some(backend::from_config(config)?)
+    } else {
+        None
+    };
 
     // 获取项目名
-    let project = std::env::current_dir()
-        .ok()
-        .and_then(|p| p.file_name().map(|s| s.to_string_lossy().to_string()))
-        .unwrap_or_else(|| "unknown".to_string());
+    let project = config.resolve_project_name(cwd.as_deref());
+
+    let title = card_title(status, &project);
+
+    // 快捷回复按钮（目前仅 Telegram 支持），其他渠道仍依靠文字提示回复
+    let quick_replies: Vec<String> = if config.notification.quick_replies {
+        QUICK_REPLIES.iter().map(|s| s.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let send = |to: &str| -> Result<()> {
+        if let Some(body) = &linkless_text {
+            let message = OutgoingMessage {
+                title: Some(title.clone()),
+                body: body.clone(),
+                link: None,
+                quick_replies: quick_replies.clone(),
+                urgent,
+            };
+            return dispatch_notification(config, to, &message);
+        }
+
+        // 构建链接 URL
+        let transcript_url = build_transcript_url(config, session_id).unwrap_or_default();
+
+        // 链接卡片是微信特有的消息类型，其他渠道按渠道格式发送带链接的文本
+        let Some(backend) = &backend else {
+            let message = OutgoingMessage {
+                title: Some(title.clone()),
+                body: summary.to_string(),
+                link: Some(transcript_url),
+                quick_replies: quick_replies.clone(),
+                urgent,
+            };
+            return dispatch_notification(config, to, &message);
+        };
+
+        // 使用配置的域名 + /assets/thumb.png 作为缩略图
+        // 添加时间戳参数避免缓存问题
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let thumb_url = format!("{}/assets/thumb.png?t={}", config.notification.transcript_domain, timestamp);
+
+        let card_title = sanitize_card_field(&title, CARD_TITLE_MAX_CHARS);
+        let card_desc = sanitize_card_field(summary, CARD_DESC_MAX_CHARS);
+        let card = LinkCard {
+            title: &card_title,
+            desc: &card_desc,
+            link_url: &transcript_url,
+            thumb_url: &thumb_url,
+        };
+        backend.send_link(to, &card)?;
+        activity::log(&format!("发送链接卡片 → {}（会话 {}）", sanitize_wxid(to), session_id));
+        Ok(())
+    };
+
+    // 任务失败时按配置先发一条文本提醒，比链接卡片更容易在通知栏中注意到
+    if status == Some(TaskStatus::Failed)
+        && config.notification.failure_text_alert
+        && let Err(e) = dispatch_notification(
+            config,
+            &wxid,
+            &OutgoingMessage {
+                urgent,
+                ..OutgoingMessage::text(title.clone())
+            },
+        )
+    {
+        eprintln!("⚠️ 发送失败提醒失败: {}", e);
+    }
 
     // 发送链接卡片
-    let title = format!("📝 任务完成 - {}", project);
-
-    // 使用配置的域名 + /assets/thumb.png 作为缩略图
-    // 添加时间戳参数避免缓存问题
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let thumb_url = format!("{}/assets/thumb.png?t={}", config.notification.transcript_domain, timestamp);
-
-    let output = Command::new(&config.gewe_cli.command)
-        .args([
-            "send-link",
-            "--to-wxid",
-            &config.notification.wxid,
-            "--title",
-            &title,
-            "--desc",
-            &summary,
-            "--link-url",
-            &transcript_url,
-            "--thumb-url",
-            &thumb_url,
-        ])
-        .output()
-        .context(format!(
-            "调用 {} 失败，请确认已安装 gewe-cli",
-            config.gewe_cli.command
-        ))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("发送链接卡片失败: {}", stderr);
-    }
-
-    // 等待回复
-    wait_reply(
-        "回复任何内容继续，回复「停止」结束远程模式。".to_string(),
-        None,
-        None,
-        None,
-    )
+    send(&wxid)?;
+
+    // 抄送给仅旁观的接收方（不等待其回复，失败不影响主流程）
+    let results = send_concurrently(&config.notification.info_wxids, config.notification.send_concurrency, send);
+    for (info_wxid, result) in results {
+        if let Err(e) = result {
+            eprintln!("⚠️ 抄送链接卡片给 {} 失败: {}", sanitize_wxid(info_wxid), e);
+        }
+    }
+
+    Ok(wxid)
 }
 
+/// 会话"等待回复"标记，离开作用域（收到回复、超时或出错）时自动清除
+///
+/// 标记失败只打印警告，不影响等待流程
+struct AwaitingGuard {
+    registry: Option<SessionRegistry>,
+    session_id: String,
+}
+
+impl AwaitingGuard {
+    fn mark(session_id: &str) -> Self {
+        let registry = SessionRegistry::new()
+            .and_then(|registry| registry.set_awaiting(session_id, true).map(|_| registry))
+            .inspect_err(|e| eprintln!("⚠️ 标记等待回复状态失败: {}", e))
+            .ok();
+
+        Self {
+            registry,
+            session_id: session_id.to_string(),
+        }
+    }
+}
+
+impl Drop for AwaitingGuard {
+    fn drop(&mut self) {
+        if let Some(registry) = &self.registry
+            && let Err(e) = registry.set_awaiting(&self.session_id, false)
+        {
+            eprintln!("⚠️ 清除等待回复状态失败: {}", e);
+        }
+    }
+}
+
+/// 向多个接收方发送，同时最多 `concurrency` 个
+///
+/// 按批次发送，一批全部完成后再开始下一批；返回每个接收方的结果，顺序与输入一致
+fn send_concurrently(
+    recipients: &[String],
+    concurrency: usize,
+    send: impl Fn(&str) -> Result<()> + Sync,
+) -> Vec<(&str, Result<()>)> {
+    let send = &send;
+    let mut results = Vec::with_capacity(recipients.len());
+
+    for batch in recipients.chunks(concurrency.max(1)) {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|recipient| scope.spawn(move || send(recipient)))
+                .collect();
+
+            for (recipient, handle) in batch.iter().zip(handles) {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("发送线程异常退出")));
+                results.push((recipient.as_str(), result));
+            }
+        });
+    }
+
+    results
+}
+
+/// 从会话 transcript 中提取摘要
+///
+/// 见 [`auto_summary`]；找不到 transcript 时返回占位文本
+pub fn summary_from_transcript(session_id: &str) -> Result<String> {
+    let config = load_config()?;
+
+    let transcript_path = server::resolve_transcript_path(SessionRegistry::new().ok().as_ref(), session_id);
+
+    let messages = transcript_path
+        .and_then(|(path, _)| transcript::parse_transcript(&path, &[]).ok())
+        .unwrap_or_default();
+
+    Ok(auto_summary(&messages, config.notification.summary_max_chars))
+}
+
+/// 由消息生成卡片摘要
+///
+/// 首行为第一条用户提问的预览，便于一眼认出是哪个会话；其后是最后一条助手消息的文本，
+/// 超出 `max_chars` 时截断。两者都没有时返回占位文本
+fn auto_summary(messages: &[transcript::Message], max_chars: usize) -> String {
+    let preview = transcript::first_user_preview(messages, transcript::PREVIEW_MAX_CHARS);
+    let text = transcript::last_assistant_text(messages).map(|text| truncate_graphemes(&text, max_chars));
+
+    match (preview, text) {
+        (Some(preview), Some(text)) => format!("💬 {}\n{}", preview, text),
+        (Some(preview), None) => format!("💬 {}\n{}", preview, AUTO_SUMMARY_PLACEHOLDER),
+        (None, Some(text)) => text,
+        (None, None) => AUTO_SUMMARY_PLACEHOLDER.to_string(),
+    }
+}
+
+/// 无法自动提取摘要时使用的占位文本
+const AUTO_SUMMARY_PLACEHOLDER: &str = "任务已完成，点击查看完整对话记录";
+
+/// 通过 serve 进程的 `/webhook/reply` 等待回复
+///
+/// 发送消息后登记等待记录，由 serve 进程收到微信回调后写入回复，
+/// 本函数轮询读取。适用于 serve 常驻运行、不希望每次任务都启动 gewe-cli webhook 的场景
+///
+/// # 参数
+///
+/// * `session_id` - 等待回复的会话 ID
+/// * `wxid` - 发送消息并等待其回复的微信 ID
+/// * `message` - 要发送的消息内容
+/// * `timeout` - 可选的超时时间（秒），如果不提供则使用配置文件中的默认值
+/// * `progress` - 可选的进度输出间隔，等待期间定期向 stderr 输出等待时长
+///
+/// # 返回
+///
+/// 返回用户的回复内容
+pub fn wait_reply_via_server(
+    session_id: &str,
+    wxid: &str,
+    message: String,
+    timeout: Option<u64>,
+    progress: Option<Duration>,
+) -> Result<String> {
+    let config = load_config()?;
+    let timeout_secs = timeout.unwrap_or(config.gewe_cli.timeout);
+
+    if wxid.is_empty() {
+        anyhow::bail!(
+            "目标微信 ID 不能为空\n\
+             请运行: gewe-cc config --wxid <微信ID>"
+        );
+    }
+
+    let pending = PendingReplyRegistry::new()?;
+    pending.create(session_id, wxid)?;
+
+    if let Err(e) = send_text(message, Some(wxid.to_string()), true) {
+        pending.remove(session_id);
+        return Err(e);
+    }
+
+    let progress = progress.filter(|d| !d.is_zero());
+    let started = Instant::now();
+    let mut last_progress = started;
+    loop {
+        if let Some(mut reply) = pending.take_reply(session_id) {
+            if config.notification.normalize_replies {
+                reply = normalize_reply(&reply);
+            }
+            reply = map_emoji_command(&reply, &config.notification.emoji_commands);
+            record_reply(session_id, &reply);
+            log_reply(Some(session_id), &reply);
+            run_reply_callback(&config.notification.on_reply_command, Some(session_id), &reply);
+            return Ok(reply);
+        }
+
+        if timeout_secs > 0 && started.elapsed() >= Duration::from_secs(timeout_secs) {
+            pending.remove(session_id);
+            return Err(ReplyTimeout(timeout_secs).into());
+        }
+
+        if let Some(interval) = progress
+            && last_progress.elapsed() >= interval
+        {
+            backend::print_progress(started);
+            last_progress = Instant::now();
+        }
+
+        std::thread::sleep(REPLY_POLL_INTERVAL);
+    }
+}
+
+/// server 模式下轮询回复的间隔
+const REPLY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 /// 仅发送通知，不等待回复
 ///
 /// # 参数
@@ -182,40 +1016,547 @@ pub fn send_link_and_wait(session_id: String, summary: String) -> Result<String>
 ///
 /// 发送成功返回 Ok(())
 pub fn send_notification(message: String, to_wxid: Option<String>) -> Result<()> {
-    let config_mgr = ConfigManager::new()?;
-    let config = config_mgr.load()?;
+    send_text(message, to_wxid, false)
+}
 
-    let wxid = to_wxid.unwrap_or(config.notification.wxid);
+/// 发送文本消息，`urgent` 表示该消息需要回复（见 [`OutgoingMessage::urgent`]）
+fn send_text(message: String, to_wxid: Option<String>, urgent: bool) -> Result<()> {
+    let config = load_config()?;
 
-    if wxid.is_empty() {
-        anyhow::bail!("目标微信 ID 不能为空");
+    let wxid = to_wxid.unwrap_or_else(|| config.notification.wxid.clone());
+
+    if config.notification.channel == "wechat" {
+        backend::from_config(&config)?;
     }
 
-    let output = Command::new(&config.gewe_cli.command)
-        .args([
-            "message",
-            "send-text",
-            "--to",
-            &wxid,
-            "--content",
-            &message,
-        ])
-        .output()
-        .context(format!(
-            "调用 {} 失败，请确认已安装 gewe-cli",
-            config.gewe_cli.command
-        ))?;
+    if let Some(warning) = message_len_warning(&message, config.notification.warn_message_len) {
+        eprintln!("⚠️ {}", warning);
+    }
+
+    let message = OutgoingMessage {
+        urgent,
+        ..OutgoingMessage::text(message)
+    };
+    dispatch_notification(&config, &wxid, &message)
+}
+
+/// 消息字符数超过 `limit` 时的警告（`limit` 为 0 时不检查）
+fn message_len_warning(message: &str, limit: usize) -> Option<String> {
+    let len = message.chars().count();
+    (limit > 0 && len > limit).then(|| {
+        format!(
+            "消息长度 {} 字符，超过 warn_message_len（{}），在微信端可能被截断",
+            len, limit
+        )
+    })
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("发送消息失败: {}", stderr);
+/// 当前是否处于免打扰时段（`notification.quiet_hours`，按配置的时区计算）
+pub fn is_quiet_now(config: &Config) -> bool {
+    let timezone = output::parse_timezone(&config.timezone).unwrap_or(output::Timezone::Local);
+    let now = output::time_of_day_in(&chrono::Utc::now(), timezone);
+    config::in_quiet_hours(&config.notification.quiet_hours, now)
+}
+
+/// 免打扰时段内是否应跳过该消息：不需要回复的消息总是跳过，
+/// 需要回复的消息在 `quiet_hours_allow_urgent` 关闭时跳过
+fn suppressed_by_quiet_hours(config: &Config, urgent: bool) -> bool {
+    (!urgent || !config.notification.quiet_hours_allow_urgent) && is_quiet_now(config)
+}
+
+/// 需要回复的消息被免打扰时段拦截时的错误
+fn ensure_not_quiet(config: &Config) -> Result<()> {
+    if suppressed_by_quiet_hours(config, true) {
+        anyhow::bail!(
+            "当前处于免打扰时段（{}），未发送消息\n\
+             如需在该时段发送需要回复的消息，请设置 quiet_hours_allow_urgent = true",
+            config.notification.quiet_hours
+        );
     }
+    Ok(())
+}
+
+/// 按配置的通知渠道格式化并发送消息
+///
+/// 格式化器由 `notification.channel` 决定（见 [`crate::format`]）。
+/// wechat 渠道通过配置的消息后端（gewe-cli 或 gewe API）发送，telegram / dingtalk 渠道直接调用 HTTP 接口，
+/// 超时时间由 `notification.http_timeout_secs` 控制
+///
+/// # 参数
+///
+/// * `config` - 当前配置
+/// * `to` - 接收方（wechat 渠道为微信 ID，telegram 渠道为 chat_id，dingtalk 渠道忽略）
+/// * `message` - 与渠道无关的逻辑消息
+pub fn dispatch_notification(config: &Config, to: &str, message: &OutgoingMessage) -> Result<()> {
+    if suppressed_by_quiet_hours(config, message.urgent) {
+        eprintln!("🌙 当前处于免打扰时段（{}），已跳过通知", config.notification.quiet_hours);
+        return Ok(());
+    }
+
+    let channel = config.notification.channel.as_str();
+    let text = format::formatter_for(channel)?.format(message);
+    let timeout = Duration::from_secs(config.notification.http_timeout_secs);
+
+    match channel {
+        "wechat" => send_wechat_text(config, to, &text),
+        "telegram" => {
+            let token = &config.notification.telegram_bot_token;
+            if token.is_empty() {
+                anyhow::bail!("未配置 telegram_bot_token");
+            }
+            let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+            let mut body = serde_json::json!({
+                "chat_id": to,
+                "text": text,
+                "parse_mode": "MarkdownV2",
+            });
+            if let Some(markup) = format::telegram_reply_markup(message) {
+                body["reply_markup"] = markup;
+            }
+            post_with_retry(&url, &body, timeout).map(|_| ())
+        }
+        "dingtalk" => {
+            let webhook = &config.notification.dingtalk_webhook;
+            if webhook.is_empty() {
+                anyhow::bail!("未配置 dingtalk_webhook");
+            }
+            let body = serde_json::json!({
+                "msgtype": "markdown",
+                "markdown": {
+                    "title": message.title.as_deref().unwrap_or("gewe-cc"),
+                    "text": text,
+                },
+            });
+            post_with_retry(webhook, &body, timeout).and_then(|response| check_dingtalk_response(&response))
+        }
+        _ => anyhow::bail!("{} 渠道暂不支持发送消息", channel),
+    }?;
 
+    activity::log(&format!("发送通知（{}）→ {}", channel, sanitize_wxid(to)));
     Ok(())
 }
 
+/// 发送 HTTP 渠道请求，连接失败时重试一次，返回响应 JSON
+///
+/// 只在请求尚未发出（无法建立连接）时重试；读取响应超时的请求可能已被处理，重试会导致消息重复
+fn post_with_retry(url: &str, body: &serde_json::Value, timeout: Duration) -> Result<serde_json::Value> {
+    match http::post_json(url, body, timeout) {
+        Err(e) if http::is_connect_error(&e) => {
+            eprintln!("⚠️ {}，正在重试", e);
+            http::post_json(url, body, timeout)
+        }
+        result => result,
+    }
+}
+
+/// 钉钉机器人失败时仍返回 HTTP 200，需检查响应中的 `errcode`
+fn check_dingtalk_response(response: &serde_json::Value) -> Result<()> {
+    match response.get("errcode").and_then(|c| c.as_i64()) {
+        Some(0) | None => Ok(()),
+        Some(code) => anyhow::bail!(
+            "钉钉接口返回错误 {}: {}",
+            code,
+            response.get("errmsg").and_then(|m| m.as_str()).unwrap_or_default()
+        ),
+    }
+}
+
+/// 通过配置的消息后端（见 [`crate::backend`]）发送微信文本消息
+fn send_wechat_text(config: &Config, wxid: &str, text: &str) -> Result<()> {
+    if wxid.is_empty() {
+        anyhow::bail!("目标微信 ID 不能为空");
+    }
+
+    backend::from_config(config)?.send_text(wxid, text)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_auto_summary() {
+        let message = |role: &str, text: &str| transcript::Message {
+            role: role.to_string(),
+            content: transcript::MessageContent::String(text.to_string()),
+            sidechain: false,
+        };
+
+        let messages = [message("user", "修复登录页"), message("assistant", "已修复全部问题")];
+        assert_eq!(auto_summary(&messages, 3), "💬 修复登录页\n已修复…");
+        assert_eq!(auto_summary(&messages[..1], 100), format!("💬 修复登录页\n{}", AUTO_SUMMARY_PLACEHOLDER));
+        assert_eq!(auto_summary(&messages[1..], 100), "已修复全部问题");
+        assert_eq!(auto_summary(&[], 100), AUTO_SUMMARY_PLACEHOLDER);
+    }
+
+    #[test]
+    fn test_message_len_warning() {
+        assert_eq!(message_len_warning("你好", 2), None);
+        assert_eq!(message_len_warning(&"字".repeat(1001), 0), None);
+
+        let warning = message_len_warning("你好啊", 2).unwrap();
+        assert!(warning.contains("消息长度 3 字符"));
+        assert!(warning.contains("warn_message_len（2）"));
+    }
+
+    #[test]
+    fn test_parse_confirmation() {
+        let yes = vec!["Y".to_string(), "y".to_string(), "是".to_string()];
+        let no = vec!["N".to_string(), "n".to_string(), "否".to_string()];
+
+        assert_eq!(parse_confirmation("Y", &yes, &no), Some(true));
+        assert_eq!(parse_confirmation(" 是\n", &yes, &no), Some(true));
+        assert_eq!(parse_confirmation("n", &yes, &no), Some(false));
+        assert_eq!(parse_confirmation("否", &yes, &no), Some(false));
+        assert_eq!(parse_confirmation("好吧", &yes, &no), None);
+        assert_eq!(parse_confirmation("yes", &yes, &no), None);
+    }
+
+    #[test]
+    fn test_sanitize_card_field() {
+        // 多行摘要合并为单行
+        assert_eq!(sanitize_card_field("✅ 成功\n修复了登录问题\r\n\n  补充测试", 256), "✅ 成功 修复了登录问题 补充测试");
+        assert_eq!(sanitize_card_field("\t列1\t列2\n", 256), "列1 列2");
+
+        // 控制字符直接去掉
+        assert_eq!(sanitize_card_field("a\u{0}b\u{7}c\u{1b}[31md", 256), "abc[31md");
+        assert_eq!(sanitize_card_field("\u{7f}完成", 256), "完成");
+
+        // 组合 emoji 中的零宽连接符不是控制字符，应当保留
+        assert_eq!(sanitize_card_field("👨‍👩‍👧 完成", 256), "👨‍👩‍👧 完成");
+
+        // 超长时截断
+        assert_eq!(sanitize_card_field("第一行\n第二行", 5), "第一行 第…");
+        assert_eq!(sanitize_card_field(" \n\r ", 10), "");
+    }
+
+    #[test]
+    fn test_check_dingtalk_response() {
+        assert!(check_dingtalk_response(&serde_json::json!({"errcode": 0, "errmsg": "ok"})).is_ok());
+        assert!(check_dingtalk_response(&serde_json::Value::Null).is_ok());
+
+        let err = check_dingtalk_response(&serde_json::json!({"errcode": 310000, "errmsg": "keywords not in content"})).unwrap_err();
+        assert!(err.to_string().contains("310000"));
+        assert!(err.to_string().contains("keywords not in content"));
+    }
+
+    #[test]
+    fn test_ensure_replies_supported() {
+        let mut config = Config::default();
+        assert!(ensure_replies_supported(&config).is_ok());
+
+        config.notification.channel = "telegram".to_string();
+        assert!(ensure_replies_supported(&config).unwrap_err().to_string().contains("reply_mode"));
+        config.notification.reply_mode = "server".to_string();
+        assert!(ensure_replies_supported(&config).is_ok());
+
+        // dingtalk 没有回复 webhook，server 模式下同样不支持
+        config.notification.channel = "dingtalk".to_string();
+        assert!(ensure_replies_supported(&config).is_err());
+    }
+
+    #[test]
+    fn test_linkless_body() {
+        let mut config = Config::default();
+
+        // 未开启 allow_linkless_cards 时保持报错
+        let err = linkless_body(&config, "abc", "完成", true).unwrap_err();
+        assert!(err.to_string().contains("Transcript 域名未配置"));
+
+        config.notification.allow_linkless_cards = true;
+        let body = linkless_body(&config, "abc", "完成", true).unwrap();
+        assert!(body.starts_with("完成\n"));
+        assert!(body.contains("会话 ID: abc"));
+        assert!(body.ends_with(REPLY_PROMPT));
+
+        // 不等待回复时不附带回复提示
+        let body = linkless_body(&config, "abc", "完成", false).unwrap();
+        assert!(!body.contains(REPLY_PROMPT));
+    }
+
+    #[test]
+    fn test_remaining_secs() {
+        let now = Instant::now();
+        assert_eq!(remaining_secs(None, now), None);
+        assert_eq!(remaining_secs(Some(now + Duration::from_secs(30)), now), Some(30));
+        assert_eq!(remaining_secs(Some(now + Duration::from_millis(200)), now), Some(1));
+        assert_eq!(remaining_secs(Some(now), now + Duration::from_secs(5)), Some(0));
+    }
+
+    #[test]
+    fn test_build_card_description() {
+        assert_eq!(build_card_description(None, None, "修复了登录问题"), "修复了登录问题");
+        assert_eq!(
+            build_card_description(Some(TaskStatus::Success), Some(200), "修复了登录问题"),
+            "✅ 成功 · ⏱️ 3分20秒\n修复了登录问题"
+        );
+        assert_eq!(build_card_description(Some(TaskStatus::Failed), None, "  "), "❌ 失败");
+        assert_eq!(build_card_description(None, Some(3725), "x"), "⏱️ 1小时2分5秒\nx");
+        assert_eq!(format_duration(42), "42秒");
+    }
+
+    #[test]
+    fn test_apply_timeout_policy() {
+        let mut config = Config::default();
+        let timeout = || -> Result<String> { Err(ReplyTimeout(60).into()) };
+
+        // 默认 abort：超时原样报错
+        let err = apply_timeout_policy(timeout(), "wxid_a", &config).unwrap_err();
+        assert!(err.downcast_ref::<ReplyTimeout>().is_some());
+
+        config.notification.on_timeout = "continue".to_string();
+        let reply = apply_timeout_policy(timeout(), "wxid_a", &config).unwrap();
+        assert_eq!(reply.text, "继续");
+        assert!(reply.timed_out);
+        assert_eq!(reply.sender, None);
+
+        config.notification.default_reply_on_timeout = "先提交当前进度".to_string();
+        assert_eq!(apply_timeout_policy(timeout(), "wxid_a", &config).unwrap().text, "先提交当前进度");
+
+        // 收到的回复与其他错误不受影响
+        let reply = apply_timeout_policy(Ok("停止".to_string()), "wxid_a", &config).unwrap();
+        assert_eq!(reply.text, "停止");
+        assert_eq!(reply.sender.as_deref(), Some("wxid_a"));
+        assert!(!reply.timed_out);
+        let err = apply_timeout_policy(Err(anyhow::anyhow!("发送失败")), "wxid_a", &config).unwrap_err();
+        assert_eq!(err.to_string(), "发送失败");
+    }
+
+    #[test]
+    fn test_reply_json_round_trip() {
+        let reply = Reply {
+            text: "第一行\n第二行 \"引号\"".to_string(),
+            sender: Some("wxid_a".to_string()),
+            timed_out: false,
+        };
+
+        let json = serde_json::to_string(&reply).unwrap();
+        assert!(!json.contains('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["reply"], "第一行\n第二行 \"引号\"");
+        assert_eq!(value["sender"], "wxid_a");
+        assert_eq!(value["timed_out"], false);
+
+        let timed_out = Reply {
+            text: "继续".to_string(),
+            sender: None,
+            timed_out: true,
+        };
+        let value = serde_json::to_value(&timed_out).unwrap();
+        assert!(value["sender"].is_null());
+
+        let value = timed_out_json();
+        assert!(value["reply"].is_null());
+        assert!(value["sender"].is_null());
+        assert_eq!(value["timed_out"], true);
+    }
+
+    #[test]
+    fn test_print_result_keeps_errors() {
+        let err = Reply::print_result(Err(ReplyTimeout(60).into()), OutputFormat::Json).unwrap_err();
+        assert!(err.downcast_ref::<ReplyTimeout>().is_some());
+        let err = Reply::print_result(Err(anyhow::anyhow!("发送失败")), OutputFormat::Text).unwrap_err();
+        assert_eq!(err.to_string(), "发送失败");
+    }
+
+    #[test]
+    fn test_build_transcript_url() {
+        let mut config = Config::default();
+        assert_eq!(build_transcript_url(&config, "abc"), None);
+
+        config.notification.transcript_domain = "https://t.example.com".to_string();
+        assert_eq!(build_transcript_url(&config, "abc").as_deref(), Some("https://t.example.com/abc"));
+    }
+
+    #[test]
+    fn test_card_title() {
+        assert_eq!(card_title(None, "my_app"), "📝 任务完成 - my_app");
+        assert_eq!(card_title(Some(TaskStatus::Success), "my_app"), "📝 任务完成 - my_app");
+        assert_eq!(card_title(Some(TaskStatus::Failed), "my_app"), "🚨 任务失败 - my_app");
+    }
+
+    #[test]
+    fn test_send_concurrently() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let recipients: Vec<String> = (1..=7).map(|i| format!("wxid_{}", i)).collect();
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = send_concurrently(&recipients, 3, |to| {
+            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            active.fetch_sub(1, Ordering::SeqCst);
+            if to == "wxid_5" {
+                anyhow::bail!("发送失败");
+            }
+            Ok(())
+        });
+
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert_eq!(results.len(), 7);
+        let names: Vec<&str> = results.iter().map(|(to, _)| *to).collect();
+        assert_eq!(names, ["wxid_1", "wxid_2", "wxid_3", "wxid_4", "wxid_5", "wxid_6", "wxid_7"]);
+        let failed: Vec<&str> = results.iter().filter(|(_, r)| r.is_err()).map(|(to, _)| *to).collect();
+        assert_eq!(failed, ["wxid_5"]);
+
+        // 0 视为 1，逐个发送
+        assert_eq!(send_concurrently(&recipients[..2], 0, |_| Ok(())).len(), 2);
+    }
+
+    #[test]
+    fn test_find_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("gewe-cli");
+        std::fs::write(&binary, "").unwrap();
+        let path_var = std::env::join_paths([dir.path()]).unwrap();
+
+        assert_eq!(find_command("gewe-cli", Some(&path_var)), Some(binary.clone()));
+        assert_eq!(find_command(binary.to_str().unwrap(), None), Some(binary));
+        assert_eq!(find_command("gewe-cli-missing", Some(&path_var)), None);
+        assert_eq!(find_command("gewe-cli", None), None);
+        assert_eq!(find_command("", Some(&path_var)), None);
+
+        let err = ensure_gewe_cli("/nonexistent/gewe-cli").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "未找到 gewe-cli（命令：/nonexistent/gewe-cli），请运行 gewe-cc init 查看安装指引"
+        );
+    }
+
+    #[test]
+    fn test_wait_with_reminders() {
+        // 第三次等待时收到回复
+        let mut calls = Vec::new();
+        let reply = wait_with_reminders("继续？".to_string(), 0, 60, 3, |message, window| {
+            calls.push((message, window));
+            if calls.len() < 3 {
+                Err(ReplyTimeout(window).into())
+            } else {
+                Ok("好".to_string())
+            }
+        })
+        .unwrap();
+        assert_eq!(reply, "好");
+        assert_eq!(calls[0], ("继续？".to_string(), 60));
+        assert_eq!(calls[1], (REMINDER_MESSAGE.to_string(), 60));
+
+        // 提醒次数用完后按剩余时间等待，最终超时报告总超时
+        let mut windows = Vec::new();
+        let err = wait_with_reminders("继续？".to_string(), 600, 60, 2, |_, window| {
+            windows.push(window);
+            Err(ReplyTimeout(window).into())
+        })
+        .unwrap_err();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(&windows[..2], [60, 60]);
+        assert!(windows[2] > 500);
+        assert_eq!(err.to_string(), "等待微信回复超时（600秒）");
+
+        // 其他错误直接返回，不再提醒
+        let mut count = 0;
+        let err = wait_with_reminders("继续？".to_string(), 0, 60, 3, |_, _| {
+            count += 1;
+            anyhow::bail!("发送微信消息失败")
+        })
+        .unwrap_err();
+        assert_eq!(count, 1);
+        assert_eq!(err.to_string(), "发送微信消息失败");
+
+        // 未配置提醒时只等待一次
+        let mut windows = Vec::new();
+        let _ = wait_with_reminders("继续？".to_string(), 120, 0, 3, |_, window| {
+            windows.push(window);
+            Err(ReplyTimeout(window).into())
+        });
+        assert_eq!(windows, [120]);
+    }
+
+    #[test]
+    fn test_map_emoji_command() {
+        let commands = crate::config::NotificationConfig::default().emoji_commands;
+
+        assert_eq!(map_emoji_command("👍", &commands), "继续");
+        assert_eq!(map_emoji_command(" [强] ", &commands), "继续");
+        assert_eq!(map_emoji_command("🛑", &commands), "停止");
+
+        // 未映射的表情与夹带其他文字的回复保持原样
+        assert_eq!(map_emoji_command("🎉", &commands), "🎉");
+        assert_eq!(map_emoji_command("👍 先改测试", &commands), "👍 先改测试");
+        assert_eq!(map_emoji_command("👍", &BTreeMap::new()), "👍");
+    }
+
+    #[test]
+    fn test_normalize_reply() {
+        // 引用 + 分隔线（手机端）
+        let reply = "「Claude Code：📝 任务完成 - my_app\n修复了登录问题」\n- - - - - - - - - - - - - - -\n继续，把测试也补上";
+        assert_eq!(normalize_reply(reply), "继续，把测试也补上");
+
+        // 引用块后直接换行接回复（PC 端）
+        assert_eq!(normalize_reply("「Claude Code：回复任何内容继续」\n 停止 "), "停止");
+
+        // 分隔线为长横线，回复本身有多行
+        assert_eq!(normalize_reply("「a：b」\n————————\n第一行\n第二行"), "第一行\n第二行");
+
+        // 普通回复保持不变
+        assert_eq!(normalize_reply("  好的，继续  "), "好的，继续");
+        assert_eq!(normalize_reply("a-b-c-d"), "a-b-c-d");
+
+        // 只有引用时保留原文
+        assert_eq!(normalize_reply("「只是引用」"), "「只是引用」");
+        assert_eq!(normalize_reply("「a：只是引用」\n- - - -"), "「a：只是引用」\n- - - -");
+
+        // 不是引用块：没有昵称或 」 后没有换行
+        assert_eq!(normalize_reply("「好的」然后继续"), "「好的」然后继续");
+        assert_eq!(normalize_reply("「好的」\n继续"), "「好的」\n继续");
+        assert_eq!(normalize_reply("「注意：先备份」再继续"), "「注意：先备份」再继续");
+
+        // 正文中的 Markdown 分隔线保留
+        assert_eq!(normalize_reply("先改接口\n\n----\n\n再补测试"), "先改接口\n\n----\n\n再补测试");
+        assert_eq!(normalize_reply("「a：b」\n继续\n- - - -\n然后提交"), "继续\n- - - -\n然后提交");
+    }
+
+    #[test]
+    fn test_reply_callback() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("callback.txt");
+        let command = format!(
+            r#"cat > "{0}"; echo "|$GEWE_CC_SESSION_ID|$GEWE_CC_REPLY" >> "{0}""#,
+            out.display()
+        );
+
+        spawn_reply_callback(&command, Some("abc"), "继续").unwrap();
+
+        // 命令在后台执行，等待其写完文件
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let expected = "继续|abc|继续\n";
+        while fs::read_to_string(&out).unwrap_or_default() != expected && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(fs::read_to_string(&out).unwrap(), expected);
+
+        // 不等待耗时的命令结束
+        let started = Instant::now();
+        spawn_reply_callback("sleep 5", None, "x").unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_check_listen_available() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let err = check_listen_available(&addr).unwrap_err();
+        assert!(err.to_string().contains(&format!("监听地址 {} 已被占用", addr)));
+
+        drop(listener);
+        assert!(check_listen_available(&addr).is_ok());
+        assert!(check_listen_available("").is_ok());
+    }
+
     #[test]
     fn test_empty_wxid_validation() {
         // 测试 wxid 为空字符串时的错误信息
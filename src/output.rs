@@ -0,0 +1,160 @@
+//! 终端输出控制
+//!
+//! 统一管理装饰性输出（横幅、提示语等），便于在脚本中静默运行
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Utc};
+use chrono_tz::Tz;
+use colored::*;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::ConfigManager;
+
+/// 命令结果的输出格式（wait-reply / send-link --output）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// 纯文本，只输出回复内容
+    #[default]
+    Text,
+    /// 单行 JSON，便于脚本解析
+    Json,
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// 设置静默模式（由 `--quiet` 全局参数控制）
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// 是否处于静默模式
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// 判断是否应输出彩色内容
+///
+/// 遵循 <https://no-color.org> 约定：设置了非空的 `NO_COLOR` 时禁用颜色；
+/// stdout 不是终端（重定向到文件或管道）时同样禁用
+pub fn should_colorize(no_color: Option<&str>, is_terminal: bool) -> bool {
+    if no_color.is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+
+    is_terminal
+}
+
+/// 输出带分隔线的标题横幅
+///
+/// 静默模式下只输出标题本身，作为必要的结果提示
+pub fn banner(title: &str, color: Color) {
+    if is_quiet() {
+        println!("{}", title.trim());
+        return;
+    }
+
+    println!("{}", "═══════════════════════════════════════".color(color));
+    println!("{}", title.color(color).bold());
+    println!("{}", "═══════════════════════════════════════".color(color));
+    println!();
+}
+
+/// 展示时间使用的时区
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timezone {
+    /// 系统本地时区
+    Local,
+    /// IANA 时区，如 Asia/Shanghai
+    Named(Tz),
+    /// 固定偏移，如 +08:00
+    Fixed(FixedOffset),
+}
+
+/// 解析时区配置：空字符串为系统本地时区，其余为 IANA 名称或固定偏移
+pub fn parse_timezone(name: &str) -> Result<Timezone> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(Timezone::Local);
+    }
+
+    if let Ok(tz) = name.parse::<Tz>() {
+        return Ok(Timezone::Named(tz));
+    }
+
+    if let Ok(offset) = name.parse::<FixedOffset>() {
+        return Ok(Timezone::Fixed(offset));
+    }
+
+    anyhow::bail!("无法识别的时区: {}（应为 IANA 名称如 Asia/Shanghai，或固定偏移如 +08:00）", name)
+}
+
+/// 配置中的时区（首次使用时读取，无法识别时回退到系统本地时区）
+fn configured_timezone() -> Timezone {
+    static TIMEZONE: OnceLock<Timezone> = OnceLock::new();
+
+    *TIMEZONE.get_or_init(|| {
+        let name = ConfigManager::new()
+            .and_then(|mgr| mgr.load())
+            .map(|config| config.timezone)
+            .unwrap_or_default();
+
+        parse_timezone(&name).unwrap_or_else(|e| {
+            eprintln!("⚠️ {}，使用系统本地时区", e);
+            Timezone::Local
+        })
+    })
+}
+
+/// 按配置的时区格式化时间，所有面向用户的时间展示都应经过此函数
+pub fn format_time(ts: &DateTime<Utc>) -> String {
+    format_time_in(ts, configured_timezone())
+}
+
+/// 按指定时区格式化时间
+pub fn format_time_in(ts: &DateTime<Utc>, timezone: Timezone) -> String {
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+    match timezone {
+        Timezone::Local => ts.with_timezone(&Local).format(FORMAT).to_string(),
+        Timezone::Named(tz) => ts.with_timezone(&tz).format(FORMAT).to_string(),
+        Timezone::Fixed(offset) => ts.with_timezone(&offset).format(FORMAT).to_string(),
+    }
+}
+
+/// 指定时区下的当天时间（时:分:秒）
+pub fn time_of_day_in(ts: &DateTime<Utc>, timezone: Timezone) -> NaiveTime {
+    match timezone {
+        Timezone::Local => ts.with_timezone(&Local).time(),
+        Timezone::Named(tz) => ts.with_timezone(&tz).time(),
+        Timezone::Fixed(offset) => ts.with_timezone(&offset).time(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_colorize() {
+        assert!(should_colorize(None, true));
+        assert!(!should_colorize(None, false));
+        assert!(!should_colorize(Some("1"), true));
+        // 空值视为未设置
+        assert!(should_colorize(Some(""), true));
+    }
+
+    #[test]
+    fn test_format_time_with_timezone() {
+        let ts = "2025-01-02T03:04:05Z".parse::<DateTime<Utc>>().unwrap();
+
+        let shanghai = parse_timezone("Asia/Shanghai").unwrap();
+        assert_eq!(format_time_in(&ts, shanghai), "2025-01-02 11:04:05");
+
+        let fixed = parse_timezone("-05:00").unwrap();
+        assert_eq!(format_time_in(&ts, fixed), "2025-01-01 22:04:05");
+
+        assert_eq!(parse_timezone("").unwrap(), Timezone::Local);
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! 按客户端 IP 的令牌桶限流
+//!
+//! 每个 IP 一个令牌桶：容量为每分钟允许的请求数，按秒匀速补充。
+//! 用于保护对公网暴露的 transcript 页面，避免扫描随机会话 ID 造成大量文件系统访问
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// 跟踪的 IP 达到该数量时清理令牌桶，避免内存无限增长
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// 令牌桶限流器
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// 尝试为 `client` 消耗一个令牌，返回是否允许本次请求
+    ///
+    /// `per_minute` 为 0 时不限流
+    pub fn check(&self, client: &str, per_minute: u32) -> bool {
+        self.check_at(client, per_minute, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, per_minute: u32, now: Instant) -> bool {
+        if per_minute == 0 {
+            return true;
+        }
+
+        let capacity = per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return true;
+        };
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            evict(&mut buckets, now, capacity, refill_per_sec);
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            last: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.last = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 清理令牌桶：先去掉已回满的，仍超过上限的一半时按最后访问时间淘汰最早的
+///
+/// 每次清理后至少留出一半容量，清理的开销分摊到之后的新客户端上
+fn evict(buckets: &mut HashMap<String, Bucket>, now: Instant, capacity: f64, refill_per_sec: f64) {
+    buckets.retain(|_, bucket| {
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens + elapsed * refill_per_sec < capacity
+    });
+
+    let keep = MAX_TRACKED_CLIENTS / 2;
+    if buckets.len() <= keep {
+        return;
+    }
+
+    let excess = buckets.len() - keep;
+    let mut lasts: Vec<Instant> = buckets.values().map(|bucket| bucket.last).collect();
+    let (_, cutoff, _) = lasts.select_nth_unstable(excess - 1);
+    let cutoff = *cutoff;
+    buckets.retain(|_, bucket| bucket.last > cutoff);
+}
+
+/// 确定客户端标识
+///
+/// 默认使用连接的对端地址；对端是本机或 `trusted_proxies` 中的代理（经 frpc / nginx 转发）时，
+/// 从右向左读取 `X-Forwarded-For`，取第一个不可信的地址。最左侧的地址由客户端任意填写，不能直接使用
+pub fn client_key(forwarded_for: Option<&str>, peer: Option<IpAddr>, trusted_proxies: &[IpAddr]) -> String {
+    let Some(peer) = peer else {
+        return "unknown".to_string();
+    };
+
+    let is_trusted = |ip: &IpAddr| ip.is_loopback() || trusted_proxies.contains(ip);
+    let mut client = peer;
+    if is_trusted(&peer) {
+        for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !is_trusted(&ip) {
+                break;
+            }
+        }
+    }
+    client.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("1.1.1.1", 3, start));
+        }
+        assert!(!limiter.check_at("1.1.1.1", 3, start));
+
+        // 其他 IP 不受影响
+        assert!(limiter.check_at("2.2.2.2", 3, start));
+
+        // 每 20 秒补充一个令牌
+        assert!(limiter.check_at("1.1.1.1", 3, start + Duration::from_secs(20)));
+        assert!(!limiter.check_at("1.1.1.1", 3, start + Duration::from_secs(20)));
+
+        // 0 表示不限流
+        assert!(limiter.check_at("1.1.1.1", 0, start));
+    }
+
+    #[test]
+    fn test_evict_bounds_tracked_clients() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+
+        // 每个客户端都用完令牌，清理已回满的令牌桶无法腾出空间
+        for i in 0..MAX_TRACKED_CLIENTS {
+            let now = start + Duration::from_millis(i as u64);
+            assert!(limiter.check_at(&format!("c{}", i), 1, now));
+        }
+
+        let now = start + Duration::from_millis(MAX_TRACKED_CLIENTS as u64);
+        assert!(limiter.check_at("new", 1, now));
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_TRACKED_CLIENTS / 2 + 1);
+        assert!(buckets.contains_key("new"));
+        assert!(buckets.contains_key(&format!("c{}", MAX_TRACKED_CLIENTS - 1)));
+        assert!(!buckets.contains_key("c0"));
+    }
+
+    #[test]
+    fn test_client_key() {
+        let loopback = Some("127.0.0.1".parse().unwrap());
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let remote = Some("198.51.100.7".parse().unwrap());
+
+        // 经本机代理转发：取最右侧的不可信地址，忽略客户端伪造的左侧地址
+        assert_eq!(client_key(Some("1.2.3.4, 203.0.113.5"), loopback, &[]), "203.0.113.5");
+        assert_eq!(client_key(Some("1.2.3.4, 203.0.113.5, 10.0.0.1"), loopback, &[proxy]), "203.0.113.5");
+        assert_eq!(client_key(Some("203.0.113.5"), Some(proxy), &[proxy]), "203.0.113.5");
+        assert_eq!(client_key(Some(" "), loopback, &[]), "127.0.0.1");
+        assert_eq!(client_key(Some("garbage"), loopback, &[]), "127.0.0.1");
+
+        // 直连的客户端不读取 X-Forwarded-For
+        assert_eq!(client_key(Some("203.0.113.5"), remote, &[]), "198.51.100.7");
+        assert_eq!(client_key(Some("203.0.113.5"), Some(proxy), &[]), "10.0.0.1");
+        assert_eq!(client_key(None, None, &[]), "unknown");
+    }
+
+    #[test]
+    fn test_spoofed_forwarded_for_does_not_reset_limit() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        let peer = Some("198.51.100.7".parse().unwrap());
+
+        for spoofed in ["1.1.1.1", "2.2.2.2", "3.3.3.3"] {
+            let key = client_key(Some(spoofed), peer, &[]);
+            assert!(limiter.check_at(&key, 3, start));
+        }
+        let key = client_key(Some("4.4.4.4"), peer, &[]);
+        assert!(!limiter.check_at(&key, 3, start));
+
+        // 经本机代理转发时，伪造的左侧地址同样不影响
+        let loopback = Some("127.0.0.1".parse().unwrap());
+        for spoofed in ["1.1.1.1", "2.2.2.2", "3.3.3.3"] {
+            let key = client_key(Some(&format!("{}, 203.0.113.5", spoofed)), loopback, &[]);
+            assert!(limiter.check_at(&key, 3, start));
+        }
+        let key = client_key(Some("4.4.4.4, 203.0.113.5"), loopback, &[]);
+        assert!(!limiter.check_at(&key, 3, start));
+    }
+}
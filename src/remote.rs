@@ -1,22 +1,29 @@
 use anyhow::Result;
 use colored::*;
-use crate::config::ConfigManager;
+use crate::config::{self, ConfigManager, UNSETTABLE_FIELDS};
+use crate::output::{self, banner};
 use crate::sanitize::{sanitize_wxid, sanitize_listen_addr};
 
 pub fn handle_on() -> Result<()> {
     let config_mgr = ConfigManager::new()?;
-    config_mgr.enable_remote()?;
+    let changed = config_mgr.enable_remote()?;
 
     let config = config_mgr.load()?;
 
-    println!("{}", "═══════════════════════════════════════".green());
-    println!("{}", "  ✅ 远程模式已启用".green().bold());
-    println!("{}", "═══════════════════════════════════════".green());
-    println!();
+    if changed {
+        banner("  ✅ 远程模式已启用", Color::Green);
+    } else {
+        banner("  ℹ️ 远程模式已经是启用状态", Color::Cyan);
+    }
+
+    if output::is_quiet() {
+        return Ok(());
+    }
+
     println!("{}", "配置信息:".bright_white().bold());
     println!("  {} {}", "目标微信:".dimmed(), sanitize_wxid(&config.notification.wxid));
     println!("  {} {}", "监听地址:".dimmed(), sanitize_listen_addr(&config.notification.listen));
-    println!("  {} ~/.gewe-cc/remote.lock", "标记文件:".dimmed());
+    println!("  {} {}", "标记文件:".dimmed(), config_mgr.lock_file().display());
     println!();
     println!("{}", "任务完成后将自动等待微信指令。".dimmed());
     println!();
@@ -30,10 +37,12 @@ pub fn handle_off(session_id: Option<String>) -> Result<()> {
         let config_mgr = crate::config::ConfigManager::new()?;
         config_mgr.disable_session(&sid)?;
 
-        println!("{}", "═══════════════════════════════════════".cyan());
-        println!("{}", "  🛑 会话已关闭".cyan().bold());
-        println!("{}", "═══════════════════════════════════════".cyan());
-        println!();
+        banner("  🛑 会话已关闭", Color::Cyan);
+
+        if output::is_quiet() {
+            return Ok(());
+        }
+
         println!("  会话 ID: {}", sid.dimmed());
         println!();
         println!("{}", "本会话已结束，但全局远程模式仍处于启用状态。".dimmed());
@@ -44,12 +53,17 @@ pub fn handle_off(session_id: Option<String>) -> Result<()> {
 
     // 否则关闭全局远程模式
     let config_mgr = crate::config::ConfigManager::new()?;
-    config_mgr.disable_remote()?;
+    if !config_mgr.disable_remote()? {
+        banner("  ℹ️ 远程模式已经是禁用状态", Color::Cyan);
+        return Ok(());
+    }
+
+    banner("  ❌ 远程模式已禁用", Color::Yellow);
+
+    if output::is_quiet() {
+        return Ok(());
+    }
 
-    println!("{}", "═══════════════════════════════════════".yellow());
-    println!("{}", "  ❌ 远程模式已禁用".yellow().bold());
-    println!("{}", "═══════════════════════════════════════".yellow());
-    println!();
     println!("{}", "任务完成后将正常停止，不再等待微信指令。".dimmed());
     println!();
 
@@ -60,10 +74,12 @@ pub fn handle_status() -> Result<()> {
     let config_mgr = ConfigManager::new()?;
     let enabled = config_mgr.is_remote_enabled();
 
-    println!("{}", "═══════════════════════════════════════".cyan());
-    println!("{}", "  📊 远程模式状态".cyan().bold());
-    println!("{}", "═══════════════════════════════════════".cyan());
-    println!();
+    if output::is_quiet() {
+        println!("{}", if enabled { "✅ 已启用" } else { "❌ 未启用" });
+        return Ok(());
+    }
+
+    banner("  📊 远程模式状态", Color::Cyan);
 
     if enabled {
         let config = config_mgr.load()?;
@@ -72,7 +88,7 @@ pub fn handle_status() -> Result<()> {
         println!("  {}:", "配置".bright_white().bold());
         println!("    {} {}", "目标微信:".dimmed(), sanitize_wxid(&config.notification.wxid));
         println!("    {} {}", "监听地址:".dimmed(), sanitize_listen_addr(&config.notification.listen));
-        println!("    {} ~/.gewe-cc/remote.lock", "标记文件:".dimmed());
+        println!("    {} {}", "标记文件:".dimmed(), config_mgr.lock_file().display());
         println!();
         println!("  {} gewe-cc off", "禁用:".dimmed());
     } else {
@@ -86,19 +102,72 @@ pub fn handle_status() -> Result<()> {
     Ok(())
 }
 
-pub fn handle_config(wxid: Option<String>, listen: Option<String>, timeout: Option<u64>, transcript_domain: Option<String>) -> Result<()> {
+/// 检查配置文件是否完整、一致，输出检查清单，返回是否全部通过
+pub fn handle_validate() -> Result<bool> {
     let config_mgr = ConfigManager::new()?;
+    let config = config_mgr.load()?;
+    let checks = config.validate();
 
-    // 检查是否提供了至少一个参数
-    if wxid.is_none() && listen.is_none() && timeout.is_none() && transcript_domain.is_none() {
-        println!("{}", "═══════════════════════════════════════".yellow());
-        println!("{}", "  ⚙️  配置管理".yellow().bold());
-        println!("{}", "═══════════════════════════════════════".yellow());
+    banner("  🔍 配置检查", Color::Cyan);
+    if !output::is_quiet() {
+        println!("  {} {}", "配置文件:".dimmed(), config_mgr.config_file().display());
+        if config_mgr.needs_migration()? {
+            println!("  {} 配置文件为旧版本，可运行 gewe-cc config --migrate 写回迁移结果", "ℹ️".yellow());
+        }
         println!();
+    }
+
+    for check in &checks {
+        match &check.error {
+            None => println!("  {} {}", "✅".green(), check.name),
+            Some(error) => println!("  {} {}: {}", "❌".red(), check.name, error.red()),
+        }
+    }
+
+    let failed = checks.iter().filter(|check| check.error.is_some()).count();
+    println!();
+    if failed == 0 {
+        println!("  {}", "配置检查全部通过".green());
+    } else {
+        println!("  {}", format!("{} 项检查未通过", failed).red());
+    }
+    println!();
+
+    Ok(failed == 0)
+}
+
+pub fn handle_migrate() -> Result<()> {
+    let config_mgr = ConfigManager::new()?;
+
+    match config_mgr.migrate_file()? {
+        None => println!("✅ 配置已是最新版本 ({})，无需迁移", config::CONFIG_VERSION),
+        Some(migration) => {
+            println!("🔧 配置已从版本 {} 迁移到 {}", migration.from_version, config::CONFIG_VERSION);
+            for note in &migration.notes {
+                println!("   - {}", note);
+            }
+            println!("   原配置已备份到: {}", config_mgr.backup_file().display());
+        }
+    }
+
+    Ok(())
+}
+
+pub fn handle_config(wxid: Option<String>, listen: Option<String>, timeout: Option<u64>, transcript_domain: Option<String>, unset: Vec<String>) -> Result<()> {
+    let config_mgr = ConfigManager::new()?;
+    let quiet = output::is_quiet();
+
+    // 检查是否提供了至少一个参数
+    if wxid.is_none() && listen.is_none() && timeout.is_none() && transcript_domain.is_none() && unset.is_empty() {
+        if !quiet {
+            banner("  ⚙️  配置管理", Color::Yellow);
+        }
 
         let config = config_mgr.load()?;
 
-        println!("{}", "当前配置:".bright_white().bold());
+        if !quiet {
+            println!("{}", "当前配置:".bright_white().bold());
+        }
         println!("  {} {}", "目标微信:".dimmed(), sanitize_wxid(&config.notification.wxid));
         println!("  {} {}", "监听地址:".dimmed(), sanitize_listen_addr(&config.notification.listen));
         println!("  {} {}", "超时时间:".dimmed(), if config.gewe_cli.timeout == 0 {
@@ -114,18 +183,33 @@ pub fn handle_config(wxid: Option<String>, listen: Option<String>, timeout: Opti
             }
         );
         println!("  {} {}", "配置文件:".dimmed(), config_mgr.config_file().display());
+
+        if quiet {
+            return Ok(());
+        }
+
         println!();
         println!("{}", "修改配置:".bright_white().bold());
         println!("  gewe-cc config --wxid <新的微信ID>");
         println!("  gewe-cc config --listen <新的监听地址>");
         println!("  gewe-cc config --timeout <超时秒数>  # 0 表示无限等待");
         println!("  gewe-cc config --transcript-domain <域名>");
+        println!("  gewe-cc config --unset <配置项>  # 可选: {}", UNSETTABLE_FIELDS.join(", "));
         println!("  gewe-cc config --wxid <微信ID> --listen <监听地址> --timeout <秒数>");
         println!();
 
         return Ok(());
     }
 
+    // 先校验所有待重置的配置项，避免部分生效
+    if let Some(field) = unset.iter().find(|f| !UNSETTABLE_FIELDS.contains(&f.as_str())) {
+        anyhow::bail!(
+            "未知的配置项: {}\n可重置的配置项: {}",
+            field,
+            UNSETTABLE_FIELDS.join(", ")
+        );
+    }
+
     // 更新配置
     config_mgr.update_notification(wxid.clone(), listen.clone(), transcript_domain.clone())?;
 
@@ -136,10 +220,15 @@ pub fn handle_config(wxid: Option<String>, listen: Option<String>, timeout: Opti
         config_mgr.save(&config)?;
     }
 
-    println!("{}", "═══════════════════════════════════════".green());
-    println!("{}", "  ✅ 配置已更新".green().bold());
-    println!("{}", "═══════════════════════════════════════".green());
-    println!();
+    for field in &unset {
+        config_mgr.unset_field(field)?;
+    }
+
+    banner("  ✅ 配置已更新", Color::Green);
+
+    if quiet {
+        return Ok(());
+    }
 
     if let Some(wxid) = wxid {
         println!("  {} {}", "目标微信:".dimmed(), sanitize_wxid(&wxid));
@@ -161,6 +250,10 @@ pub fn handle_config(wxid: Option<String>, listen: Option<String>, timeout: Opti
         println!("  {} {}", "Transcript域名:".dimmed(), domain);
     }
 
+    for field in &unset {
+        println!("  {} {}", "已重置:".dimmed(), field);
+    }
+
     println!();
 
     Ok(())
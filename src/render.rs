@@ -0,0 +1,83 @@
+//! 渲染预览
+//!
+//! 无需进行中的 Claude Code 会话即可查看 transcript 页面效果：
+//! 内置的示例 transcript 覆盖各类内容块（文本、思考、工具调用、工具结果、错误结果、图片），
+//! 便于调整样式、主题或新增内容块类型时快速预览
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+use crate::config::ConfigManager;
+use crate::output::{self, banner};
+use crate::server;
+use crate::transcript;
+
+/// 内置示例 transcript
+const SAMPLE_TRANSCRIPT: &str = include_str!("../assets/sample-transcript.jsonl");
+
+/// 示例页面使用的会话 ID
+const SAMPLE_SESSION_ID: &str = "sample";
+
+/// 渲染示例或指定会话的 transcript 页面
+///
+/// `theme` 覆盖配置中的代码高亮主题；未指定 `out` 时输出到 stdout
+pub fn run(session_id: Option<&str>, theme: Option<String>, out: Option<&Path>) -> Result<()> {
+    let config = ConfigManager::new()?.load().unwrap_or_default();
+    let mut options = config.transcript.clone();
+    if let Some(theme) = theme {
+        options.code_theme = theme;
+    }
+
+    let html = match session_id {
+        Some(session_id) => {
+            let path = server::find_transcript(session_id)?;
+            let messages = transcript::parse_transcript(&path, &options.entry_types)?;
+            transcript::render_to_html(&messages, session_id, &options)
+        }
+        None => render_sample(&mut options)?,
+    };
+
+    let Some(out) = out else {
+        print!("{}", html);
+        return Ok(());
+    };
+
+    fs::write(out, &html).context(format!("写入文件失败: {}", out.display()))?;
+
+    banner("  🎨 页面已渲染", Color::Green);
+    println!("  {} {}", "文件:".dimmed(), out.display());
+    if !output::is_quiet() {
+        println!("  {} {}", "代码主题:".dimmed(), options.code_theme);
+        println!();
+    }
+
+    Ok(())
+}
+
+/// 渲染内置示例，展示包括系统内容在内的全部内容块
+fn render_sample(options: &mut crate::config::TranscriptConfig) -> Result<String> {
+    options.show_system = true;
+    let messages = transcript::parse_transcript_content(SAMPLE_TRANSCRIPT, &[], "内置示例")?;
+    Ok(transcript::render_to_html(&messages, SAMPLE_SESSION_ID, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TranscriptConfig;
+
+    #[test]
+    fn test_render_sample_covers_block_types() {
+        let html = render_sample(&mut TranscriptConfig::default()).unwrap();
+
+        assert!(html.contains("帮我修复登录接口"));
+        assert!(html.contains("先看一下登录接口的实现"));
+        assert!(html.contains("并行工具调用 (2)"));
+        assert!(html.contains(r#"<div class="tool-result error">"#));
+        assert!(html.contains("iVBORw0KGgo"));
+        assert!(html.contains("子代理"));
+        assert!(html.contains("system-block"));
+    }
+}
@@ -1,6 +1,8 @@
-/// 脱敏工具模块
-///
-/// 用于对敏感信息进行脱敏处理，保护用户隐私
+//! 脱敏工具模块
+//!
+//! 用于对敏感信息进行脱敏处理，保护用户隐私
+
+use crate::util::{first_chars, last_chars};
 
 /// 脱敏 wxid（微信ID）
 ///
@@ -24,22 +26,19 @@ pub fn sanitize_wxid(wxid: &str) -> String {
 
     // 如果是 wxid_ 格式
     if let Some(id_part) = wxid.strip_prefix("wxid_") {
-        if id_part.len() < 3 {
+        if id_part.chars().count() < 3 {
             // 如果 ID 部分太短（小于 3 位），直接保留
             return wxid.to_string();
         }
         // 保留后 3 位
-        let suffix = &id_part[id_part.len() - 3..];
-        return format!("wxid_***{}", suffix);
+        return format!("wxid_***{}", last_chars(id_part, 3));
     }
 
-    // 其他格式，保留前 6 位和后 3 位
-    if wxid.len() <= 9 {
+    // 其他格式，保留前 6 位和后 3 位（按字符计算，兼容中文等多字节字符）
+    if wxid.chars().count() <= 9 {
         return wxid.to_string();
     }
-    let prefix = &wxid[..6];
-    let suffix = &wxid[wxid.len() - 3..];
-    format!("{}***{}", prefix, suffix)
+    format!("{}***{}", first_chars(wxid, 6), last_chars(wxid, 3))
 }
 
 /// 脱敏监听地址
@@ -102,6 +101,10 @@ mod tests {
         // 非 wxid_ 格式
         assert_eq!(sanitize_wxid("user123456789"), "user12***789");
         assert_eq!(sanitize_wxid("short"), "short");
+
+        // 多字节字符不会在字节中间切断
+        assert_eq!(sanitize_wxid("张三的微信号是这个呀"), "张三的微信号***这个呀");
+        assert_eq!(sanitize_wxid("wxid_🎉🎉🎉🎉"), "wxid_***🎉🎉🎉");
     }
 
     #[test]
@@ -1,38 +1,83 @@
 use anyhow::{Context, Result};
 use axum::{
-    extract::Path,
-    http::{StatusCode, header},
+    body::Bytes,
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Query, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
-    routing::get,
-    Router,
+    routing::{get, post},
+    Json, Router,
     body::Body,
 };
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::io::Write;
+use std::net::SocketAddr;
 use std::path::{Component, Path as StdPath, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 use tokio_util::io::ReaderStream;
 
-use crate::config::ConfigManager;
+use crate::config::{self, Config, ConfigManager};
+use crate::format;
+use crate::history::ReplyHistory;
+use crate::http;
+use crate::output;
+use crate::ratelimit::{self, RateLimiter};
 use crate::transcript;
 
 /// Session 映射管理器
 #[derive(Debug, Clone)]
 pub struct SessionRegistry {
-    sessions: Arc<RwLock<HashMap<String, PathBuf>>>,
+    sessions: Arc<RwLock<HashMap<String, SessionEntry>>>,
     sessions_file: PathBuf,
+    /// 等待人工回复的会话标记目录，每个会话一个文件，内容为开始等待的时间戳
+    awaiting_dir: PathBuf,
+    /// 最多保留的会话数（0 表示不限制），超出时淘汰最早注册的会话
+    max_sessions: usize,
+}
+
+/// sessions.json 中的一条会话记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SessionEntry {
+    transcript_path: PathBuf,
+    /// 注册时间，超出 `max_sessions` 时最早注册的先被淘汰
+    registered_at: DateTime<Utc>,
+}
+
+/// 会话列表中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub transcript_path: PathBuf,
+    /// 开始等待回复的时间（未在等待时为 None）
+    pub awaiting_since: Option<DateTime<Utc>>,
 }
 
 impl SessionRegistry {
     pub fn new() -> Result<Self> {
         let config_mgr = ConfigManager::new()?;
         let config_dir = config_mgr.config_file().parent().unwrap().to_path_buf();
+
+        let max_sessions = config_mgr
+            .load()
+            .map(|config| config.remote.max_sessions)
+            .unwrap_or_else(|_| Config::default().remote.max_sessions);
+
+        Self::with_dir(config_dir, max_sessions)
+    }
+
+    /// 使用指定目录下的 sessions.json 与等待回复标记目录
+    pub(crate) fn with_dir(config_dir: PathBuf, max_sessions: usize) -> Result<Self> {
         let sessions_file = config_dir.join("sessions.json");
 
         // 读取现有的 session 映射
         let sessions = if sessions_file.exists() {
             let content = fs::read_to_string(&sessions_file)?;
-            serde_json::from_str(&content).unwrap_or_default()
+            parse_sessions(&content).unwrap_or_default()
         } else {
             HashMap::new()
         };
@@ -40,62 +85,432 @@ impl SessionRegistry {
         Ok(Self {
             sessions: Arc::new(RwLock::new(sessions)),
             sessions_file,
+            awaiting_dir: config_dir.join("awaiting"),
+            max_sessions,
         })
     }
 
     /// 注册一个 session（同步版本）
-    pub fn register(&self, session_id: String, transcript_path: PathBuf) -> Result<()> {
+    ///
+    /// session 已映射到相同路径时不写文件（Stop Hook 循环中会反复注册），
+    /// 返回是否有变更。会话数超过 `max_sessions` 时淘汰最早注册的会话，
+    /// 被淘汰的会话仍可通过默认路径推导找到 transcript
+    pub fn register(&self, session_id: String, transcript_path: PathBuf) -> Result<bool> {
         let mut sessions = self.sessions.write().map_err(|e| {
             anyhow::anyhow!("获取写锁失败: {}", e)
         })?;
 
-        sessions.insert(session_id, transcript_path);
+        if sessions.get(&session_id).map(|entry| &entry.transcript_path) == Some(&transcript_path) {
+            return Ok(false);
+        }
+
+        sessions.insert(
+            session_id.clone(),
+            SessionEntry {
+                transcript_path,
+                registered_at: Utc::now(),
+            },
+        );
+        evict_oldest_sessions(&mut sessions, self.max_sessions, &session_id);
 
         // 保存到文件
         self.save_sessions(&sessions)?;
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// sessions.json 的路径
+    pub fn sessions_file(&self) -> &StdPath {
+        &self.sessions_file
     }
 
     /// 获取 session 的 transcript 路径（同步版本）
     pub fn get(&self, session_id: &str) -> Option<PathBuf> {
         if let Ok(sessions) = self.sessions.read()
-            && let Some(path) = sessions.get(session_id)
+            && let Some(entry) = sessions.get(session_id)
         {
-            return Some(path.clone());
+            return Some(entry.transcript_path.clone());
         }
 
         // 可能有新的 sessions.json 写入，尝试重新加载
         if let Ok(content) = fs::read_to_string(&self.sessions_file)
-            && let Ok(updated) = serde_json::from_str::<HashMap<String, PathBuf>>(&content)
+            && let Some(updated) = parse_sessions(&content)
             && let Ok(mut sessions) = self.sessions.write()
         {
             *sessions = updated;
         }
 
         let sessions = self.sessions.read().ok()?;
-        sessions.get(session_id).cloned()
+        sessions.get(session_id).map(|entry| entry.transcript_path.clone())
+    }
+
+    /// 标记或清除会话的"等待回复"状态
+    ///
+    /// 状态持久化为文件，重启后的 serve 进程仍可读取；会话 ID 不合法时拒绝，避免拼出目录外的路径
+    pub fn set_awaiting(&self, session_id: &str, awaiting: bool) -> Result<()> {
+        if !is_valid_session_id(session_id, false) {
+            anyhow::bail!("会话 ID 不合法: {}", session_id);
+        }
+        let marker = self.awaiting_dir.join(session_id);
+        if awaiting {
+            fs::create_dir_all(&self.awaiting_dir).context("创建等待回复标记目录失败")?;
+            fs::write(&marker, Utc::now().timestamp().to_string()).context("写入等待回复标记失败")?;
+        } else if marker.exists() {
+            fs::remove_file(&marker).context("清除等待回复标记失败")?;
+        }
+        Ok(())
+    }
+
+    /// 会话开始等待回复的时间
+    pub fn awaiting_since(&self, session_id: &str) -> Option<DateTime<Utc>> {
+        if !is_valid_session_id(session_id, false) {
+            return None;
+        }
+        let content = fs::read_to_string(self.awaiting_dir.join(session_id)).ok()?;
+        let secs = content.trim().parse::<i64>().ok()?;
+        DateTime::from_timestamp(secs, 0)
+    }
+
+    /// 列出所有会话，等待回复的排在前面（按开始等待时间先后），其余按 ID 排序
+    pub fn list(&self) -> Vec<SessionSummary> {
+        // 其他进程可能已写入新的 sessions.json
+        let sessions = fs::read_to_string(&self.sessions_file)
+            .ok()
+            .and_then(|content| parse_sessions(&content))
+            .or_else(|| self.sessions.read().ok().map(|sessions| sessions.clone()))
+            .unwrap_or_default();
+
+        let mut list: Vec<SessionSummary> = sessions
+            .into_iter()
+            .map(|(session_id, entry)| SessionSummary {
+                awaiting_since: self.awaiting_since(&session_id),
+                session_id,
+                transcript_path: entry.transcript_path,
+            })
+            .collect();
+
+        list.sort_by(|a, b| match (a.awaiting_since, b.awaiting_since) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.session_id.cmp(&b.session_id),
+        });
+        list
+    }
+
+    /// 已注册的 session 数量
+    pub fn session_count(&self) -> usize {
+        self.sessions.read().map(|sessions| sessions.len()).unwrap_or(0)
     }
 
     /// 保存 sessions 到文件
-    fn save_sessions(&self, sessions: &HashMap<String, PathBuf>) -> Result<()> {
+    ///
+    /// 先写同目录下名称唯一的临时文件再重命名，避免并发读取到写了一半的内容，
+    /// 也避免多个进程同时保存时共用同一个临时文件
+    fn save_sessions(&self, sessions: &HashMap<String, SessionEntry>) -> Result<()> {
         let content = serde_json::to_string_pretty(sessions)?;
-        fs::write(&self.sessions_file, content)?;
+        let dir = self.sessions_file.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(StdPath::new("."));
+        let mut tmp_file = tempfile::NamedTempFile::new_in(dir).context("创建 sessions 临时文件失败")?;
+        tmp_file.write_all(content.as_bytes()).context("写入 sessions 临时文件失败")?;
+        tmp_file.persist(&self.sessions_file).context("保存 sessions.json 失败")?;
         Ok(())
     }
 }
 
+/// 解析 sessions.json
+///
+/// 兼容旧格式（会话 ID → transcript 路径），旧记录的注册时间视为最早，超出上限时优先淘汰
+fn parse_sessions(content: &str) -> Option<HashMap<String, SessionEntry>> {
+    if let Ok(sessions) = serde_json::from_str::<HashMap<String, SessionEntry>>(content) {
+        return Some(sessions);
+    }
+
+    let legacy = serde_json::from_str::<HashMap<String, PathBuf>>(content).ok()?;
+    Some(
+        legacy
+            .into_iter()
+            .map(|(session_id, transcript_path)| {
+                let entry = SessionEntry {
+                    transcript_path,
+                    registered_at: DateTime::UNIX_EPOCH,
+                };
+                (session_id, entry)
+            })
+            .collect(),
+    )
+}
+
+/// 会话数超过 `max` 时按注册时间淘汰最早的会话（`max` 为 0 时不限制），`keep` 指定的会话不会被淘汰
+fn evict_oldest_sessions(sessions: &mut HashMap<String, SessionEntry>, max: usize, keep: &str) {
+    if max == 0 || sessions.len() <= max {
+        return;
+    }
+
+    let mut candidates: Vec<(DateTime<Utc>, String)> = sessions
+        .iter()
+        .filter(|(session_id, _)| session_id.as_str() != keep)
+        .map(|(session_id, entry)| (entry.registered_at, session_id.clone()))
+        .collect();
+    candidates.sort();
+
+    let excess = sessions.len() - max;
+    for (_, session_id) in candidates.into_iter().take(excess) {
+        sessions.remove(&session_id);
+    }
+}
+
+/// 等待中的回复记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingReply {
+    /// 等待其回复的微信 ID
+    pub wxid: String,
+    /// 开始等待的时间
+    pub created_at: DateTime<Utc>,
+    /// 收到的回复（未收到时为 None）
+    #[serde(default)]
+    pub reply: Option<String>,
+}
+
+/// 等待回复注册表
+///
+/// 每个会话对应 `~/.gewe-cc/pending_replies/<session_id>.json`，
+/// 由等待方（send-link 进程）创建，由 serve 进程的 webhook 写入回复
+#[derive(Debug, Clone)]
+pub struct PendingReplyRegistry {
+    dir: PathBuf,
+}
+
+impl PendingReplyRegistry {
+    pub fn new() -> Result<Self> {
+        let config_mgr = ConfigManager::new()?;
+        let config_dir = config_mgr.config_file().parent().unwrap().to_path_buf();
+        Ok(Self::with_dir(config_dir.join("pending_replies")))
+    }
+
+    fn with_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn entry_file(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", session_id))
+    }
+
+    /// 登记一个等待回复的会话
+    pub fn create(&self, session_id: &str, wxid: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir).context("创建等待回复目录失败")?;
+        let pending = PendingReply {
+            wxid: wxid.to_string(),
+            created_at: Utc::now(),
+            reply: None,
+        };
+        self.write(session_id, &pending)
+    }
+
+    /// 取出会话已收到的回复；收到后移除登记
+    pub fn take_reply(&self, session_id: &str) -> Option<String> {
+        let content = fs::read_to_string(self.entry_file(session_id)).ok()?;
+        let pending: PendingReply = serde_json::from_str(&content).ok()?;
+        let reply = pending.reply?;
+        let _ = fs::remove_file(self.entry_file(session_id));
+        Some(reply)
+    }
+
+    /// 取消等待
+    pub fn remove(&self, session_id: &str) {
+        let _ = fs::remove_file(self.entry_file(session_id));
+    }
+
+    /// 将收到的回复匹配给该微信 ID 最早开始等待的会话
+    ///
+    /// 返回匹配到的 session_id
+    pub fn deliver(&self, from_wxid: &str, text: &str) -> Result<Option<String>> {
+        let mut oldest: Option<(String, PendingReply)> = None;
+
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(None),
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(session_id) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|_| path.extension().is_some_and(|ext| ext == "json"))
+            else {
+                continue;
+            };
+
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let Ok(pending) = serde_json::from_str::<PendingReply>(&content) else {
+                continue;
+            };
+
+            if pending.wxid != from_wxid || pending.reply.is_some() {
+                continue;
+            }
+
+            if oldest
+                .as_ref()
+                .is_none_or(|(_, current)| pending.created_at < current.created_at)
+            {
+                oldest = Some((session_id.to_string(), pending));
+            }
+        }
+
+        let Some((session_id, mut pending)) = oldest else {
+            return Ok(None);
+        };
+
+        pending.reply = Some(text.to_string());
+        self.write(&session_id, &pending)?;
+        Ok(Some(session_id))
+    }
+
+    fn write(&self, session_id: &str, pending: &PendingReply) -> Result<()> {
+        let content = serde_json::to_string_pretty(pending)?;
+        fs::write(self.entry_file(session_id), content).context("写入等待回复记录失败")?;
+        Ok(())
+    }
+}
+
+/// 服务器运行指标（Prometheus 文本格式导出）
+#[derive(Debug, Default)]
+struct Metrics {
+    transcript_requests: AtomicU64,
+    transcript_not_found: AtomicU64,
+    transcript_parse_errors: AtomicU64,
+}
+
+impl Metrics {
+    fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 渲染为 Prometheus 文本格式
+    fn render(&self, registry_size: usize) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "gewe_cc_transcript_requests_total",
+                "Transcript 页面请求总数",
+                &self.transcript_requests,
+            ),
+            (
+                "gewe_cc_transcript_not_found_total",
+                "Transcript 未找到（404）次数",
+                &self.transcript_not_found,
+            ),
+            (
+                "gewe_cc_transcript_parse_errors_total",
+                "Transcript 解析失败次数",
+                &self.transcript_parse_errors,
+            ),
+        ];
+
+        for (name, help, counter) in counters {
+            out.push_str(&format!("# HELP {} {}\n", name, help));
+            out.push_str(&format!("# TYPE {} counter\n", name));
+            out.push_str(&format!("{} {}\n", name, counter.load(Ordering::Relaxed)));
+        }
+
+        out.push_str("# HELP gewe_cc_registry_sessions 已注册的 session 数量\n");
+        out.push_str("# TYPE gewe_cc_registry_sessions gauge\n");
+        out.push_str(&format!("gewe_cc_registry_sessions {}\n", registry_size));
+
+        out
+    }
+}
+
+/// 服务器共享状态
+#[derive(Debug, Clone)]
+struct AppState {
+    registry: SessionRegistry,
+    pending_replies: PendingReplyRegistry,
+    history: ReplyHistory,
+    config: Arc<RwLock<Config>>,
+    metrics: Arc<Metrics>,
+    rate_limiter: Arc<RateLimiter>,
+    nonces: Arc<NonceCache>,
+}
+
+impl AppState {
+    /// 当前生效配置的快照
+    fn config(&self) -> Config {
+        self.config
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// serve 命令的启动参数
+#[derive(Debug, Default)]
+pub struct ServeOptions {
+    /// 监听端口
+    pub port: u16,
+    /// 是否启用 /metrics
+    pub metrics: bool,
+    /// 是否监听配置文件变化
+    pub watch: bool,
+    /// 是否在 / 提供会话索引页
+    pub index: bool,
+    /// HTTPS 证书与私钥（PEM 格式），为 None 时使用 HTTP
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// 启动后是否在默认浏览器中打开本地地址
+    pub open: bool,
+}
+
 /// 启动 HTTP 服务器
-pub async fn start_server(port: u16) -> Result<()> {
-    let registry = SessionRegistry::new()?;
+///
+/// `metrics` 为 true 时额外提供 `GET /metrics` 指标接口；
+/// `watch` 为 true 时监听配置文件变化并自动重新加载
+pub async fn start_server(options: ServeOptions) -> Result<()> {
+    let config_mgr = ConfigManager::new()?;
+    let state = AppState {
+        registry: SessionRegistry::new()?,
+        pending_replies: PendingReplyRegistry::new()?,
+        history: ReplyHistory::new()?,
+        config: Arc::new(RwLock::new(config_mgr.load().unwrap_or_default())),
+        metrics: Arc::new(Metrics::default()),
+        rate_limiter: Arc::new(RateLimiter::default()),
+        nonces: Arc::new(NonceCache::default()),
+    };
 
-    let app = Router::new()
-        .route("/{session_id}", get(transcript_handler))
-        .route("/assets/{*path}", get(static_handler))
-        .route("/health", get(health_handler))
-        .with_state(registry);
+    // 先加载证书，出错时在启动前给出明确提示
+    let tls = match &options.tls {
+        Some((cert, key)) => Some(load_tls_config(cert, key).await?),
+        None => None,
+    };
+
+    if options.watch {
+        tokio::spawn(watch_config(config_mgr, state.config.clone()));
+    }
 
+    let app = build_router(state, options.metrics, options.index);
+    let port = options.port;
     let addr = format!("0.0.0.0:{}", port);
+
+    if let Some(tls) = tls {
+        let socket_addr: SocketAddr = addr.parse().context(format!("无效的监听地址: {}", addr))?;
+
+        println!("🔒 HTTPS 服务器已启动: https://{}", addr);
+        println!("   本地访问: https://localhost:{}", port);
+
+        if options.open {
+            open_browser(format!("https://localhost:{}/", port));
+        }
+
+        axum_server::bind_rustls(socket_addr, tls)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .context(format!("HTTPS 服务器运行失败（地址: {}）", addr))?;
+
+        return Ok(());
+    }
+
     let listener = tokio::net::TcpListener::bind(&addr)
         .await
         .context(format!("绑定地址失败: {}", addr))?;
@@ -104,30 +519,214 @@ pub async fn start_server(port: u16) -> Result<()> {
     println!("   本地访问: http://localhost:{}", port);
     println!("   配置 frpc 转发后可通过域名访问");
 
-    axum::serve(listener, app)
+    if options.open {
+        open_browser(format!("http://localhost:{}/", port));
+    }
+
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .context("HTTP 服务器运行失败")?;
 
     Ok(())
 }
 
+/// 在默认浏览器中打开 `url`（serve --open）
+///
+/// 无图形界面的环境（如 SSH 登录的 Linux 服务器）直接跳过；打开失败只输出警告，不影响服务运行
+fn open_browser(url: String) {
+    let display = std::env::var_os("DISPLAY").is_some_and(|v| !v.is_empty())
+        || std::env::var_os("WAYLAND_DISPLAY").is_some_and(|v| !v.is_empty());
+    if !has_display(cfg!(target_os = "linux"), display) {
+        println!("   未检测到图形界面，跳过打开浏览器");
+        return;
+    }
+
+    // 部分平台上打开浏览器会阻塞到启动命令退出，放到后台线程避免拖慢服务
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = webbrowser::open(&url) {
+            eprintln!("⚠️ 打开浏览器失败（{}）: {}", url, e);
+        }
+    });
+}
+
+/// 是否可以打开图形浏览器：Linux 需要 X11 或 Wayland 显示，macOS / Windows 视为始终可用
+fn has_display(linux: bool, display: bool) -> bool {
+    !linux || display
+}
+
+/// 加载 PEM 格式的证书链与私钥
+async fn load_tls_config(cert: &StdPath, key: &StdPath) -> Result<axum_server::tls_rustls::RustlsConfig> {
+    for (label, path) in [("证书", cert), ("私钥", key)] {
+        if !path.is_file() {
+            anyhow::bail!("TLS {}文件不存在: {}", label, path.display());
+        }
+    }
+
+    axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key)
+        .await
+        .context(format!(
+            "加载 TLS 证书失败，请确认为 PEM 格式且证书与私钥匹配\n证书: {}\n私钥: {}",
+            cert.display(),
+            key.display()
+        ))
+}
+
+/// 配置文件轮询间隔
+const CONFIG_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 轮询配置文件的修改时间，变化时重新加载配置
+///
+/// 只替换配置本身，SessionRegistry 等运行时状态保持不变；
+/// 新配置解析失败时保留旧配置
+async fn watch_config(config_mgr: ConfigManager, config: Arc<RwLock<Config>>) {
+    let modified = |path: &StdPath| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified(config_mgr.config_file());
+
+    loop {
+        tokio::time::sleep(CONFIG_WATCH_INTERVAL).await;
+
+        let current = modified(config_mgr.config_file());
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        match config_mgr.load() {
+            Ok(new_config) => {
+                if let Ok(mut guard) = config.write() {
+                    *guard = new_config;
+                    println!("🔄 配置已重新加载");
+                }
+            }
+            Err(e) => eprintln!("⚠️ 重新加载配置失败，继续使用旧配置: {:#}", e),
+        }
+    }
+}
+
+/// 构建路由
+fn build_router(state: AppState, metrics: bool, index: bool) -> Router {
+    // 会触发文件系统查找的会话页面按客户端 IP 限流
+    let mut sessions = Router::new()
+        .route("/{session_id}", get(transcript_handler))
+        .route("/{session_id}/replies", get(replies_handler))
+        .route("/{session_id}/events", get(events_handler));
+    if index {
+        sessions = sessions.route("/", get(index_handler));
+    }
+    let sessions = sessions.route_layer(middleware::from_fn_with_state(state.clone(), rate_limit));
+
+    let mut app = Router::new().merge(sessions);
+    if metrics {
+        app = app.route("/metrics", get(metrics_handler));
+    }
+
+    app.route("/assets/{*path}", get(static_handler))
+        .route("/health", get(health_handler))
+        .route(
+            "/webhook/reply",
+            post(reply_webhook_handler).layer(DefaultBodyLimit::max(MAX_REPLY_BODY_BYTES)),
+        )
+        .route(
+            "/webhook/telegram",
+            post(telegram_webhook_handler).layer(DefaultBodyLimit::max(MAX_REPLY_BODY_BYTES)),
+        )
+        .with_state(state)
+}
+
+/// 限流中间件：超过 `transcript.rate_limit_per_minute` 时返回 429
+async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let forwarded_for = request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok());
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    let config = state.config();
+    let trusted_proxies: Vec<std::net::IpAddr> = config
+        .transcript
+        .trusted_proxies
+        .iter()
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+    let client = ratelimit::client_key(forwarded_for, peer, &trusted_proxies);
+
+    if !state.rate_limiter.check(&client, config.transcript.rate_limit_per_minute) {
+        return (StatusCode::TOO_MANY_REQUESTS, "请求过于频繁，请稍后再试").into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Transcript 页面查询参数
+#[derive(Debug, Default, Deserialize)]
+struct TranscriptQuery {
+    /// 消息顺序：asc 或 desc，覆盖配置中的默认值
+    order: Option<String>,
+    /// 自动刷新间隔（秒），用于查看进行中的会话
+    refresh: Option<u64>,
+    /// 是否展示思考过程（0 / false 隐藏），覆盖配置中的默认值
+    thinking: Option<String>,
+    /// 实时模式（1 / true 开启）：订阅事件流，transcript 更新时自动刷新
+    live: Option<String>,
+}
+
+/// 会话 ID 最大长度
+const MAX_SESSION_ID_LEN: usize = 128;
+
+/// 会话 ID 是否合法
+///
+/// 严格模式只接受 UUID 形式（十六进制与 `-`）；否则也接受字母、数字与 `_`。
+/// 两种模式都不允许 `/`、`.` 等字符，避免拼接路径时发生目录穿越
+pub fn is_valid_session_id(session_id: &str, strict: bool) -> bool {
+    !session_id.is_empty()
+        && session_id.len() <= MAX_SESSION_ID_LEN
+        && session_id.chars().all(|c| {
+            c == '-' || if strict { c.is_ascii_hexdigit() } else { c.is_ascii_alphanumeric() || c == '_' }
+        })
+}
+
+/// 会话 ID 不合法时返回 400 响应，应在访问文件系统之前调用
+fn reject_invalid_session_id(session_id: &str, config: &Config) -> Option<Response> {
+    if is_valid_session_id(session_id, config.transcript.strict_session_ids) {
+        return None;
+    }
+    Some((StatusCode::BAD_REQUEST, "会话 ID 格式不正确").into_response())
+}
+
 /// Transcript 路由处理
 async fn transcript_handler(
     Path(session_id): Path<String>,
-    axum::extract::State(registry): axum::extract::State<SessionRegistry>,
+    Query(query): Query<TranscriptQuery>,
+    State(state): State<AppState>,
 ) -> impl IntoResponse {
-    // 尝试从注册表获取路径
-    let transcript_path = if let Some(path) = registry.get(&session_id) {
-        path
-    } else {
-        // 如果注册表中没有，尝试从 Claude Code 的默认路径推导
-        match infer_transcript_path(&session_id) {
-            Some(path) if path.exists() => path,
-            _ => {
-                return (
-                    StatusCode::NOT_FOUND,
-                    Html(format!(
-                        r#"<!DOCTYPE html>
+    Metrics::inc(&state.metrics.transcript_requests);
+    let mut config = state.config();
+    if let Some(rejection) = reject_invalid_session_id(&session_id, &config) {
+        return rejection;
+    }
+    if let Some(order) = query.order {
+        config.transcript.message_order = order;
+    }
+    if let Some(refresh) = query.refresh {
+        config.transcript.refresh_secs = refresh;
+    }
+    if let Some(thinking) = query.thinking {
+        config.transcript.show_thinking = !matches!(thinking.as_str(), "0" | "false");
+    }
+    if let Some(live) = query.live {
+        config.transcript.live = matches!(live.as_str(), "1" | "true");
+    }
+
+    let transcript_path = match resolve_transcript_path_blocking(&state.registry, &session_id).await {
+        Some((path, _)) => path,
+        None => {
+            Metrics::inc(&state.metrics.transcript_not_found);
+            return (
+                StatusCode::NOT_FOUND,
+                Html(format!(
+                    r#"<!DOCTYPE html>
 <html>
 <head><title>Session Not Found</title></head>
 <body>
@@ -136,16 +735,16 @@ async fn transcript_handler(
     <p>请检查 Session ID 是否正确</p>
 </body>
 </html>"#,
-                        session_id
-                    )),
-                )
-                    .into_response();
-            }
+                    transcript::html_escape(&session_id)
+                )),
+            )
+                .into_response();
         }
     };
 
     // 检查文件是否存在
     if !transcript_path.exists() {
+        Metrics::inc(&state.metrics.transcript_not_found);
         return (
             StatusCode::NOT_FOUND,
             Html(format!(
@@ -156,19 +755,21 @@ async fn transcript_handler(
     <h1>❌ Transcript 文件不存在</h1>
     <p>Session ID: <code>{}</code></p>
     <p>路径: <code>{}</code></p>
+    <p>会话已登记，但 Claude Code 尚未创建 transcript 文件</p>
 </body>
 </html>"#,
-                session_id,
-                transcript_path.display()
+                transcript::html_escape(&session_id),
+                transcript::html_escape(&transcript_path.display().to_string())
             )),
         )
             .into_response();
     }
 
     // 解析 transcript
-    let messages = match transcript::parse_transcript(&transcript_path) {
+    let messages = match transcript::parse_transcript(&transcript_path, &config.transcript.entry_types) {
         Ok(msgs) => msgs,
         Err(e) => {
+            Metrics::inc(&state.metrics.transcript_parse_errors);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Html(format!(
@@ -181,7 +782,8 @@ async fn transcript_handler(
     <p>错误: <code>{}</code></p>
 </body>
 </html>"#,
-                    session_id, e
+                    transcript::html_escape(&session_id),
+                    transcript::html_escape(&e.to_string())
                 )),
             )
                 .into_response();
@@ -189,11 +791,491 @@ async fn transcript_handler(
     };
 
     // 渲染 HTML
-    let html = transcript::render_to_html(&messages, &session_id);
+    let html = transcript::render_to_html(&messages, &session_id, &config.transcript);
 
     (StatusCode::OK, Html(html)).into_response()
 }
 
+/// 会话索引页：列出所有会话，高亮正在等待回复的会话
+async fn index_handler(State(state): State<AppState>) -> impl IntoResponse {
+    // 列出会话与读取预览都要访问文件系统，放到阻塞线程池中执行
+    let registry = state.registry.clone();
+    let (sessions, previews) = tokio::task::spawn_blocking(move || {
+        let sessions = registry.list();
+        let paths: Vec<PathBuf> = sessions.iter().map(|session| session.transcript_path.clone()).collect();
+        let previews = session_previews(preview_cache(), &paths);
+        (sessions, previews)
+    })
+    .await
+    .unwrap_or_default();
+
+    let mut rows = String::new();
+    for (session, preview) in sessions.iter().zip(previews) {
+        let id = transcript::html_escape(&session.session_id);
+        let (class, status) = match session.awaiting_since {
+            Some(since) => (
+                " class=\"awaiting\"",
+                format!("⏳ 等待回复（{} 起）", output::format_time(&since)),
+            ),
+            None => ("", String::new()),
+        };
+        let preview = preview
+            .map(|preview| format!("<div class=\"preview\">{}</div>", transcript::html_escape(&preview)))
+            .unwrap_or_default();
+        rows.push_str(&format!(
+            "        <tr{}><td><a href=\"/{}\"><code>{}</code></a>{}</td><td>{}</td></tr>\n",
+            class, id, id, preview, status
+        ));
+    }
+
+    let awaiting = sessions.iter().filter(|s| s.awaiting_since.is_some()).count();
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta http-equiv="refresh" content="30">
+    <title>gewe-cc 会话</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; max-width: 900px; margin: 0 auto; padding: 20px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        td {{ border-bottom: 1px solid #eee; padding: 8px; }}
+        tr.awaiting td {{ background: #fff8e1; font-weight: bold; }}
+        tr.awaiting td:first-child {{ border-left: 4px solid #ff9800; }}
+        .preview {{ color: #666; font-size: 0.9em; margin-top: 4px; }}
+    </style>
+</head>
+<body>
+    <h1>📋 会话</h1>
+    <p>共 {} 个会话，{} 个等待回复</p>
+    <table>
+{}    </table>
+</body>
+</html>"#,
+        sessions.len(),
+        awaiting,
+        rows
+    ))
+}
+
+/// transcript 路径 → (修改时间, 首条提问预览)
+type PreviewCache = HashMap<PathBuf, (SystemTime, Option<String>)>;
+
+fn preview_cache() -> &'static Mutex<PreviewCache> {
+    static CACHE: OnceLock<Mutex<PreviewCache>> = OnceLock::new();
+    CACHE.get_or_init(Mutex::default)
+}
+
+/// 会话列表中各会话的首条提问预览，按 transcript 修改时间缓存，避免每次刷新都重新读取
+///
+/// 读取文件时不持有缓存锁；不在 `paths` 中的会话（已被淘汰或删除）从缓存中移除
+fn session_previews(cache: &Mutex<PreviewCache>, paths: &[PathBuf]) -> Vec<Option<String>> {
+    let previews = paths
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+            let cached = cache
+                .lock()
+                .ok()
+                .and_then(|cache| cache.get(path).cloned())
+                .filter(|(cached_at, _)| *cached_at == modified);
+            if let Some((_, preview)) = cached {
+                return preview;
+            }
+
+            let preview = transcript::read_first_user_preview(path, transcript::PREVIEW_MAX_CHARS);
+            if let Ok(mut cache) = cache.lock() {
+                cache.insert(path.clone(), (modified, preview.clone()));
+            }
+            preview
+        })
+        .collect();
+
+    if let Ok(mut cache) = cache.lock() {
+        let live: std::collections::HashSet<&PathBuf> = paths.iter().collect();
+        cache.retain(|path, _| live.contains(path));
+    }
+    previews
+}
+
+/// 回复历史路由处理
+async fn replies_handler(
+    Path(session_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    if let Some(rejection) = reject_invalid_session_id(&session_id, &state.config()) {
+        return rejection;
+    }
+    Json(state.history.load(&session_id)).into_response()
+}
+
+/// 事件流检查 transcript 是否更新的间隔
+const EVENTS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 事件流心跳间隔，避免代理因长时间无数据断开连接
+const EVENTS_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// 会话更新事件流（SSE）：transcript 文件变化时推送 `update` 事件
+///
+/// 供实时模式（`?live=1`）的页面订阅；客户端断开后写入失败，后台任务随之退出
+async fn events_handler(
+    Path(session_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    if let Some(rejection) = reject_invalid_session_id(&session_id, &state.config()) {
+        return rejection;
+    }
+    let Some(transcript_path) = resolve_transcript_path_blocking(&state.registry, &session_id)
+        .await
+        .map(|(path, _)| path)
+        .filter(|path| path.exists())
+    else {
+        return (StatusCode::NOT_FOUND, "Session 不存在").into_response();
+    };
+
+    let (mut writer, reader) = tokio::io::duplex(1024);
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+
+        let modified = |path: &PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last_modified = modified(&transcript_path);
+        let mut last_sent = Instant::now();
+
+        if writer.write_all(b": connected\n\n").await.is_err() {
+            return;
+        }
+
+        loop {
+            tokio::time::sleep(EVENTS_POLL_INTERVAL).await;
+
+            let current = modified(&transcript_path);
+            let event: &[u8] = if current != last_modified {
+                last_modified = current;
+                b"event: update\ndata: {}\n\n"
+            } else if last_sent.elapsed() >= EVENTS_KEEPALIVE {
+                b": keepalive\n\n"
+            } else {
+                continue;
+            };
+
+            if writer.write_all(event).await.is_err() {
+                break;
+            }
+            last_sent = Instant::now();
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from_stream(ReaderStream::new(reader)))
+        .unwrap()
+        .into_response()
+}
+
+/// 微信回调中的字符串字段（形如 `{"string": "..."}`）
+#[derive(Debug, Default, Deserialize)]
+struct WrappedString {
+    #[serde(default)]
+    string: String,
+}
+
+/// 微信消息回调数据
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CallbackData {
+    #[serde(default)]
+    from_user_name: WrappedString,
+    #[serde(default)]
+    msg_type: i64,
+    #[serde(default)]
+    content: WrappedString,
+}
+
+/// 微信消息回调
+#[derive(Debug, Deserialize)]
+struct ReplyCallback {
+    #[serde(rename = "TypeName")]
+    type_name: String,
+    #[serde(rename = "Data")]
+    data: Option<CallbackData>,
+}
+
+/// 文本消息类型
+const MSG_TYPE_TEXT: i64 = 1;
+
+/// 解析微信消息回调，仅对新的文本消息返回 `(发送者, 内容)`
+pub fn parse_text_message(body: &[u8]) -> Option<(String, String)> {
+    let callback: ReplyCallback = serde_json::from_slice(body).ok()?;
+    let data = callback.data?;
+    let is_text = callback.type_name == "AddMsg" && data.msg_type == MSG_TYPE_TEXT;
+    (is_text && !data.from_user_name.string.is_empty()).then_some((data.from_user_name.string, data.content.string))
+}
+
+/// 回复 webhook 请求体上限
+pub(crate) const MAX_REPLY_BODY_BYTES: usize = 64 * 1024;
+
+/// 单条回复文本上限（字符数）
+const MAX_REPLY_TEXT_CHARS: usize = 4096;
+
+/// 签名时间戳允许的最大偏差（秒），超出视为重放
+const MAX_SIGNATURE_SKEW_SECS: i64 = 300;
+
+/// 回复 webhook 签名参数（通过查询字符串传递）
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SignatureQuery {
+    signature: Option<String>,
+    timestamp: Option<String>,
+    nonce: Option<String>,
+}
+
+/// 已使用过的签名 nonce 及其时间戳
+///
+/// 签名不覆盖请求体，截获的回调 URL 在时间窗口内可被重放；
+/// 记录窗口内出现过的 nonce，重复出现时拒绝
+#[derive(Debug, Default)]
+pub(crate) struct NonceCache {
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl NonceCache {
+    /// 记录 nonce，窗口内已出现过时返回 false
+    ///
+    /// 只记录签名校验通过的请求，条目数受限于窗口内的合法回调数量；
+    /// 时间戳可能超前当前时间，因此保留两倍窗口后再清理
+    fn insert(&self, nonce: &str, now: i64) -> bool {
+        let Ok(mut seen) = self.seen.lock() else {
+            return false;
+        };
+        seen.retain(|_, ts| now - *ts <= 2 * MAX_SIGNATURE_SKEW_SECS);
+        if seen.contains_key(nonce) {
+            return false;
+        }
+        seen.insert(nonce.to_string(), now);
+        true
+    }
+}
+
+/// 校验回调签名
+///
+/// 签名算法与微信公众平台的服务器配置校验一致：将 token、timestamp、nonce 按字典序排序后拼接，
+/// 取 SHA1 的十六进制小写形式；时间戳与当前时间相差超过
+/// [`MAX_SIGNATURE_SKEW_SECS`] 时视为无效。gewe 的消息回调本身不携带签名，
+/// 这些参数需由部署在 gewe 与 serve 之间的转发层按此算法生成
+fn verify_signature(token: &str, query: &SignatureQuery, now: i64) -> bool {
+    let (Some(signature), Some(timestamp), Some(nonce)) =
+        (&query.signature, &query.timestamp, &query.nonce)
+    else {
+        return false;
+    };
+
+    let Ok(ts) = timestamp.parse::<i64>() else {
+        return false;
+    };
+    if (now - ts).abs() > MAX_SIGNATURE_SKEW_SECS {
+        return false;
+    }
+
+    let expected = compute_signature(token, timestamp, nonce);
+    constant_time_eq(expected.as_bytes(), signature.to_ascii_lowercase().as_bytes())
+}
+
+/// 计算回调签名
+pub(crate) fn compute_signature(token: &str, timestamp: &str, nonce: &str) -> String {
+    use sha1::{Digest, Sha1};
+
+    let mut parts = [token, timestamp, nonce];
+    parts.sort_unstable();
+    let digest = Sha1::digest(parts.concat().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 校验回调签名并记录 nonce，失败时返回原因（用于 401 响应）
+pub(crate) fn check_signature(
+    token: &str,
+    query: &SignatureQuery,
+    nonces: &NonceCache,
+    now: i64,
+) -> std::result::Result<(), &'static str> {
+    if !verify_signature(token, query, now) {
+        return Err("签名校验失败");
+    }
+    if !nonces.insert(query.nonce.as_deref().unwrap_or_default(), now) {
+        return Err("重复的回调请求");
+    }
+    Ok(())
+}
+
+/// 长度相同时逐字节比较全部内容，避免通过响应时间推测签名
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 回复 webhook：接收微信消息回调并匹配给等待中的会话
+///
+/// 配置了 `webhook_token` 时先校验签名，缺少、不匹配或 nonce 重复时返回 401；
+/// 请求体超过 [`MAX_REPLY_BODY_BYTES`] 时由 `DefaultBodyLimit` 返回 413，
+/// 不是合法的回调 JSON 时返回 400
+async fn reply_webhook_handler(
+    State(state): State<AppState>,
+    Query(query): Query<SignatureQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let token = state.config().notification.webhook_token;
+    if !token.is_empty()
+        && let Err(reason) = check_signature(&token, &query, &state.nonces, Utc::now().timestamp())
+    {
+        return (StatusCode::UNAUTHORIZED, reason);
+    }
+
+    let callback: ReplyCallback = match serde_json::from_slice(&body) {
+        Ok(callback) => callback,
+        Err(_) => return (StatusCode::BAD_REQUEST, "无效的回调数据"),
+    };
+
+    let Some(data) = callback.data else {
+        return (StatusCode::OK, "ignored");
+    };
+
+    // 只处理新的文本消息
+    if callback.type_name != "AddMsg" || data.msg_type != MSG_TYPE_TEXT {
+        return (StatusCode::OK, "ignored");
+    }
+
+    if data.from_user_name.string.is_empty() {
+        return (StatusCode::BAD_REQUEST, "缺少发送者");
+    }
+
+    deliver_reply(&state, &data.from_user_name.string, &data.content.string)
+}
+
+/// 将收到的回复交给等待中的会话
+fn deliver_reply(state: &AppState, from: &str, text: &str) -> (StatusCode, &'static str) {
+    if text.chars().count() > MAX_REPLY_TEXT_CHARS {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "回复内容过长");
+    }
+
+    match state.pending_replies.deliver(from, text.trim()) {
+        Ok(Some(session_id)) => {
+            println!("📩 收到回复，已匹配会话: {}", session_id);
+            (StatusCode::OK, "OK")
+        }
+        Ok(None) => (StatusCode::OK, "ignored"),
+        Err(e) => {
+            eprintln!("⚠️ 写入回复失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "写入回复失败")
+        }
+    }
+}
+
+/// Telegram Update（只解析回复匹配需要的字段）
+#[derive(Debug, Deserialize)]
+struct TelegramUpdate {
+    message: Option<TelegramMessage>,
+    callback_query: Option<TelegramCallbackQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramMessage {
+    chat: TelegramChat,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramChat {
+    id: i64,
+}
+
+/// 点击 inline keyboard 按钮产生的回调
+#[derive(Debug, Deserialize)]
+struct TelegramCallbackQuery {
+    id: String,
+    data: Option<String>,
+    message: Option<TelegramMessage>,
+}
+
+/// Telegram webhook 的 secret token 请求头（对应 setWebhook 的 secret_token 参数）
+const TELEGRAM_SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+
+/// 从 Telegram Update 中取出 `(chat_id, 回复文本)`
+///
+/// 快捷回复按钮的回调按 callback_data 还原为对应的回复，普通文本消息原样使用
+fn telegram_reply(update: &TelegramUpdate) -> Option<(String, String)> {
+    if let Some(query) = &update.callback_query {
+        let chat = query.message.as_ref()?.chat.id;
+        let reply = format::parse_quick_reply(query.data.as_deref()?)?;
+        return Some((chat.to_string(), reply.to_string()));
+    }
+
+    let message = update.message.as_ref()?;
+    (!message.text.trim().is_empty()).then(|| (message.chat.id.to_string(), message.text.clone()))
+}
+
+/// Telegram 回复 webhook：接收 Bot 的 Update 并匹配给等待中的会话
+///
+/// 配置了 `webhook_token` 时要求请求头 `X-Telegram-Bot-Api-Secret-Token` 与之一致，
+/// 否则返回 401。快捷回复按钮的回调会额外调用 answerCallbackQuery 结束按钮的加载状态
+async fn telegram_webhook_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let config = state.config();
+    let token = &config.notification.webhook_token;
+    if !token.is_empty() {
+        let provided = headers
+            .get(TELEGRAM_SECRET_HEADER)
+            .map(|value| value.as_bytes())
+            .unwrap_or_default();
+        if !constant_time_eq(token.as_bytes(), provided) {
+            return (StatusCode::UNAUTHORIZED, "签名校验失败");
+        }
+    }
+
+    let update: TelegramUpdate = match serde_json::from_slice(&body) {
+        Ok(update) => update,
+        Err(_) => return (StatusCode::BAD_REQUEST, "无效的回调数据"),
+    };
+
+    if let Some(query) = &update.callback_query {
+        answer_callback_query(&config, &query.id);
+    }
+
+    let Some((chat_id, text)) = telegram_reply(&update) else {
+        return (StatusCode::OK, "ignored");
+    };
+
+    deliver_reply(&state, &chat_id, &text)
+}
+
+/// 后台调用 answerCallbackQuery，失败只打印警告
+fn answer_callback_query(config: &Config, callback_query_id: &str) {
+    let bot_token = &config.notification.telegram_bot_token;
+    if bot_token.is_empty() {
+        return;
+    }
+
+    let url = format!("https://api.telegram.org/bot{}/answerCallbackQuery", bot_token);
+    let body = serde_json::json!({ "callback_query_id": callback_query_id });
+    let timeout = Duration::from_secs(config.notification.http_timeout_secs);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = http::post_json(&url, &body, timeout) {
+            eprintln!("⚠️ 应答 Telegram 回调失败: {}", e);
+        }
+    });
+}
+
+/// 指标接口（Prometheus 文本格式）
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        state.metrics.render(state.registry.session_count()),
+    )
+}
+
 /// 健康检查
 async fn health_handler() -> impl IntoResponse {
     (StatusCode::OK, "OK")
@@ -201,7 +1283,10 @@ async fn health_handler() -> impl IntoResponse {
 
 /// 静态文件服务
 async fn static_handler(Path(path): Path<String>) -> impl IntoResponse {
-    let assets_dir = get_assets_dir();
+    let assets_dir = match get_assets_dir() {
+        Ok(dir) => dir,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
 
     // 确保资源目录存在
     if let Err(e) = fs::create_dir_all(&assets_dir) {
@@ -250,6 +1335,8 @@ async fn static_handler(Path(path): Path<String>) -> impl IntoResponse {
         Some("svg") => "image/svg+xml",
         Some("ico") => "image/x-icon",
         Some("webp") => "image/webp",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
         _ => "application/octet-stream",
     };
 
@@ -266,41 +1353,667 @@ async fn static_handler(Path(path): Path<String>) -> impl IntoResponse {
 }
 
 /// 获取资源目录路径
-fn get_assets_dir() -> PathBuf {
-    dirs::home_dir()
-        .unwrap()
-        .join(".gewe-cc")
-        .join("assets")
+pub fn get_assets_dir() -> Result<PathBuf> {
+    Ok(config::config_dir_path()?.join("assets"))
 }
 
 fn is_safe_relative_path(path: &StdPath) -> bool {
     path.components().all(|component| matches!(component, Component::Normal(_)))
 }
 
+/// transcript 索引的有效期，过期后下次查找时重建
+const TRANSCRIPT_INDEX_TTL: Duration = Duration::from_secs(60);
+
+/// session_id → transcript 路径的索引，避免每次查找都遍历所有项目目录
+#[derive(Debug, Default)]
+struct TranscriptIndex {
+    built_at: Option<Instant>,
+    /// 构建索引时 projects 目录的修改时间，新增项目目录后索引失效
+    projects_mtime: Option<SystemTime>,
+    /// 构建索引时的项目目录，未命中时在这些目录中直接查找
+    projects: Vec<PathBuf>,
+    paths: HashMap<String, PathBuf>,
+}
+
+impl TranscriptIndex {
+    fn is_fresh(&self, projects_mtime: Option<SystemTime>) -> bool {
+        self.built_at.is_some_and(|built| built.elapsed() < TRANSCRIPT_INDEX_TTL)
+            && self.projects_mtime == projects_mtime
+    }
+
+    /// 完整扫描 projects 目录重建索引
+    fn rebuild(&mut self, projects_dir: &StdPath, projects_mtime: Option<SystemTime>) {
+        self.paths.clear();
+        self.projects.clear();
+        self.built_at = Some(Instant::now());
+        self.projects_mtime = projects_mtime;
+
+        let Ok(projects) = fs::read_dir(projects_dir) else {
+            return;
+        };
+
+        self.projects = projects.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect();
+        for project in &self.projects {
+            let Ok(files) = fs::read_dir(project) else {
+                continue;
+            };
+
+            for path in files.flatten().map(|entry| entry.path()) {
+                let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+
+                // 同时存在时优先使用未压缩的 .jsonl
+                if let Some(session_id) = file_name.strip_suffix(".jsonl") {
+                    self.paths.insert(session_id.to_string(), path);
+                } else if let Some(session_id) = file_name.strip_suffix(".jsonl.gz") {
+                    self.paths.entry(session_id.to_string()).or_insert(path);
+                }
+            }
+        }
+    }
+
+    /// 查找会话：索引过期或 projects 目录变化时重建，未命中时只在各项目目录中直接查找该会话的文件
+    ///
+    /// 未命中不会触发完整扫描，避免请求不存在的会话 ID 时反复遍历所有 transcript
+    fn lookup(&mut self, projects_dir: &StdPath, session_id: &str) -> Option<PathBuf> {
+        let projects_mtime = fs::metadata(projects_dir).and_then(|m| m.modified()).ok();
+        if !self.is_fresh(projects_mtime) {
+            self.rebuild(projects_dir, projects_mtime);
+        }
+
+        if let Some(path) = self.paths.get(session_id).filter(|path| path.exists()) {
+            return Some(path.clone());
+        }
+
+        match self.probe(session_id) {
+            Some(path) => {
+                self.paths.insert(session_id.to_string(), path.clone());
+                Some(path)
+            }
+            None => {
+                self.paths.remove(session_id);
+                None
+            }
+        }
+    }
+
+    /// 在已知的项目目录中查找 `<session_id>.jsonl`（其次 `.jsonl.gz`）
+    fn probe(&self, session_id: &str) -> Option<PathBuf> {
+        if !is_valid_session_id(session_id, false) {
+            return None;
+        }
+
+        ["jsonl", "jsonl.gz"].iter().find_map(|extension| {
+            self.projects
+                .iter()
+                .map(|project| project.join(format!("{}.{}", session_id, extension)))
+                .find(|path| path.is_file())
+        })
+    }
+}
+
+fn transcript_index() -> &'static Mutex<TranscriptIndex> {
+    static INDEX: OnceLock<Mutex<TranscriptIndex>> = OnceLock::new();
+    INDEX.get_or_init(Mutex::default)
+}
+
+/// transcript 路径的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptSource {
+    /// 来自会话注册表（sessions.json）
+    Registry,
+    /// 按 Claude Code 默认目录推导
+    Inferred,
+}
+
+/// 解析会话的 transcript 路径：优先使用注册表，其次按 Claude Code 默认路径推导
+///
+/// 注册表中的路径原样返回（文件可能已被删除），推导出的路径一定存在
+pub fn resolve_transcript_path(registry: Option<&SessionRegistry>, session_id: &str) -> Option<(PathBuf, TranscriptSource)> {
+    registry
+        .and_then(|registry| registry.get(session_id))
+        .map(|path| (path, TranscriptSource::Registry))
+        .or_else(|| infer_transcript_path(session_id).map(|path| (path, TranscriptSource::Inferred)))
+}
+
+/// 在阻塞线程池中执行 [`resolve_transcript_path`]，供异步处理函数使用
+async fn resolve_transcript_path_blocking(registry: &SessionRegistry, session_id: &str) -> Option<(PathBuf, TranscriptSource)> {
+    let registry = registry.clone();
+    let session_id = session_id.to_string();
+    tokio::task::spawn_blocking(move || resolve_transcript_path(Some(&registry), &session_id))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// 查找会话已存在的 transcript 文件，找不到时返回错误
+pub fn find_transcript(session_id: &str) -> Result<PathBuf> {
+    resolve_transcript_path(SessionRegistry::new().ok().as_ref(), session_id)
+        .map(|(path, _)| path)
+        .filter(|path| path.exists())
+        .ok_or_else(|| anyhow::anyhow!("未找到会话的 transcript: {}", session_id))
+}
+
+/// Claude Code 存放各项目 transcript 的目录
+pub fn claude_projects_dir() -> Result<PathBuf> {
+    Ok(config::home_dir()?.join(".claude/projects"))
+}
+
 /// 从 session_id 推导 transcript 路径
 ///
 /// Claude Code 的 transcript 路径通常在：
 /// ~/.claude/projects/{project_hash}/{session_id}.jsonl
-fn infer_transcript_path(session_id: &str) -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    let projects_dir = home.join(".claude/projects");
+///
+/// 也会查找 gzip 压缩归档的 `{session_id}.jsonl.gz`。
+/// 查找结果来自按需构建的索引（见 [`TranscriptIndex::lookup`]）
+pub fn infer_transcript_path(session_id: &str) -> Option<PathBuf> {
+    let projects_dir = claude_projects_dir().ok()?;
 
     if !projects_dir.exists() {
         return None;
     }
 
-    // 遍历所有项目目录，查找匹配的 session_id.jsonl
-    for entry in fs::read_dir(&projects_dir).ok()? {
-        let entry = entry.ok()?;
-        let path = entry.path();
+    let mut index = transcript_index().lock().ok()?;
+    index.lookup(&projects_dir, session_id)
+}
 
-        if path.is_dir() {
-            let transcript_file = path.join(format!("{}.jsonl", session_id));
-            if transcript_file.exists() {
-                return Some(transcript_file);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn test_state(dir: &StdPath) -> AppState {
+        AppState {
+            registry: SessionRegistry::with_dir(dir.to_path_buf(), Config::default().remote.max_sessions).unwrap(),
+            pending_replies: PendingReplyRegistry::with_dir(dir.join("pending_replies")),
+            history: ReplyHistory::with_dir(dir.join("replies")),
+            config: Arc::new(RwLock::new(Config::default())),
+            metrics: Arc::new(Metrics::default()),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            nonces: Arc::new(NonceCache::default()),
+        }
+    }
+
+    #[test]
+    fn test_register_skips_identical_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_state(dir.path()).registry;
+        let path = dir.path().join("a.jsonl");
+
+        assert!(registry.register("s1".to_string(), path.clone()).unwrap());
+
+        // 第二次相同注册不应重写文件
+        fs::write(&registry.sessions_file, "sentinel").unwrap();
+        assert!(!registry.register("s1".to_string(), path).unwrap());
+        assert_eq!(fs::read_to_string(&registry.sessions_file).unwrap(), "sentinel");
+
+        // 路径变化时重新写入
+        assert!(registry.register("s1".to_string(), dir.path().join("b.jsonl")).unwrap());
+        let saved: HashMap<String, SessionEntry> =
+            serde_json::from_str(&fs::read_to_string(&registry.sessions_file).unwrap()).unwrap();
+        assert_eq!(saved["s1"].transcript_path, dir.path().join("b.jsonl"));
+
+        // 临时文件已重命名，不会残留在目录中
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name != "sessions.json")
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_register_evicts_oldest_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = test_state(dir.path()).registry;
+        registry.max_sessions = 2;
+
+        // 旧格式的记录视为最早注册
+        fs::write(&registry.sessions_file, r#"{"legacy": "/tmp/legacy.jsonl"}"#).unwrap();
+        assert_eq!(registry.get("legacy"), Some(PathBuf::from("/tmp/legacy.jsonl")));
+
+        registry.register("s1".to_string(), dir.path().join("1.jsonl")).unwrap();
+        registry.register("s2".to_string(), dir.path().join("2.jsonl")).unwrap();
+        let ids: Vec<_> = registry.list().into_iter().map(|s| s.session_id).collect();
+        assert_eq!(ids, ["s1", "s2"]);
+
+        registry.register("s3".to_string(), dir.path().join("3.jsonl")).unwrap();
+        let ids: Vec<_> = registry.list().into_iter().map(|s| s.session_id).collect();
+        assert_eq!(ids, ["s2", "s3"]);
+        // 淘汰的是最早注册的 s1
+        assert!(registry.get("s1").is_none());
+        assert!(registry.get("s2").is_some());
+    }
+
+    #[test]
+    fn test_set_awaiting_rejects_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_state(dir.path()).registry;
+
+        assert!(registry.set_awaiting("../../x", true).is_err());
+        assert!(registry.set_awaiting("a/b", true).is_err());
+        assert!(!dir.path().join("x").exists());
+        assert_eq!(registry.awaiting_since("../sessions.json"), None);
+
+        registry.set_awaiting("s1", true).unwrap();
+        assert!(registry.awaiting_since("s1").is_some());
+    }
+
+    #[test]
+    fn test_resolve_transcript_path_prefers_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_state(dir.path()).registry;
+        let path = dir.path().join("gone.jsonl");
+        registry.register("s1".to_string(), path.clone()).unwrap();
+
+        // 注册表中的路径即使已不存在也原样返回，便于排查
+        assert_eq!(
+            resolve_transcript_path(Some(&registry), "s1"),
+            Some((path, TranscriptSource::Registry))
+        );
+        assert_eq!(resolve_transcript_path(Some(&registry), "no-such-session-3f9a"), None);
+    }
+
+    #[test]
+    fn test_transcript_index_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-home-me-demo");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("s1.jsonl"), "").unwrap();
+        fs::write(project.join("s2.jsonl.gz"), "").unwrap();
+        fs::write(project.join("s2.jsonl"), "").unwrap();
+
+        let mut index = TranscriptIndex::default();
+        assert_eq!(index.lookup(dir.path(), "s1"), Some(project.join("s1.jsonl")));
+        assert_eq!(index.lookup(dir.path(), "s2"), Some(project.join("s2.jsonl")));
+        assert_eq!(index.lookup(dir.path(), "missing"), None);
+
+        // 命中缓存时不重建索引
+        let built_at = index.built_at;
+        assert!(index.lookup(dir.path(), "s1").is_some());
+        assert_eq!(index.built_at, built_at);
+
+        // 未命中时直接查找文件，不重建索引也能找到新出现的会话
+        fs::write(project.join("s3.jsonl"), "").unwrap();
+        fs::write(project.join("s4.jsonl.gz"), "").unwrap();
+        assert_eq!(index.lookup(dir.path(), "s3"), Some(project.join("s3.jsonl")));
+        assert_eq!(index.lookup(dir.path(), "s4"), Some(project.join("s4.jsonl.gz")));
+        assert_eq!(index.lookup(dir.path(), "../s3"), None);
+        assert_eq!(index.built_at, built_at);
+
+        // 文件被删除后不再返回失效路径
+        fs::remove_file(project.join("s1.jsonl")).unwrap();
+        assert_eq!(index.lookup(dir.path(), "s1"), None);
+        assert_eq!(index.built_at, built_at);
+
+        // 索引过期后重建
+        index.built_at = Instant::now().checked_sub(TRANSCRIPT_INDEX_TTL);
+        assert_eq!(index.lookup(dir.path(), "s2"), Some(project.join("s2.jsonl")));
+        assert_ne!(index.built_at, built_at);
+    }
+
+    #[test]
+    fn test_session_previews_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.jsonl");
+        let b = dir.path().join("b.jsonl");
+        fs::write(&a, r#"{"type":"user","message":{"role":"user","content":"修复登录"}}"#).unwrap();
+        fs::write(&b, r#"{"type":"user","message":{"role":"user","content":"补测试"}}"#).unwrap();
+
+        let cache = Mutex::default();
+        let previews = session_previews(&cache, &[a.clone(), b.clone(), dir.path().join("missing.jsonl")]);
+        assert_eq!(previews, [Some("修复登录".to_string()), Some("补测试".to_string()), None]);
+        assert_eq!(cache.lock().unwrap().len(), 2);
+
+        // 会话不在列表中后从缓存移除
+        assert_eq!(session_previews(&cache, std::slice::from_ref(&b)), [Some("补测试".to_string())]);
+        let cached: Vec<PathBuf> = cache.lock().unwrap().keys().cloned().collect();
+        assert_eq!(cached, [b]);
+    }
+
+    #[test]
+    fn test_is_valid_session_id() {
+        assert!(is_valid_session_id("75f2b194-4b91-4c19-8e97-4ff5fe562ece", true));
+        assert!(!is_valid_session_id("my_session", true));
+        assert!(is_valid_session_id("my_session", false));
+
+        for malicious in ["", "..", "../../etc/passwd", "a/b", "a\\b", "abc.jsonl", "%2e%2e", "abc\0", &"a".repeat(129)] {
+            assert!(!is_valid_session_id(malicious, true), "{:?}", malicious);
+            assert!(!is_valid_session_id(malicious, false), "{:?}", malicious);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_session_id_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+
+        for uri in [
+            "/..%2F..%2Fetc%2Fpasswd",
+            "/..%2F..%2Fetc%2Fpasswd/replies",
+            "/..%2F..%2Fetc%2Fpasswd/events",
+            "/abc.jsonl",
+            "/%3Cscript%3E",
+        ] {
+            let response = build_router(state.clone(), false, false)
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
         }
     }
 
-    None
+    #[tokio::test]
+    async fn test_events_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        let transcript = dir.path().join("5e551.jsonl");
+        fs::write(&transcript, "").unwrap();
+        state.registry.register("5e551".to_string(), transcript).unwrap();
+
+        let response = build_router(state.clone(), false, false)
+            .oneshot(Request::get("/5e551/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+        let response = build_router(state, false, false)
+            .oneshot(Request::get("/0000dead/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_index_highlights_awaiting_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        fs::write(
+            dir.path().join("a.jsonl"),
+            r#"{"type":"user","message":{"role":"user","content":"修复 <登录> 页"}}"#,
+        )
+        .unwrap();
+        state.registry.register("idle".to_string(), dir.path().join("a.jsonl")).unwrap();
+        state.registry.register("busy".to_string(), dir.path().join("b.jsonl")).unwrap();
+        state.registry.set_awaiting("busy", true).unwrap();
+
+        // 标记已持久化，新的注册表实例同样可见
+        let reloaded = SessionRegistry::with_dir(dir.path().to_path_buf(), 0).unwrap();
+        let list = reloaded.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].session_id, "busy");
+        assert!(list[0].awaiting_since.is_some());
+        assert!(list[1].awaiting_since.is_none());
+
+        // 未开启 --index 时不提供索引页
+        let response = build_router(state.clone(), false, false)
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = build_router(state.clone(), false, true)
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"<tr class="awaiting"><td><a href="/busy">"#));
+        assert!(html.contains(r#"<tr><td><a href="/idle">"#));
+        // 首条提问作为预览，转义后展示；transcript 不存在时不显示
+        assert!(html.contains(r#"<div class="preview">修复 &lt;登录&gt; 页</div>"#));
+        assert!(html.contains(r#"<code>busy</code></a></td>"#));
+
+        state.registry.set_awaiting("busy", false).unwrap();
+        assert!(reloaded.list().iter().all(|s| s.awaiting_since.is_none()));
+    }
+
+    async fn post_reply(app: Router, body: Vec<u8>) -> StatusCode {
+        let request = Request::post("/webhook/reply")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_reply_webhook_rejects_oversized_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = build_router(test_state(dir.path()), false, false);
+
+        let body = format!(
+            r#"{{"TypeName":"AddMsg","Data":{{"Content":{{"string":"{}"}}}}}}"#,
+            "a".repeat(MAX_REPLY_BODY_BYTES)
+        );
+        assert_eq!(post_reply(app, body.into_bytes()).await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_reply_webhook_rejects_malformed_body() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let app = build_router(test_state(dir.path()), false, false);
+        assert_eq!(post_reply(app, b"{not json".to_vec()).await, StatusCode::BAD_REQUEST);
+
+        // 缺少 TypeName
+        let app = build_router(test_state(dir.path()), false, false);
+        assert_eq!(post_reply(app, br#"{"Data":{}}"#.to_vec()).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_reply_webhook_delivers_reply() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        state.pending_replies.create("s1", "wxid_user").unwrap();
+
+        let app = build_router(state.clone(), false, false);
+        let body = r#"{"TypeName":"AddMsg","Data":{"FromUserName":{"string":"wxid_user"},"MsgType":1,"Content":{"string":" 继续 "}}}"#;
+        assert_eq!(post_reply(app, body.as_bytes().to_vec()).await, StatusCode::OK);
+        assert_eq!(state.pending_replies.take_reply("s1"), Some("继续".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_telegram_webhook_delivers_quick_reply() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        state.config.write().unwrap().notification.webhook_token = "secret".to_string();
+        state.pending_replies.create("s1", "10086").unwrap();
+
+        let post = |body: &'static str, secret: &'static str| {
+            let app = build_router(state.clone(), false, false);
+            let request = Request::post("/webhook/telegram")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(TELEGRAM_SECRET_HEADER, secret)
+                .body(Body::from(body))
+                .unwrap();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        let callback = r#"{"callback_query":{"id":"q1","data":"reply:停止","message":{"chat":{"id":10086}}}}"#;
+        assert_eq!(post(callback, "wrong").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(post(callback, "secret").await, StatusCode::OK);
+        assert_eq!(state.pending_replies.take_reply("s1"), Some("停止".to_string()));
+
+        state.pending_replies.create("s1", "10086").unwrap();
+        let message = r#"{"message":{"chat":{"id":10086},"text":" 继续 "}}"#;
+        assert_eq!(post(message, "secret").await, StatusCode::OK);
+        assert_eq!(state.pending_replies.take_reply("s1"), Some("继续".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_session_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        state.config.write().unwrap().transcript.rate_limit_per_minute = 2;
+
+        // 经本机代理（如 frpc）转发，客户端 IP 取自 X-Forwarded-For
+        let get = |uri: &str, ip: &str| {
+            let app = build_router(state.clone(), false, false);
+            let mut request = Request::get(uri)
+                .header("x-forwarded-for", ip)
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 50000))));
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(get("/5e551/replies", "203.0.113.5").await, StatusCode::OK);
+        assert_eq!(get("/5e551/replies", "203.0.113.5").await, StatusCode::OK);
+        assert_eq!(get("/5e551/replies", "203.0.113.5").await, StatusCode::TOO_MANY_REQUESTS);
+
+        // 其他 IP 与 /health 不受影响
+        assert_eq!(get("/5e551/replies", "198.51.100.7").await, StatusCode::OK);
+        assert_eq!(get("/health", "203.0.113.5").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_load_tls_config_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("cert.pem");
+        let key = dir.path().join("key.pem");
+
+        let err = load_tls_config(&cert, &key).await.unwrap_err();
+        assert!(err.to_string().contains("TLS 证书文件不存在"));
+
+        fs::write(&cert, "not a certificate").unwrap();
+        fs::write(&key, "not a key").unwrap();
+        let err = load_tls_config(&cert, &key).await.unwrap_err();
+        assert!(err.to_string().contains("加载 TLS 证书失败"));
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let query = |signature: &str, timestamp: &str| SignatureQuery {
+            signature: Some(signature.to_string()),
+            timestamp: Some(timestamp.to_string()),
+            nonce: Some("n0nce".to_string()),
+        };
+        let now = 1_700_000_000;
+        let signature = compute_signature("secret", "1700000000", "n0nce");
+
+        assert!(verify_signature("secret", &query(&signature, "1700000000"), now));
+        assert!(verify_signature("secret", &query(&signature.to_uppercase(), "1700000000"), now));
+
+        // 篡改签名或 token 不一致
+        let mut tampered = signature.clone();
+        tampered.replace_range(0..1, if signature.starts_with('0') { "1" } else { "0" });
+        assert!(!verify_signature("secret", &query(&tampered, "1700000000"), now));
+        assert!(!verify_signature("other", &query(&signature, "1700000000"), now));
+
+        // 时间戳过期
+        assert!(!verify_signature("secret", &query(&signature, "1700000000"), now + 301));
+
+        // 缺少签名参数
+        assert!(!verify_signature("secret", &SignatureQuery::default(), now));
+    }
+
+    #[tokio::test]
+    async fn test_reply_webhook_verifies_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        state.config.write().unwrap().notification.webhook_token = "secret".to_string();
+        state.pending_replies.create("s1", "wxid_user").unwrap();
+
+        let body = r#"{"TypeName":"AddMsg","Data":{"FromUserName":{"string":"wxid_user"},"MsgType":1,"Content":{"string":"继续"}}}"#;
+        let timestamp = Utc::now().timestamp().to_string();
+        let signature = compute_signature("secret", &timestamp, "abc");
+        let post = |uri: String| {
+            let app = build_router(state.clone(), false, false);
+            async move {
+                let request = Request::post(uri)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap();
+                app.oneshot(request).await.unwrap().status()
+            }
+        };
+
+        // 未签名
+        assert_eq!(post("/webhook/reply".to_string()).await, StatusCode::UNAUTHORIZED);
+
+        // 签名被篡改（nonce 与签名不匹配）
+        let uri = format!("/webhook/reply?signature={}&timestamp={}&nonce=xyz", signature, timestamp);
+        assert_eq!(post(uri).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(state.pending_replies.take_reply("s1"), None);
+
+        let uri = format!("/webhook/reply?signature={}&timestamp={}&nonce=abc", signature, timestamp);
+        assert_eq!(post(uri.clone()).await, StatusCode::OK);
+        assert_eq!(state.pending_replies.take_reply("s1"), Some("继续".to_string()));
+
+        // 重放同一个签名 URL
+        state.pending_replies.create("s1", "wxid_user").unwrap();
+        assert_eq!(post(uri).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(state.pending_replies.take_reply("s1"), None);
+    }
+
+    #[test]
+    fn test_nonce_cache() {
+        let cache = NonceCache::default();
+        let now = 1_700_000_000;
+        assert!(cache.insert("a", now));
+        assert!(!cache.insert("a", now + 1));
+        assert!(cache.insert("b", now + 1));
+
+        // 超出两倍窗口后清理，不再视为重复
+        assert!(cache.insert("a", now + 2 * MAX_SIGNATURE_SKEW_SECS + 2));
+        assert_eq!(cache.seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_has_display() {
+        assert!(has_display(true, true));
+        assert!(!has_display(true, false));
+        assert!(has_display(false, false));
+    }
+
+    #[test]
+    fn test_metrics_render() {
+        let metrics = Metrics::default();
+        Metrics::inc(&metrics.transcript_requests);
+        Metrics::inc(&metrics.transcript_requests);
+        Metrics::inc(&metrics.transcript_parse_errors);
+
+        let text = metrics.render(7);
+        assert!(text.contains("# TYPE gewe_cc_transcript_requests_total counter"));
+        assert!(text.contains("gewe_cc_transcript_requests_total 2\n"));
+        assert!(text.contains("gewe_cc_transcript_not_found_total 0\n"));
+        assert!(text.contains("gewe_cc_transcript_parse_errors_total 1\n"));
+        assert!(text.contains("gewe_cc_registry_sessions 7\n"));
+    }
+
+    #[test]
+    fn test_pending_reply_delivered_to_oldest_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let pending = PendingReplyRegistry::with_dir(dir.path().to_path_buf());
+
+        pending.create("session-a", "wxid_user").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        pending.create("session-b", "wxid_user").unwrap();
+        pending.create("session-c", "wxid_other").unwrap();
+
+        // 来自其他人的消息不会匹配
+        assert_eq!(pending.deliver("wxid_nobody", "hi").unwrap(), None);
+
+        assert_eq!(
+            pending.deliver("wxid_user", "继续").unwrap(),
+            Some("session-a".to_string())
+        );
+        assert_eq!(
+            pending.deliver("wxid_user", "停止").unwrap(),
+            Some("session-b".to_string())
+        );
+
+        assert_eq!(pending.take_reply("session-a"), Some("继续".to_string()));
+        assert_eq!(pending.take_reply("session-b"), Some("停止".to_string()));
+        assert_eq!(pending.take_reply("session-c"), None);
+
+        // 取出后登记被移除
+        assert_eq!(pending.take_reply("session-a"), None);
+    }
 }
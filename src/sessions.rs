@@ -0,0 +1,129 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::history::ReplyHistory;
+use crate::output::{self, banner};
+use crate::server::{self, SessionRegistry, TranscriptSource};
+
+/// 列出已注册的会话，高亮等待回复的会话
+pub fn handle_list() -> Result<()> {
+    let sessions = SessionRegistry::new()?.list();
+
+    banner("  📋 会话列表", Color::Cyan);
+
+    if sessions.is_empty() {
+        println!("  {}", "暂无会话".dimmed());
+        println!();
+        return Ok(());
+    }
+
+    for session in &sessions {
+        match session.awaiting_since {
+            Some(since) => println!(
+                "  {} {}",
+                session.session_id.yellow().bold(),
+                format!("⏳ 等待回复（{} 起）", output::format_time(&since)).yellow()
+            ),
+            None => println!("  {}", session.session_id),
+        }
+        if !output::is_quiet() {
+            println!("    {}", session.transcript_path.display().to_string().dimmed());
+        }
+    }
+
+    if !output::is_quiet() {
+        let awaiting = sessions.iter().filter(|s| s.awaiting_since.is_some()).count();
+        println!();
+        println!("  共 {} 个会话，{} 个等待回复", sessions.len(), awaiting);
+    }
+    println!();
+
+    Ok(())
+}
+
+/// 查看会话的回复历史
+pub fn handle_replies(session_id: &str) -> Result<()> {
+    let records = ReplyHistory::new()?.load(session_id);
+
+    if !output::is_quiet() {
+        banner("  💬 回复历史", Color::Cyan);
+        println!("  会话 ID: {}", session_id.dimmed());
+        println!();
+    }
+
+    if records.is_empty() {
+        println!("  {}", "暂无回复记录".dimmed());
+        println!();
+        return Ok(());
+    }
+
+    for record in records {
+        let time = output::format_time(&record.timestamp);
+        println!("  {} {}", time.dimmed(), record.text);
+    }
+    println!();
+
+    Ok(())
+}
+
+/// 输出会话最近一条回复，供远程控制 skill 在中断后恢复最新指令
+///
+/// 回复内容输出到 stdout，收到时间输出到 stderr（`--quiet` 时省略）
+pub fn handle_last_reply(session_id: &str) -> Result<()> {
+    let Some(record) = ReplyHistory::new()?.last(session_id) else {
+        anyhow::bail!("会话暂无回复记录: {}", session_id);
+    };
+
+    if !output::is_quiet() {
+        eprintln!("{}", format!("收到于 {}", output::format_time(&record.timestamp)).dimmed());
+    }
+    println!("{}", record.text);
+
+    Ok(())
+}
+
+/// 输出会话解析到的 transcript 路径及其是否存在，返回是否找到可用的文件
+///
+/// 与 transcript 页面使用同一套解析逻辑，用于排查页面 404 的原因
+pub fn handle_resolve(session_id: &str) -> Result<bool> {
+    let registry = SessionRegistry::new().ok();
+    let resolved = server::resolve_transcript_path(registry.as_ref(), session_id);
+
+    banner("  🔎 Transcript 路径", Color::Cyan);
+    println!("  {} {}", "会话 ID:".dimmed(), session_id);
+
+    let Some((path, source)) = resolved else {
+        println!("  {}", "❌ 未找到会话的 transcript".red());
+        println!();
+        println!("  已查找：");
+        if let Some(registry) = &registry {
+            println!("    {} {}", "注册表:".dimmed(), registry.sessions_file().display());
+        }
+        if let Ok(projects_dir) = server::claude_projects_dir() {
+            println!(
+                "    {} {}",
+                "项目目录:".dimmed(),
+                projects_dir.join("*").join(format!("{}.jsonl(.gz)", session_id)).display()
+            );
+        }
+        println!();
+        return Ok(false);
+    };
+
+    let source = match source {
+        TranscriptSource::Registry => "注册表",
+        TranscriptSource::Inferred => "按默认目录推导",
+    };
+    let exists = path.exists();
+
+    println!("  {} {}", "来源:".dimmed(), source);
+    println!("  {} {}", "路径:".dimmed(), path.display());
+    if exists {
+        println!("  {} {}", "状态:".dimmed(), "✅ 文件存在".green());
+    } else {
+        println!("  {} {}", "状态:".dimmed(), "❌ 文件不存在（注册表中的路径已失效）".red());
+    }
+    println!();
+
+    Ok(exists)
+}
@@ -1,8 +1,12 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
+use crate::config::TranscriptConfig;
+
 /// Transcript 条目（顶层）
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TranscriptEntry {
@@ -12,6 +16,36 @@ pub struct TranscriptEntry {
     pub message: Option<Message>,
     #[serde(default)]
     pub content: Option<serde_json::Value>,
+    /// 摘要条目（type 为 summary）的内容
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// 是否为子代理（Task 工具）产生的条目
+    #[serde(default, rename = "isSidechain")]
+    pub is_sidechain: bool,
+}
+
+impl TranscriptEntry {
+    /// 将非消息条目转换为伪消息，role 为条目类型
+    ///
+    /// 优先使用 `content` 字段（字符串直接使用，其他结构格式化为 JSON），
+    /// 其次使用 `summary` 字段；均为空时返回 None
+    fn into_pseudo_message(self) -> Option<Message> {
+        let text = match self.content {
+            Some(serde_json::Value::String(text)) => text,
+            Some(serde_json::Value::Null) | None => self.summary?,
+            Some(value) => serde_json::to_string_pretty(&value).unwrap_or_default(),
+        };
+
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        Some(Message {
+            role: self.entry_type,
+            content: MessageContent::String(text),
+            sidechain: self.is_sidechain,
+        })
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -19,6 +53,9 @@ pub struct Message {
     pub role: String,
     #[serde(default)]
     pub content: MessageContent,
+    /// 是否为子代理（Task 工具）产生的消息，取自条目的 `isSidechain`
+    #[serde(skip)]
+    pub sidechain: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -34,8 +71,12 @@ impl Default for MessageContent {
     }
 }
 
+/// 消息内容块
+///
+/// 按 `type` 分派（见 [`ContentBlock`] 的 `Deserialize` 实现）：已知类型字段缺失或类型不符时报错，
+/// 未知类型或没有 `type` 的内容块保留为 [`ContentBlock::Other`]
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(tag = "type")]
+#[serde(tag = "type", remote = "Self")]
 pub enum ContentBlock {
     #[serde(rename = "text")]
     Text { text: String },
@@ -52,9 +93,64 @@ pub enum ContentBlock {
         tool_use_id: String,
         #[serde(default)]
         content: ToolResultContent,
+        /// 工具执行失败（如命令退出码非 0）时为 true
+        #[serde(default)]
+        is_error: Option<bool>,
     },
-    #[serde(other)]
-    Other,
+    /// 未识别的内容块，保留原始 JSON（开启 show_system 时展示）
+    #[serde(untagged, skip_deserializing)]
+    Other(serde_json::Value),
+}
+
+/// 已知内容块的 `type` 取值
+const KNOWN_BLOCK_TYPES: &[&str] = &["text", "thinking", "tool_use", "tool_result"];
+
+impl<'de> Deserialize<'de> for ContentBlock {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let known = value
+            .get("type")
+            .and_then(|t| t.as_str())
+            .is_some_and(|t| KNOWN_BLOCK_TYPES.contains(&t));
+        if !known {
+            return Ok(ContentBlock::Other(value));
+        }
+        ContentBlock::deserialize(value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for ContentBlock {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        ContentBlock::serialize(self, serializer)
+    }
+}
+
+/// tool_use 内容块的借用视图
+#[derive(Debug, Clone, Copy)]
+pub struct ToolUse<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+    pub input: &'a serde_json::Value,
+}
+
+impl ContentBlock {
+    /// tool_use 内容块的 id、工具名与输入参数，其他内容块返回 None
+    pub fn as_tool_use(&self) -> Option<ToolUse<'_>> {
+        match self {
+            ContentBlock::ToolUse { id, name, input } => Some(ToolUse { id, name, input }),
+            _ => None,
+        }
+    }
+
+    /// tool_use 内容块的工具名
+    pub fn tool_name(&self) -> Option<&str> {
+        self.as_tool_use().map(|tool| tool.name)
+    }
+
+    /// tool_use 输入参数中的字段，不是 tool_use 或字段不存在时返回 None
+    pub fn tool_input_field(&self, key: &str) -> Option<&serde_json::Value> {
+        self.as_tool_use()?.input.get(key)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -78,11 +174,39 @@ pub struct ToolResultItem {
     pub text: Option<String>,
 }
 
+/// gzip 文件头
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 读取 transcript 文件内容
+///
+/// 扩展名为 `.gz` 或以 gzip 文件头开头的文件会先解压
+pub fn read_transcript(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).context(format!("读取 transcript 文件失败: {}", path.display()))?;
+
+    let gzipped = path.extension().is_some_and(|ext| ext == "gz") || bytes.starts_with(&GZIP_MAGIC);
+    if !gzipped {
+        return String::from_utf8(bytes)
+            .context(format!("transcript 文件不是有效的 UTF-8: {}", path.display()));
+    }
+
+    let mut content = String::new();
+    flate2::read::GzDecoder::new(bytes.as_slice())
+        .read_to_string(&mut content)
+        .context(format!("解压 transcript 文件失败: {}", path.display()))?;
+    Ok(content)
+}
+
 /// 解析 transcript 文件
-pub fn parse_transcript(path: &Path) -> Result<Vec<Message>> {
-    let content = fs::read_to_string(path)
-        .context(format!("读取 transcript 文件失败: {}", path.display()))?;
+///
+/// `entry_types` 指定需要额外展示的非消息条目类型（如 `summary`），
+/// 这些条目会以伪消息的形式保留，role 为条目类型
+pub fn parse_transcript(path: &Path, entry_types: &[String]) -> Result<Vec<Message>> {
+    let content = read_transcript(path)?;
+    parse_transcript_content(&content, entry_types, &path.display().to_string())
+}
 
+/// 解析 transcript 内容，`source` 用于错误信息中标明来源
+pub fn parse_transcript_content(content: &str, entry_types: &[String], source: &str) -> Result<Vec<Message>> {
     let mut messages = Vec::new();
     for (line_no, line) in content.lines().enumerate() {
         if line.trim().is_empty() {
@@ -92,11 +216,20 @@ pub fn parse_transcript(path: &Path) -> Result<Vec<Message>> {
         let entry: TranscriptEntry = serde_json::from_str(line).context(format!(
             "解析 transcript 第 {} 行失败: {}",
             line_no + 1,
-            path.display()
+            source
         ))?;
 
-        // 只保留有 message 的条目（user 和 assistant 消息）
-        if let Some(message) = entry.message {
+        // 保留有 message 的条目（user 和 assistant 消息）
+        if let Some(mut message) = entry.message {
+            message.sidechain = entry.is_sidechain;
+            messages.push(message);
+            continue;
+        }
+
+        // 按配置保留其他类型的条目
+        if entry_types.contains(&entry.entry_type)
+            && let Some(message) = entry.into_pseudo_message()
+        {
             messages.push(message);
         }
     }
@@ -104,10 +237,123 @@ pub fn parse_transcript(path: &Path) -> Result<Vec<Message>> {
     Ok(messages)
 }
 
+/// 提取最后一条助手消息中的文本内容
+///
+/// 多个文本块按换行拼接；找不到任何助手文本时返回 None
+pub fn last_assistant_text(messages: &[Message]) -> Option<String> {
+    messages
+        .iter()
+        .rev()
+        .filter(|message| message.role == "assistant")
+        .find_map(|message| {
+            let text = match &message.content {
+                MessageContent::String(text) => text.trim().to_string(),
+                MessageContent::Array(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text { text } => Some(text.trim()),
+                        _ => None,
+                    })
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            };
+            (!text.is_empty()).then_some(text)
+        })
+}
+
+/// 会话预览的默认最大字符数
+pub const PREVIEW_MAX_CHARS: usize = 80;
+
+/// 提取第一条用户提问作为会话预览
+///
+/// 跳过子代理消息、工具结果与系统注入内容（如 system-reminder），
+/// 空白折叠为单个空格后按字素截断到 `max_chars`；找不到用户文本时返回 None
+pub fn first_user_preview(messages: &[Message], max_chars: usize) -> Option<String> {
+    messages
+        .iter()
+        .filter(|message| message.role == "user" && !message.sidechain)
+        .find_map(|message| {
+            let texts: Vec<&str> = match &message.content {
+                MessageContent::String(text) => vec![text.as_str()],
+                MessageContent::Array(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+            let text = texts
+                .iter()
+                .flat_map(|text| split_system_segments(text))
+                .filter(|(tag, _)| tag.is_none())
+                .flat_map(|(_, content)| content.split_whitespace())
+                .collect::<Vec<_>>()
+                .join(" ");
+            (!text.is_empty()).then(|| crate::util::truncate_graphemes(&text, max_chars))
+        })
+}
+
+/// 从 transcript 文件中读取首条用户提问的预览（规则同 [`first_user_preview`]）
+///
+/// 逐行读取，找到后立即停止，不解析整个文件；无法解析的行直接跳过
+pub fn read_first_user_preview(path: &Path, max_chars: usize) -> Option<String> {
+    use std::io::{BufRead, BufReader};
+
+    let mut reader = BufReader::new(fs::File::open(path).ok()?);
+    let gzipped = path.extension().is_some_and(|ext| ext == "gz") || reader.fill_buf().ok()?.starts_with(&GZIP_MAGIC);
+    let reader: Box<dyn BufRead> = if gzipped {
+        Box::new(BufReader::new(flate2::read::GzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    };
+
+    reader.lines().map_while(|line| line.ok()).find_map(|line| {
+        let entry: TranscriptEntry = serde_json::from_str(&line).ok()?;
+        let mut message = entry.message?;
+        message.sidechain = entry.is_sidechain;
+        first_user_preview(std::slice::from_ref(&message), max_chars)
+    })
+}
+
+/// 会话暂无消息时的占位提示
+const EMPTY_PLACEHOLDER: &str = r#"            <div class="empty-placeholder">
+                <p>📭 该会话暂无消息记录</p>
+                <p>Transcript 文件已创建，但还没有写入内容，请稍后刷新</p>
+            </div>
+"#;
+
 /// 将消息渲染成 HTML
-pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
+pub fn render_to_html(messages: &[Message], session_id: &str, options: &TranscriptConfig) -> String {
     let mut html = String::new();
     let safe_session_id = html_escape(session_id);
+    let inline = options.asset_mode == ASSET_MODE_INLINE;
+    let safe_title = html_escape(&options.page_title);
+
+    // 配置了 Logo 时显示图片，否则显示默认图标
+    let header_icon = if options.logo_url.is_empty() {
+        "📝".to_string()
+    } else {
+        format!(r#"<img class="logo" src="{}" alt="">"#, html_escape_attr(&options.logo_url))
+    };
+
+    // 提示横幅只出现在页面中，不写入 <title>
+    let page_banner = if options.page_banner.trim().is_empty() {
+        String::new()
+    } else {
+        format!(
+            "        <div class=\"page-banner\">⚠️ {}</div>\n",
+            html_escape(options.page_banner.trim())
+        )
+    };
+
+    // 存在子代理消息时在页眉提供折叠开关
+    let sidechain_toggle = if messages.iter().any(|m| m.sidechain) {
+        "            <button class=\"sidechain-toggle\" onclick=\"toggleSidechains()\">折叠子代理</button>\n"
+    } else {
+        ""
+    };
 
     // HTML 头部
     html.push_str(&format!(
@@ -116,7 +362,7 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>会话记录 - {}</title>
+{}    <title>{} - {}</title>
     <style>
         * {{
             margin: 0;
@@ -159,6 +405,35 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
             font-size: 0.9em;
         }}
 
+        header .logo {{
+            height: 1.2em;
+            vertical-align: middle;
+            margin-right: 8px;
+        }}
+
+        .live-status {{
+            position: fixed;
+            top: 12px;
+            left: 50%;
+            transform: translateX(-50%);
+            background: #ffebee;
+            color: #c62828;
+            padding: 6px 14px;
+            border-radius: 16px;
+            font-size: 0.85em;
+            box-shadow: 0 2px 6px rgba(0, 0, 0, 0.15);
+            z-index: 10;
+        }}
+
+        .page-banner {{
+            background: #fff3cd;
+            color: #856404;
+            border-bottom: 1px solid #ffeeba;
+            padding: 10px 20px;
+            text-align: center;
+            font-weight: bold;
+        }}
+
         .scroll-btn {{
             position: fixed;
             bottom: 30px;
@@ -185,6 +460,30 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
             padding: 20px;
         }}
 
+        .empty-placeholder {{
+            text-align: center;
+            color: #9e9e9e;
+            padding: 60px 20px;
+        }}
+
+        .empty-placeholder p + p {{
+            margin-top: 8px;
+            font-size: 13px;
+        }}
+
+        .markdown-notice {{
+            margin-bottom: 16px;
+            padding: 8px 12px;
+            border-radius: 6px;
+            background: #fff8e1;
+            color: #8d6e00;
+            font-size: 13px;
+        }}
+
+        .markdown-fallback {{
+            white-space: pre-wrap;
+        }}
+
         .message {{
             margin-bottom: 20px;
             padding: 15px 20px;
@@ -218,6 +517,17 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
             color: #7b1fa2;
         }}
 
+        .message.other {{
+            background: #fafafa;
+            border-left: 4px dashed #9e9e9e;
+            color: #616161;
+            font-size: 0.9em;
+        }}
+
+        .message.other .message-role {{
+            color: #757575;
+        }}
+
         .message-content {{
             white-space: pre-wrap;
             word-wrap: break-word;
@@ -244,6 +554,84 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
             padding: 0;
         }}
 
+        .repeat-badge {{
+            display: inline-block;
+            margin-left: 6px;
+            padding: 0 6px;
+            border-radius: 8px;
+            background: #ff9800;
+            color: white;
+            font-size: 0.85em;
+            letter-spacing: 0;
+        }}
+
+        .code-wrap {{
+            position: relative;
+        }}
+
+        .copy-btn {{
+            font-size: 12px;
+            padding: 2px 8px;
+            border: 1px solid #ccc;
+            border-radius: 4px;
+            background: rgba(255, 255, 255, 0.9);
+            color: #555;
+            cursor: pointer;
+            user-select: none;
+        }}
+
+        .code-wrap > .copy-btn {{
+            position: absolute;
+            top: 6px;
+            right: 6px;
+        }}
+
+        .message-role .copy-btn {{
+            margin-left: 8px;
+            font-weight: normal;
+            text-transform: none;
+            letter-spacing: 0;
+        }}
+
+        .message-content table {{
+            border-collapse: collapse;
+            margin: 10px 0;
+            display: block;
+            overflow-x: auto;
+            white-space: normal;
+        }}
+
+        .message-content th,
+        .message-content td {{
+            border: 1px solid #ddd;
+            padding: 6px 12px;
+            text-align: left;
+        }}
+
+        .message-content th {{
+            background: #f5f5f5;
+            font-weight: bold;
+        }}
+
+        .message-content tr:nth-child(even) td {{
+            background: #fafafa;
+        }}
+
+        .message-content .task-list-item,
+        .message-content li:has(> input[type="checkbox"]) {{
+            list-style: none;
+        }}
+
+        .message-content li > input[type="checkbox"] {{
+            margin: 0 6px 0 -20px;
+            vertical-align: middle;
+        }}
+
+        .image-block img {{
+            max-width: 100%;
+            border-radius: 6px;
+        }}
+
         .tool-use {{
             background: #fff3e0;
             border-left-color: #ff9800;
@@ -256,12 +644,91 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
             margin-bottom: 5px;
         }}
 
+        .tool-use .tool-summary {{
+            font-weight: normal;
+            opacity: 0.8;
+        }}
+
         .tool-result {{
             background: #e8f5e9;
             border-left-color: #4caf50;
             font-size: 0.9em;
         }}
 
+        .tool-result.error {{
+            background: #ffebee;
+            border-left-color: #f44336;
+        }}
+
+        .tool-result.error .tool-result-label {{
+            color: #c62828;
+        }}
+
+        .tool-group {{
+            border: 1px dashed #ff9800;
+            border-radius: 8px;
+            padding: 10px 12px;
+            margin: 10px 0;
+        }}
+
+        .tool-group-header {{
+            font-weight: bold;
+            color: #f57c00;
+            font-size: 0.9em;
+            margin-bottom: 6px;
+        }}
+
+        .tool-group .tool-use,
+        .tool-group .tool-result {{
+            margin: 6px 0;
+        }}
+
+        .tool-result-label {{
+            font-weight: bold;
+            color: #388e3c;
+            margin-bottom: 5px;
+        }}
+
+        .sidechain {{
+            margin: 0 0 20px 24px;
+            padding: 10px 15px;
+            border-left: 3px dotted #90a4ae;
+            background: #fafafa;
+            border-radius: 8px;
+        }}
+
+        .sidechain > summary {{
+            cursor: pointer;
+            font-weight: bold;
+            color: #546e7a;
+            margin-bottom: 10px;
+        }}
+
+        .sidechain-toggle {{
+            margin-top: 10px;
+            background: rgba(255, 255, 255, 0.2);
+            color: white;
+            border: 1px solid rgba(255, 255, 255, 0.6);
+            padding: 4px 12px;
+            border-radius: 12px;
+            cursor: pointer;
+        }}
+
+        .system-block {{
+            background: #fafafa;
+            border-left: 3px solid #bdbdbd;
+            color: #757575;
+            padding: 8px 12px;
+            margin: 10px 0;
+            border-radius: 4px;
+            font-size: 0.85em;
+        }}
+
+        .system-header {{
+            font-weight: bold;
+            margin-bottom: 4px;
+        }}
+
         .thinking {{
             background: #fff8e1;
             border-left: 4px solid #ffc107;
@@ -298,181 +765,1861 @@ pub fn render_to_html(messages: &[Message], session_id: &str) -> String {
             }}
         }}
     </style>
-    <script src="https://cdn.jsdelivr.net/npm/marked@12/marked.min.js"></script>
-    <link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/styles/github.min.css">
-    <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/highlight.min.js"></script>
-</head>
+{}{}</head>
 <body>
     <div class="container">
         <header>
-            <h1>📝 会话记录</h1>
+            <h1>{} {}</h1>
             <p>Session ID: {}</p>
-        </header>
-        <div class="messages">
+{}        </header>
+{}        <div class="messages">
 "#,
-        safe_session_id, safe_session_id
+        refresh_tag(options.refresh_secs),
+        safe_title,
+        safe_session_id,
+        asset_tags(&options.asset_mode, &options.code_theme),
+        role_style(options),
+        header_icon,
+        safe_title,
+        safe_session_id,
+        sidechain_toggle,
+        page_banner
     ));
 
     // 渲染每条消息
-    for message in messages {
-        let role_class = match message.role.as_str() {
-            "user" => "user",
-            "assistant" => "assistant",
-            _ => "other",
-        };
+    // 倒序时最新的消息在最上方
+    let descending = options.message_order.eq_ignore_ascii_case("desc");
+    let ordered: Vec<&Message> = if descending {
+        messages.iter().rev().collect()
+    } else {
+        messages.iter().collect()
+    };
 
-        html.push_str(&format!(
-            r#"            <div class="message {}">
-                <div class="message-role">{}</div>
-"#,
-            role_class, message.role
-        ));
+    // transcript 文件已存在但还没有写入消息（例如 Stop 时会话刚注册），显示占位提示而不是空白页
+    if messages.is_empty() {
+        html.push_str(EMPTY_PLACEHOLDER);
+    }
 
-        // 渲染消息内容
-        match &message.content {
-            MessageContent::String(text) => {
-                html.push_str(r#"                <div class="message-content" data-markdown>"#);
-                html.push_str(&html_escape(text));
-                html.push_str("</div>\n");
-            }
-            MessageContent::Array(blocks) => {
-                for block in blocks {
-                    match block {
-                        ContentBlock::Text { text } => {
-                            html.push_str(r#"                <div class="message-content" data-markdown>"#);
-                            html.push_str(&html_escape(text));
-                            html.push_str("</div>\n");
-                        }
-                        ContentBlock::Thinking { thinking } => {
-                            html.push_str(r#"                <div class="thinking">"#);
-                            html.push_str(r#"<div class="thinking-header">💭 思考过程</div>"#);
-                            html.push_str("<pre><code>");
-                            html.push_str(&html_escape(thinking));
-                            html.push_str("</code></pre>");
-                            html.push_str("</div>\n");
-                        }
-                        ContentBlock::ToolUse { name, input, .. } => {
-                            html.push_str(r#"                <div class="tool-use">"#);
-                            html.push_str(&format!(r#"<div class="tool-name">🔧 Tool: {}</div>"#, name));
-                            html.push_str("<pre><code>");
-                            html.push_str(&html_escape(
-                                &serde_json::to_string_pretty(input).unwrap_or_default(),
-                            ));
-                            html.push_str("</code></pre>");
-                            html.push_str("</div>\n");
-                        }
-                        ContentBlock::ToolResult { content, .. } => {
-                            html.push_str(r#"                <div class="tool-result">"#);
-                            html.push_str("<pre><code>");
-                            match content {
-                                ToolResultContent::String(s) => {
-                                    html.push_str(&html_escape(s));
-                                }
-                                ToolResultContent::Array(items) => {
-                                    for item in items {
-                                        if let Some(text) = &item.text {
-                                            html.push_str(&html_escape(text));
-                                        }
-                                    }
-                                }
-                            }
-                            html.push_str("</code></pre>");
-                            html.push_str("</div>\n");
-                        }
-                        ContentBlock::Other => {}
-                    }
-                }
-            }
+    let parallel = parallel_tool_calls(messages, options);
+
+    let ordered: Vec<(&Message, usize)> = if options.dedupe_messages {
+        collapse_duplicates(&ordered)
+    } else {
+        ordered.into_iter().map(|message| (message, 1)).collect()
+    };
+
+    // 连续的子代理消息归为一组，嵌套展示并可折叠
+    for group in ordered.chunk_by(|a, b| a.0.sidechain == b.0.sidechain) {
+        let sidechain = group[0].0.sidechain;
+        if sidechain {
+            html.push_str(&format!(
+                "            <details class=\"sidechain\" open><summary>🤖 子代理（{} 条消息）</summary>\n",
+                group.iter().map(|(_, count)| count).sum::<usize>()
+            ));
+        }
+
+        for (message, count) in group {
+            push_message(&mut html, message, options, &parallel, *count);
         }
 
-        html.push_str("            </div>\n");
+        if sidechain {
+            html.push_str("            </details>\n");
+        }
+    }
+
+    html.push_str("        </div>\n    </div>\n");
+
+    // 跳转按钮：正序跳到底部（最新），倒序回到顶部（最新）
+    if descending {
+        html.push_str(r#"    <button class="scroll-btn" onclick="scrollToTop()">⬆️ 回到顶部</button>"#);
+    } else {
+        html.push_str(r#"    <button class="scroll-btn" onclick="scrollToBottom()">⬇️ 跳到底部</button>"#);
     }
+    html.push('\n');
 
     // HTML 尾部
     html.push_str(
-        r#"        </div>
-    </div>
-    <button class="scroll-btn" onclick="scrollToBottom()">⬇️ 跳到底部</button>
-    <script>
+        r#"    <script>
         function scrollToBottom() {
             window.scrollTo({ top: document.body.scrollHeight, behavior: 'smooth' });
         }
 
-        // Markdown 渲染
-        document.addEventListener('DOMContentLoaded', function() {
-            const renderer = new marked.Renderer();
-            renderer.html = () => '';
-            renderer.link = (href, title, text) => {
-                const safeHref = sanitizeUrl(href);
-                if (!safeHref) return text;
-                const titleAttr = title ? ` title="${escapeAttr(title)}"` : '';
-                return `<a href="${safeHref}"${titleAttr} rel="noopener noreferrer" target="_blank">${text}</a>`;
-            };
-            renderer.image = (href, title, text) => {
-                const safeHref = sanitizeUrl(href);
-                if (!safeHref) return text;
-                const titleAttr = title ? ` title="${escapeAttr(title)}"` : '';
-                const altAttr = text ? ` alt="${escapeAttr(text)}"` : ' alt=""';
-                return `<img src="${safeHref}"${altAttr}${titleAttr} />`;
-            };
+        function scrollToTop() {
+            window.scrollTo({ top: 0, behavior: 'smooth' });
+        }
+
+        function toggleSidechains() {
+            const groups = document.querySelectorAll('details.sidechain');
+            const collapse = Array.from(groups).some(el => el.open);
+            groups.forEach(el => { el.open = !collapse; });
+            document.querySelector('.sidechain-toggle').textContent = collapse ? '展开子代理' : '折叠子代理';
+        }
+    </script>
+"#,
+    );
 
-            marked.use({ renderer, mangle: false, headerIds: false });
+    // inline 模式下 Markdown 已在服务端渲染，不依赖外部脚本
+    if !inline {
+        html.push_str(MARKDOWN_SCRIPT);
+    }
 
-            document.querySelectorAll('[data-markdown]').forEach(el => {
-                const markdown = el.textContent;
-                el.innerHTML = marked.parse(markdown);
-            });
+    html.push_str(COPY_SCRIPT);
 
-            // 代码高亮
-            hljs.highlightAll();
-        });
+    // 实时模式依赖 serve 的事件流，自包含的导出页面不启用
+    if options.live && !inline {
+        html.push_str(LIVE_SCRIPT);
+    }
 
-        function sanitizeUrl(href) {
-            if (!href) return null;
-            if (href.startsWith('#') || href.startsWith('/')) return href;
-            try {
-                const url = new URL(href, window.location.origin);
-                if (['http:', 'https:', 'mailto:'].includes(url.protocol)) {
-                    return url.href;
-                }
-            } catch (e) {
-                return null;
+    html.push_str("</body>\n</html>\n");
+
+    if options.minify_html {
+        minify_html(&html)
+    } else {
+        html
+    }
+}
+
+/// 不做压缩、原样保留内容的元素
+///
+/// 消息内容使用 `white-space: pre-wrap` 展示，其中的空白同样有意义
+const PRESERVED_TAGS: &[&str] = &["pre", "code", "script", "textarea"];
+
+/// 压缩 HTML
+///
+/// 删除标签之间仅含空白的文本（缩进、换行），其余文本中的连续空白折叠为一个空格，
+/// `<style>` 中的连续空白同样折叠；[`PRESERVED_TAGS`] 与消息内容中的空白原样保留
+pub fn minify_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            let tag = &rest[..end];
+            out.push_str(tag);
+            rest = &rest[end..];
+
+            let Some(name) = opening_tag_name(tag) else {
+                continue;
+            };
+            if tag.ends_with("/>") {
+                continue;
             }
-            return null;
-        }
 
-        function escapeAttr(value) {
-            return value
-                .replace(/&/g, "&amp;")
-                .replace(/"/g, "&quot;")
-                .replace(/</g, "&lt;")
-                .replace(/>/g, "&gt;");
+            if PRESERVED_TAGS.contains(&name) || tag.contains(r#"class="message-content""#) {
+                let len = element_content_len(rest, name);
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+            } else if name == "style" {
+                let len = rest.find("</style>").unwrap_or(rest.len());
+                out.push_str(&collapse_whitespace(&rest[..len]));
+                rest = &rest[len..];
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = &rest[..end];
+            if !text.trim().is_empty() {
+                out.push_str(&collapse_whitespace(text));
+            }
+            rest = &rest[end..];
         }
-    </script>
-</body>
-</html>
-"#,
-    );
+    }
 
-    html
+    out
 }
 
-/// HTML 转义
-fn html_escape(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
+/// 开始标签的标签名（结束标签、注释与 DOCTYPE 返回 None）
+fn opening_tag_name(tag: &str) -> Option<&str> {
+    let body = tag.strip_prefix('<')?;
+    let len = body
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(body.len());
+    (len > 0).then(|| &body[..len])
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// 元素内容（含结束标签）的长度，处理同名元素嵌套
+fn element_content_len(rest: &str, name: &str) -> usize {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut depth = 1;
+    let mut pos = 0;
 
-    #[test]
-    fn test_html_escape() {
-        assert_eq!(html_escape("<script>alert('xss')</script>"),
-                   "&lt;script&gt;alert(&#39;xss&#39;)&lt;/script&gt;");
+    while depth > 0 {
+        let next_close = match rest[pos..].find(&close) {
+            Some(i) => pos + i,
+            None => return rest.len(),
+        };
+        // 同名的嵌套开始标签（名称后须为 `>`、空白或 `/`）
+        let next_open = rest[pos..next_close].match_indices(&open).find_map(|(i, _)| {
+            let after = rest[pos + i + open.len()..].chars().next();
+            matches!(after, Some(c) if c == '>' || c == '/' || c.is_whitespace()).then_some(pos + i)
+        });
+
+        match next_open {
+            Some(i) => {
+                depth += 1;
+                pos = i + open.len();
+            }
+            None => {
+                depth -= 1;
+                pos = next_close + close.len();
+            }
+        }
+    }
+
+    pos
+}
+
+/// 将连续空白折叠为一个空格
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// 配置的角色强调色样式，覆盖默认配色
+///
+/// 只接受由字母、数字及 `#(),.% ` 组成的取值，避免注入额外的 CSS 或 HTML
+fn role_style(options: &TranscriptConfig) -> String {
+    let is_safe_color = |color: &str| {
+        color
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "#(),.% ".contains(c))
+    };
+
+    let mut style = String::new();
+    for (class, color) in [("user", &options.user_color), ("assistant", &options.assistant_color)] {
+        let color = color.trim();
+        if color.is_empty() || !is_safe_color(color) {
+            continue;
+        }
+        style.push_str(&format!(
+            "    <style>.message.{0} {{ border-left-color: {1}; }} .message.{0} .message-role {{ color: {1}; }}</style>\n",
+            class, color
+        ));
+    }
+    style
+}
+
+/// 角色显示名：配置了 user_label / assistant_label 时使用配置，否则使用原始角色名
+fn role_label<'a>(role: &'a str, options: &'a TranscriptConfig) -> &'a str {
+    match role {
+        "user" if !options.user_label.is_empty() => &options.user_label,
+        "assistant" if !options.assistant_label.is_empty() => &options.assistant_label,
+        _ => role,
+    }
+}
+
+/// 合并连续重复的消息，返回 `(消息, 连续出现次数)`
+fn collapse_duplicates<'a>(messages: &[&'a Message]) -> Vec<(&'a Message, usize)> {
+    let mut collapsed: Vec<(&Message, usize, String)> = Vec::new();
+
+    for message in messages {
+        let key = dedupe_key(message);
+        match collapsed.last_mut() {
+            Some((_, count, last_key)) if *last_key == key => *count += 1,
+            _ => collapsed.push((message, 1, key)),
+        }
+    }
+
+    collapsed.into_iter().map(|(message, count, _)| (message, count)).collect()
+}
+
+/// 去重比较用的键：角色、是否子代理与内容；文本按空白归一化，其他内容块按 JSON 比较
+fn dedupe_key(message: &Message) -> String {
+    let normalize = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let content = match &message.content {
+        MessageContent::String(text) => normalize(text),
+        MessageContent::Array(blocks) => blocks
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text { text } => normalize(text),
+                ContentBlock::Thinking { thinking } => normalize(thinking),
+                other => serde_json::to_string(other).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    format!("{}\n{}\n{}", message.role, message.sidechain, content)
+}
+
+/// 渲染单条消息
+fn push_message(
+    html: &mut String,
+    message: &Message,
+    options: &TranscriptConfig,
+    parallel: &ParallelCalls,
+    count: usize,
+) {
+    // 内容全部被过滤时整条消息不展示，避免留下空白气泡
+    let visible = match &message.content {
+        MessageContent::String(_) if !block_type_allowed("text", options) => return,
+        MessageContent::String(_) => Vec::new(),
+        MessageContent::Array(blocks) => {
+            let visible = visible_blocks(blocks, options);
+            if visible.is_empty() && !blocks.is_empty() {
+                return;
+            }
+            visible
+        }
+    };
+
+    let role_class = match message.role.as_str() {
+        "user" => "user",
+        "assistant" => "assistant",
+        _ => "other",
+    };
+
+    // 合并的重复消息在角色名后标注次数
+    let repeat_badge = if count > 1 {
+        format!(r#" <span class="repeat-badge" title="连续重复 {} 次">×{}</span>"#, count, count)
+    } else {
+        String::new()
+    };
+
+    html.push_str(&format!(
+        r#"            <div class="message {}">
+                <div class="message-role">{}{}</div>
+"#,
+        role_class,
+        html_escape(role_label(&message.role, options)),
+        repeat_badge
+    ));
+
+    // 渲染消息内容
+    match &message.content {
+        MessageContent::String(text) => {
+            push_text(html, text, options);
+        }
+        MessageContent::Array(_) => {
+            push_blocks(html, &visible, options, parallel);
+        }
+    }
+
+    html.push_str("            </div>\n");
+}
+
+/// 识别工具结果的内容类型并格式化
+///
+/// 内容为 JSON 对象或数组时美化输出，并返回用于代码高亮的语言标记 `json`；
+/// 其他内容原样返回
+pub fn detect_and_format_result(text: &str) -> (String, Option<&'static str>) {
+    let trimmed = text.trim();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return (text.to_string(), None);
+    }
+
+    match serde_json::from_str::<serde_json::Value>(trimmed) {
+        Ok(value) => match serde_json::to_string_pretty(&value) {
+            Ok(pretty) => (pretty, Some("json")),
+            Err(_) => (text.to_string(), None),
+        },
+        Err(_) => (text.to_string(), None),
+    }
+}
+
+/// 自动刷新的最小间隔（秒），避免过于频繁地重新解析 transcript
+const MIN_REFRESH_SECS: u64 = 5;
+
+/// 自动刷新标签，`secs` 为 0 时不刷新
+fn refresh_tag(secs: u64) -> String {
+    if secs == 0 {
+        return String::new();
+    }
+
+    format!(
+        "    <meta http-equiv=\"refresh\" content=\"{}\">\n",
+        secs.max(MIN_REFRESH_SECS)
+    )
+}
+
+/// 并行工具调用中单个调用的位置：(组内序号, 工具名)
+type ParallelCalls<'a> = HashMap<&'a str, (usize, &'a str)>;
+
+/// 收集并行工具调用
+///
+/// 同一条助手消息中连续出现的多个 tool_use 视为一次并行调用，
+/// 按出现顺序编号，用于让随后到达的 tool_result 与调用对齐
+fn parallel_tool_calls<'a>(messages: &'a [Message], options: &TranscriptConfig) -> ParallelCalls<'a> {
+    let mut parallel = HashMap::new();
+
+    for message in messages.iter().filter(|m| m.role == "assistant") {
+        let MessageContent::Array(blocks) = &message.content else {
+            continue;
+        };
+
+        for run in tool_use_runs(&visible_blocks(blocks, options)) {
+            if run.len() < 2 {
+                continue;
+            }
+            for (index, block) in run.iter().enumerate() {
+                if let Some(tool) = block.as_tool_use() {
+                    parallel.insert(tool.id, (index + 1, tool.name));
+                }
+            }
+        }
+    }
+
+    parallel
+}
+
+/// 按连续的 tool_use 切分内容块，非 tool_use 块各自成组
+fn tool_use_runs<'a, 'b>(blocks: &'b [&'a ContentBlock]) -> Vec<&'b [&'a ContentBlock]> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+
+    while start < blocks.len() {
+        let len = match blocks[start].tool_name() {
+            Some(_) => blocks[start..].iter().take_while(|b| b.tool_name().is_some()).count(),
+            None => 1,
+        };
+        runs.push(&blocks[start..start + len]);
+        start += len;
+    }
+
+    runs
+}
+
+/// `render_blocks` 可选的内容块类型
+pub const RENDER_BLOCK_TYPES: &[&str] = &["text", "thinking", "tool_use", "tool_result", "image"];
+
+/// 内容块类型是否在 `render_blocks` 白名单中
+fn block_type_allowed(kind: &str, options: &TranscriptConfig) -> bool {
+    options.render_blocks.iter().any(|allowed| allowed == kind)
+}
+
+/// 内容块是否需要渲染：不在 `render_blocks` 中的类型一律省略，
+/// 未识别的内容块（图片除外）仍由 `show_system` 控制
+fn block_visible(block: &ContentBlock, options: &TranscriptConfig) -> bool {
+    let kind = match block {
+        ContentBlock::Text { .. } => "text",
+        ContentBlock::Thinking { .. } => {
+            if !options.show_thinking {
+                return false;
+            }
+            "thinking"
+        }
+        ContentBlock::ToolUse { .. } => "tool_use",
+        ContentBlock::ToolResult { .. } => "tool_result",
+        ContentBlock::Other(raw) => match raw.get("type").and_then(|t| t.as_str()) {
+            Some("image") => "image",
+            _ => return true,
+        },
+    };
+    block_type_allowed(kind, options)
+}
+
+/// 需要渲染的内容块：先剔除隐藏的内容块，使被思考块隔开的工具调用仍能正确分组
+fn visible_blocks<'a>(blocks: &'a [ContentBlock], options: &TranscriptConfig) -> Vec<&'a ContentBlock> {
+    blocks.iter().filter(|b| block_visible(b, options)).collect()
+}
+
+/// 渲染消息的内容块
+///
+/// 并行工具调用包裹在「并行工具调用 (N)」分组中；
+/// 对应的多个结果同样分组，并按调用顺序排列
+fn push_blocks(html: &mut String, blocks: &[&ContentBlock], options: &TranscriptConfig, parallel: &ParallelCalls) {
+    let mut i = 0;
+
+    while i < blocks.len() {
+        let tool_uses = blocks[i..]
+            .iter()
+            .take_while(|b| b.tool_name().is_some())
+            .count();
+        if tool_uses > 1 {
+            push_tool_group(html, &format!("🔀 并行工具调用 ({})", tool_uses), blocks[i..i + tool_uses].iter().copied(), options, parallel);
+            i += tool_uses;
+            continue;
+        }
+
+        let parallel_results = blocks[i..]
+            .iter()
+            .take_while(|b| matches!(b, ContentBlock::ToolResult { tool_use_id, .. } if parallel.contains_key(tool_use_id.as_str())))
+            .count();
+        if parallel_results > 1 {
+            let mut results: Vec<&ContentBlock> = blocks[i..i + parallel_results].to_vec();
+            results.sort_by_key(|b| match b {
+                ContentBlock::ToolResult { tool_use_id, .. } => parallel.get(tool_use_id.as_str()).map(|(index, _)| *index),
+                _ => None,
+            });
+            push_tool_group(html, &format!("📥 并行工具结果 ({})", parallel_results), results, options, parallel);
+            i += parallel_results;
+            continue;
+        }
+
+        push_block(html, blocks[i], options, parallel);
+        i += 1;
+    }
+}
+
+fn push_tool_group<'a>(
+    html: &mut String,
+    header: &str,
+    blocks: impl IntoIterator<Item = &'a ContentBlock>,
+    options: &TranscriptConfig,
+    parallel: &ParallelCalls,
+) {
+    html.push_str(r#"                <div class="tool-group">"#);
+    html.push_str(&format!(r#"<div class="tool-group-header">{}</div>"#, header));
+    html.push('\n');
+    for block in blocks {
+        push_block(html, block, options, parallel);
+    }
+    html.push_str("                </div>\n");
+}
+
+/// 常用工具的图标，便于在工具调用较多的 transcript 中快速辨认
+const TOOL_ICONS: &[(&str, &str)] = &[
+    ("Read", "📖"),
+    ("Write", "📝"),
+    ("Edit", "✏️"),
+    ("MultiEdit", "✏️"),
+    ("NotebookEdit", "✏️"),
+    ("Bash", "💻"),
+    ("BashOutput", "💻"),
+    ("KillShell", "💻"),
+    ("Grep", "🔍"),
+    ("Glob", "📂"),
+    ("WebFetch", "🌐"),
+    ("WebSearch", "🔎"),
+    ("Task", "🤖"),
+    ("TodoWrite", "✅"),
+    ("ExitPlanMode", "📋"),
+];
+
+/// 工具调用标题中的图标：MCP 工具（`mcp__` 前缀）为 🔌，未知工具为 🔧
+fn tool_icon(name: &str) -> &'static str {
+    if name.starts_with("mcp__") {
+        return "🔌";
+    }
+
+    TOOL_ICONS
+        .iter()
+        .find(|(tool, _)| *tool == name)
+        .map_or("🔧", |(_, icon)| icon)
+}
+
+/// 工具名后附带的说明：Bash、Task 等工具输入中的 `description`，没有时为空
+fn tool_summary(block: &ContentBlock) -> String {
+    match block.tool_input_field("description").and_then(|v| v.as_str()).map(str::trim) {
+        Some(description) if !description.is_empty() => {
+            format!(r#"<span class="tool-summary"> · {}</span>"#, html_escape(description))
+        }
+        _ => String::new(),
+    }
+}
+
+fn push_block(html: &mut String, block: &ContentBlock, options: &TranscriptConfig, parallel: &ParallelCalls) {
+    match block {
+        ContentBlock::Text { text } => {
+            push_text(html, text, options);
+        }
+        ContentBlock::Thinking { thinking } => {
+            html.push_str(r#"                <div class="thinking">"#);
+            html.push_str(r#"<div class="thinking-header">💭 思考过程</div>"#);
+            html.push_str("<pre><code>");
+            html.push_str(&html_escape(thinking));
+            html.push_str("</code></pre>");
+            html.push_str("</div>\n");
+        }
+        ContentBlock::ToolUse { id, name, input } => {
+            html.push_str(r#"                <div class="tool-use">"#);
+            match parallel.get(id.as_str()) {
+                Some((index, _)) => html.push_str(&format!(
+                    r#"<div class="tool-name">{} #{} Tool: {}{}</div>"#,
+                    tool_icon(name),
+                    index,
+                    html_escape(name),
+                    tool_summary(block)
+                )),
+                None => html.push_str(&format!(
+                    r#"<div class="tool-name">{} Tool: {}{}</div>"#,
+                    tool_icon(name),
+                    html_escape(name),
+                    tool_summary(block)
+                )),
+            }
+            html.push_str("<pre><code>");
+            html.push_str(&html_escape(
+                &serde_json::to_string_pretty(input).unwrap_or_default(),
+            ));
+            html.push_str("</code></pre>");
+            html.push_str("</div>\n");
+        }
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } => {
+            let is_error = is_error.unwrap_or(false);
+            if is_error {
+                html.push_str(r#"                <div class="tool-result error">"#);
+            } else {
+                html.push_str(r#"                <div class="tool-result">"#);
+            }
+            match (parallel.get(tool_use_id.as_str()), is_error) {
+                (Some((index, name)), _) => html.push_str(&format!(
+                    r#"<div class="tool-result-label">{} #{} {}</div>"#,
+                    if is_error { "⚠️" } else { "↩️" },
+                    index,
+                    html_escape(name)
+                )),
+                (None, true) => html.push_str(r#"<div class="tool-result-label">⚠️ 执行出错</div>"#),
+                (None, false) => {}
+            }
+            match content {
+                ToolResultContent::String(s) => {
+                    let (text, language) = detect_and_format_result(s);
+                    match language {
+                        Some(language) => html.push_str(&format!(
+                            r#"<pre><code class="language-{}">"#,
+                            html_escape_attr(language)
+                        )),
+                        None => html.push_str("<pre><code>"),
+                    }
+                    html.push_str(&html_escape(&text));
+                }
+                ToolResultContent::Array(items) => {
+                    html.push_str("<pre><code>");
+                    for item in items {
+                        if let Some(text) = &item.text {
+                            html.push_str(&html_escape(text));
+                        }
+                    }
+                }
+            }
+            html.push_str("</code></pre>");
+            html.push_str("</div>\n");
+        }
+        ContentBlock::Other(raw) => {
+            if let Some(src) = image_data_uri(raw) {
+                html.push_str(&format!(
+                    "                <div class=\"image-block\"><img src=\"{}\" alt=\"图片\"></div>\n",
+                    html_escape_attr(&src)
+                ));
+            } else if options.show_system {
+                let kind = raw.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
+                push_system_block(html, kind, &serde_json::to_string_pretty(raw).unwrap_or_default());
+            }
+        }
+    }
+}
+
+/// Claude Code 注入到消息文本中的系统内容标签
+const SYSTEM_TAGS: &[&str] = &["system-reminder", "user-prompt-submit-hook"];
+
+/// 将文本拆分为普通内容与系统内容片段
+///
+/// 系统内容片段为 [`SYSTEM_TAGS`] 中标签包裹的部分，返回 `(标签名, 内容)`；
+/// 普通内容的标签名为 None。缺少闭合标签时其后的内容按普通文本处理
+fn split_system_segments(text: &str) -> Vec<(Option<&'static str>, &str)> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    loop {
+        let next = SYSTEM_TAGS
+            .iter()
+            .filter_map(|tag| {
+                let open = format!("<{}>", tag);
+                let start = rest.find(&open)?;
+                let close = format!("</{}>", tag);
+                let end = rest[start + open.len()..].find(&close)? + start + open.len();
+                Some((start, tag, start + open.len(), end, end + close.len()))
+            })
+            .min_by_key(|(start, ..)| *start);
+
+        let Some((start, tag, body_start, body_end, after)) = next else {
+            break;
+        };
+
+        if start > 0 {
+            segments.push((None, &rest[..start]));
+        }
+        segments.push((Some(*tag), &rest[body_start..body_end]));
+        rest = &rest[after..];
+    }
+
+    if !rest.is_empty() {
+        segments.push((None, rest));
+    }
+    segments
+}
+
+/// 渲染消息文本，系统内容按 show_system 展示或隐藏
+fn push_text(html: &mut String, text: &str, options: &TranscriptConfig) {
+    let inline = options.asset_mode == ASSET_MODE_INLINE;
+    let segments = split_system_segments(text);
+
+    // 不含系统内容时保持原样，避免改变普通文本的空白
+    if segments.iter().all(|(tag, _)| tag.is_none()) {
+        push_markdown(html, text, inline);
+        return;
+    }
+
+    for (tag, content) in segments {
+        match tag {
+            Some(tag) if options.show_system => push_system_block(html, tag, content.trim()),
+            Some(_) => {}
+            None if content.trim().is_empty() => {}
+            None => push_markdown(html, content.trim(), inline),
+        }
+    }
+}
+
+fn push_system_block(html: &mut String, kind: &str, content: &str) {
+    html.push_str(r#"                <div class="system-block">"#);
+    html.push_str(&format!(
+        r#"<div class="system-header">⚙️ 系统提示 · {}</div>"#,
+        html_escape(kind)
+    ));
+    html.push_str("<pre><code>");
+    html.push_str(&html_escape(content));
+    html.push_str("</code></pre>");
+    html.push_str("</div>\n");
+}
+
+/// 复制按钮脚本：为每个代码块和每条助手消息添加「复制」按钮
+///
+/// 在 load 事件中执行，确保客户端 Markdown 渲染与代码高亮已完成。
+/// 代码块的按钮放在包裹 `<pre>` 的容器中而不是 `<pre>` 内部，选中复制代码时不会带上按钮文字；
+/// 复制整条消息时在副本上去掉代码块的按钮再取文本（见 `copyableText`）；
+/// 非安全上下文（如 http 访问）没有 Clipboard API 时退回 `execCommand('copy')`
+const COPY_SCRIPT: &str = r#"    <script>
+        window.addEventListener('load', function() {
+            document.querySelectorAll('pre').forEach(pre => {
+                const wrap = document.createElement('div');
+                wrap.className = 'code-wrap';
+                pre.parentNode.insertBefore(wrap, pre);
+                wrap.appendChild(pre);
+                wrap.appendChild(copyButton(() => pre.innerText));
+            });
+
+            document.querySelectorAll('.message.assistant').forEach(message => {
+                const contents = message.querySelectorAll('.message-content');
+                if (contents.length === 0) return;
+                const text = () => Array.from(contents).map(copyableText).join('\n\n');
+                message.querySelector('.message-role').appendChild(copyButton(text));
+            });
+        });
+
+        function copyableText(el) {
+            const clone = el.cloneNode(true);
+            clone.querySelectorAll('.copy-btn').forEach(button => button.remove());
+            return clone.textContent.trim();
+        }
+
+        function copyButton(getText) {
+            const button = document.createElement('button');
+            button.className = 'copy-btn';
+            button.type = 'button';
+            button.textContent = '复制';
+            button.addEventListener('click', () => {
+                copyText(getText()).then(() => {
+                    button.textContent = '已复制';
+                    setTimeout(() => { button.textContent = '复制'; }, 1500);
+                });
+            });
+            return button;
+        }
+
+        function copyText(text) {
+            if (navigator.clipboard && window.isSecureContext) {
+                return navigator.clipboard.writeText(text);
+            }
+            const textarea = document.createElement('textarea');
+            textarea.value = text;
+            textarea.style.position = 'fixed';
+            textarea.style.opacity = '0';
+            document.body.appendChild(textarea);
+            textarea.select();
+            document.execCommand('copy');
+            textarea.remove();
+            return Promise.resolve();
+        }
+    </script>
+"#;
+
+/// 实时模式脚本：订阅 `/<会话ID>/events`，收到 `update` 事件时刷新页面
+///
+/// 连接断开（如 frpc 隧道抖动）时显示提示并按指数退避重连，最长间隔 30 秒；
+/// 重连成功后刷新一次，补上断线期间的更新
+const LIVE_SCRIPT: &str = r#"    <div class="live-status" hidden>连接已断开，正在重连...</div>
+    <script>
+        (function() {
+            const url = location.pathname.replace(/\/+$/, '') + '/events';
+            const status = document.querySelector('.live-status');
+            const maxDelay = 30000;
+            let delay = 1000;
+            let disconnected = false;
+
+            function connect() {
+                const source = new EventSource(url);
+                source.onopen = () => {
+                    delay = 1000;
+                    if (disconnected) location.reload();
+                };
+                source.addEventListener('update', () => location.reload());
+                source.onerror = () => {
+                    source.close();
+                    disconnected = true;
+                    status.hidden = false;
+                    setTimeout(connect, delay);
+                    delay = Math.min(delay * 2, maxDelay);
+                };
+            }
+
+            connect();
+        })();
+    </script>
+"#;
+
+/// 客户端 Markdown 渲染与代码高亮脚本（依赖 marked.js 与 highlight.js）
+const MARKDOWN_SCRIPT: &str = r#"    <script>
+        // Markdown 渲染
+        document.addEventListener('DOMContentLoaded', function() {
+            // marked.js 加载失败（如 CDN 不可达）时保留转义后的原文，并提示未渲染
+            if (typeof marked === 'undefined') {
+                showMarkdownFallback();
+                return;
+            }
+
+            const renderer = new marked.Renderer();
+            renderer.html = () => '';
+            renderer.link = (href, title, text) => {
+                const safeHref = sanitizeUrl(href);
+                if (!safeHref) return text;
+                const titleAttr = title ? ` title="${escapeAttr(title)}"` : '';
+                return `<a href="${safeHref}"${titleAttr} rel="noopener noreferrer" target="_blank">${text}</a>`;
+            };
+            renderer.image = (href, title, text) => {
+                const safeHref = sanitizeUrl(href);
+                if (!safeHref) return text;
+                const titleAttr = title ? ` title="${escapeAttr(title)}"` : '';
+                const altAttr = text ? ` alt="${escapeAttr(text)}"` : ' alt=""';
+                return `<img src="${safeHref}"${altAttr}${titleAttr} />`;
+            };
+
+            // GFM：表格、任务列表、删除线；.message-content 已是 pre-wrap，无需 breaks
+            marked.use({ renderer, gfm: true, breaks: false, mangle: false, headerIds: false });
+
+            document.querySelectorAll('[data-markdown]').forEach(el => {
+                const markdown = el.textContent;
+                el.innerHTML = marked.parse(markdown);
+            });
+
+            // 代码高亮
+            if (typeof hljs !== 'undefined') {
+                hljs.highlightAll();
+            }
+        });
+
+        function showMarkdownFallback() {
+            const blocks = document.querySelectorAll('[data-markdown]');
+            if (!blocks.length) return;
+
+            blocks.forEach(el => el.classList.add('markdown-fallback'));
+            const notice = document.createElement('div');
+            notice.className = 'markdown-notice';
+            notice.textContent = '⚠️ Markdown 渲染不可用，消息按原文显示';
+            (document.querySelector('.messages') || document.body).prepend(notice);
+        }
+
+        function sanitizeUrl(href) {
+            if (!href) return null;
+            if (href.startsWith('#') || href.startsWith('/')) return href;
+            try {
+                const url = new URL(href, window.location.origin);
+                if (['http:', 'https:', 'mailto:'].includes(url.protocol)) {
+                    return url.href;
+                }
+            } catch (e) {
+                return null;
+            }
+            return null;
+        }
+
+        function escapeAttr(value) {
+            return value
+                .replace(/&/g, "&amp;")
+                .replace(/"/g, "&quot;")
+                .replace(/</g, "&lt;")
+                .replace(/>/g, "&gt;");
+        }
+    </script>
+"#;
+
+/// base64 图片内容块的 data URI，不是图片或格式不受支持时返回 None
+///
+/// 只接受常见的图片类型，数据须为合法的 base64 字符，避免拼接进 `src` 后被解释为其他内容
+pub fn image_data_uri(block: &serde_json::Value) -> Option<String> {
+    if block.get("type").and_then(|t| t.as_str()) != Some("image") {
+        return None;
+    }
+
+    let source = block.get("source")?;
+    if source.get("type").and_then(|t| t.as_str()) != Some("base64") {
+        return None;
+    }
+
+    let media_type = source.get("media_type").and_then(|m| m.as_str())?;
+    if !matches!(media_type, "image/png" | "image/jpeg" | "image/gif" | "image/webp") {
+        return None;
+    }
+
+    let data = source.get("data").and_then(|d| d.as_str())?;
+    let valid = !data.is_empty() && data.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='));
+    valid.then(|| format!("data:{};base64,{}", media_type, data))
+}
+
+/// highlight.js 的 CDN 地址
+pub const HIGHLIGHT_CDN_BASE: &str = "https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0";
+
+/// 代码高亮主题样式表的相对路径（相对于 CDN 地址或 `assets/vendor/`）
+pub fn code_theme_stylesheet(code_theme: &str) -> String {
+    format!("styles/{}.min.css", code_theme_name(code_theme))
+}
+
+/// 资源加载方式：从本机 `~/.gewe-cc/assets/vendor/` 加载（适用于无法访问 CDN 的内网部署）
+pub const ASSET_MODE_LOCAL: &str = "local";
+
+/// 资源加载方式：完全自包含，Markdown 在服务端渲染，不引用任何外部资源（用于导出）
+pub const ASSET_MODE_INLINE: &str = "inline";
+
+/// 根据资源加载方式生成 `<head>` 中的脚本与样式标签
+///
+/// 未识别的取值按默认的 cdn 处理，从公共 CDN 加载 marked.js / highlight.js
+fn asset_tags(asset_mode: &str, code_theme: &str) -> String {
+    let stylesheet = code_theme_stylesheet(code_theme);
+    match asset_mode {
+        ASSET_MODE_INLINE => String::new(),
+        ASSET_MODE_LOCAL => format!(
+            r#"    <script src="/assets/vendor/marked.min.js"></script>
+    <link rel="stylesheet" href="/assets/vendor/{}">
+    <script src="/assets/vendor/highlight.min.js"></script>
+"#,
+            stylesheet
+        ),
+        _ => format!(
+            r#"    <script src="https://cdn.jsdelivr.net/npm/marked@12/marked.min.js"></script>
+    <link rel="stylesheet" href="{base}/{}">
+    <script src="{base}/highlight.min.js"></script>
+"#,
+            stylesheet,
+            base = HIGHLIGHT_CDN_BASE
+        ),
+    }
+}
+
+/// 默认的 highlight.js 主题
+pub const DEFAULT_CODE_THEME: &str = "github";
+
+/// 规范化 highlight.js 主题名
+///
+/// 主题名会拼接进样式表地址，只接受字母、数字、`-`、`_`、`.` 以及子目录分隔符 `/`
+/// （如 `base16/monokai`），不合法或为空时使用默认主题
+fn code_theme_name(theme: &str) -> &str {
+    let theme = theme.trim().trim_end_matches(".min.css").trim_end_matches(".css");
+    let valid = !theme.is_empty()
+        && !theme.contains("..")
+        && !theme.starts_with('/')
+        && theme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c));
+
+    if valid { theme } else { DEFAULT_CODE_THEME }
+}
+
+/// 输出一段 Markdown 文本
+///
+/// 默认输出转义后的原文并标记 `data-markdown`，由浏览器端 marked.js 渲染；
+/// inline 模式下直接在服务端渲染为 HTML
+fn push_markdown(html: &mut String, text: &str, inline: bool) {
+    if inline {
+        html.push_str(r#"                <div class="message-content">"#);
+        html.push_str(&markdown_to_html(text));
+    } else {
+        html.push_str(r#"                <div class="message-content" data-markdown>"#);
+        html.push_str(&html_escape(text));
+    }
+    html.push_str("</div>\n");
+}
+
+/// 在服务端将 Markdown 渲染为 HTML
+///
+/// 与客户端渲染保持一致的安全策略：原始 HTML 按文本输出，
+/// 链接和图片只允许 http/https/mailto、站内路径和锚点
+fn markdown_to_html(text: &str) -> String {
+    use pulldown_cmark::{Event, Options, Parser, Tag};
+
+    let options = Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH;
+    let events = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) if !is_safe_url(&dest_url) => {
+            Event::Start(Tag::Link { link_type, dest_url: "#".into(), title, id })
+        }
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) if !is_safe_url(&dest_url) => {
+            Event::Start(Tag::Image { link_type, dest_url: "".into(), title, id })
+        }
+        other => other,
+    });
+
+    let mut out = String::new();
+    pulldown_cmark::html::push_html(&mut out, events);
+    out
+}
+
+/// 判断链接地址是否安全（与页面脚本中的 sanitizeUrl 规则一致）
+fn is_safe_url(url: &str) -> bool {
+    if url.starts_with('#') || url.starts_with('/') {
+        return true;
+    }
+
+    match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains('/') => {
+            matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https" | "mailto")
+        }
+        // 无协议的相对路径
+        _ => true,
+    }
+}
+
+/// HTML 转义
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 转义双引号包裹的属性值
+///
+/// 在 [`html_escape`] 的基础上把换行、制表符等控制字符转为数字字符引用，
+/// 避免属性值中的换行被浏览器规范化或破坏单行的属性结构
+pub fn html_escape_attr(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in html_escape(value).chars() {
+        if c.is_control() {
+            escaped.push_str(&format!("&#{};", c as u32));
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(html_escape("<script>alert('xss')</script>"),
+                   "&lt;script&gt;alert(&#39;xss&#39;)&lt;/script&gt;");
+    }
+
+    #[test]
+    fn test_render_page_title_and_logo() {
+        let html = render_to_html(&[], "abc", &TranscriptConfig::default());
+        assert!(html.contains("<title>会话记录 - abc</title>"));
+        assert!(html.contains("<h1>📝 会话记录</h1>"));
+
+        let options = TranscriptConfig {
+            page_title: "团队<记录>".to_string(),
+            logo_url: "https://example.com/logo.png".to_string(),
+            ..Default::default()
+        };
+        let html = render_to_html(&[], "abc", &options);
+        assert!(html.contains("<title>团队&lt;记录&gt; - abc</title>"));
+        assert!(html.contains(r#"<img class="logo" src="https://example.com/logo.png" alt="">"#));
+        assert!(!html.contains("📝 团队"));
+    }
+
+    #[test]
+    fn test_last_assistant_text() {
+        let lines = [
+            r#"{"type":"user","message":{"role":"user","content":"帮我写测试"}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"第一轮"}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"已完成测试"},{"type":"tool_use","id":"t1","name":"Bash","input":{}}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"t2","name":"Read","input":{}}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t2","content":"ok"}]}}"#,
+        ];
+        let messages: Vec<Message> = lines
+            .iter()
+            .filter_map(|line| serde_json::from_str::<TranscriptEntry>(line).unwrap().message)
+            .collect();
+
+        // 跳过只有工具调用的助手消息
+        assert_eq!(last_assistant_text(&messages), Some("已完成测试".to_string()));
+        assert_eq!(last_assistant_text(&messages[..1]), None);
+    }
+
+    #[test]
+    fn test_first_user_preview() {
+        let lines = [
+            r#"{"type":"user","message":{"role":"user","content":"<system-reminder>注入内容</system-reminder>"}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"你好"}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"<system-reminder>x</system-reminder>\n  修复登录页的\n样式问题  "}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":"第二个问题"}}"#,
+        ];
+        let messages: Vec<Message> = lines
+            .iter()
+            .filter_map(|line| serde_json::from_str::<TranscriptEntry>(line).unwrap().message)
+            .collect();
+
+        // 跳过系统注入与工具结果，空白折叠为单个空格
+        assert_eq!(first_user_preview(&messages, 80), Some("修复登录页的 样式问题".to_string()));
+        assert_eq!(first_user_preview(&messages, 4), Some("修复登录…".to_string()));
+        assert_eq!(first_user_preview(&messages[..3], 80), None);
+
+        // 从文件读取时找到首条提问即停止，之后无法解析的内容不影响结果
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("s.jsonl");
+        fs::write(&path, format!("{}\n{{not json\n", lines.join("\n"))).unwrap();
+        assert_eq!(read_first_user_preview(&path, 80), Some("修复登录页的 样式问题".to_string()));
+        assert!(parse_transcript(&path, &[]).is_err());
+
+        let gz_path = dir.path().join("s.jsonl.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, lines.join("\n").as_bytes()).unwrap();
+        fs::write(&gz_path, encoder.finish().unwrap()).unwrap();
+        assert_eq!(read_first_user_preview(&gz_path, 80), Some("修复登录页的 样式问题".to_string()));
+
+        assert_eq!(read_first_user_preview(&dir.path().join("missing.jsonl"), 80), None);
+    }
+
+    #[test]
+    fn test_parse_gzip_transcript() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+
+        let line = r#"{"type":"user","message":{"role":"user","content":"归档会话"}}"#;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(line.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+
+        // 按扩展名识别
+        let path = dir.path().join("t.jsonl.gz");
+        fs::write(&path, &gzipped).unwrap();
+        let messages = parse_transcript(&path, &[]).unwrap();
+        assert!(matches!(&messages[0].content, MessageContent::String(t) if t == "归档会话"));
+
+        // 按文件头识别
+        let path = dir.path().join("t.jsonl");
+        fs::write(&path, &gzipped).unwrap();
+        assert_eq!(parse_transcript(&path, &[]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_transcript_entry_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.jsonl");
+        fs::write(
+            &path,
+            [
+                r#"{"type":"summary","summary":"重构配置模块","leafUuid":"x"}"#,
+                r#"{"type":"system","content":"Hook 输出"}"#,
+                r#"{"type":"user","message":{"role":"user","content":"你好"}}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let messages = parse_transcript(&path, &[]).unwrap();
+        assert_eq!(messages.len(), 1);
+
+        let messages = parse_transcript(&path, &["summary".to_string()]).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "summary");
+        assert!(matches!(&messages[0].content, MessageContent::String(t) if t == "重构配置模块"));
+
+        let types = ["summary".to_string(), "system".to_string()];
+        let messages = parse_transcript(&path, &types).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(&messages[1].content, MessageContent::String(t) if t == "Hook 输出"));
+    }
+
+    #[test]
+    fn test_render_message_order() {
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: MessageContent::String("第一条".to_string()),
+                sidechain: false,
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: MessageContent::String("第二条".to_string()),
+                sidechain: false,
+            },
+        ];
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(html.find("第一条").unwrap() < html.find("第二条").unwrap());
+        assert!(html.contains("跳到底部"));
+
+        let options = TranscriptConfig {
+            message_order: "desc".to_string(),
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.find("第二条").unwrap() < html.find("第一条").unwrap());
+        assert!(html.contains("回到顶部"));
+        assert!(!html.contains("跳到底部"));
+    }
+
+    #[test]
+    fn test_detect_and_format_result() {
+        let (text, language) = detect_and_format_result(r#"{"items":[1,2],"ok":true}"#);
+        assert_eq!(language, Some("json"));
+        assert_eq!(text, "{\n  \"items\": [\n    1,\n    2\n  ],\n  \"ok\": true\n}");
+
+        let (text, language) = detect_and_format_result("  [1]\n");
+        assert_eq!(language, Some("json"));
+        assert_eq!(text, "[\n  1\n]");
+
+        // 非 JSON 内容保持原样
+        for raw in ["total 8\ndrwxr-xr-x", "{not json", "42", ""] {
+            assert_eq!(detect_and_format_result(raw), (raw.to_string(), None));
+        }
+    }
+
+    #[test]
+    fn test_minify_html() {
+        let html = "<div>\n    <p>a   b\n  c</p>\n    <pre><code>fn main() {\n    x\n}</code></pre>\n<div class=\"message-content\" data-markdown>  缩进\n\n  <div>保留</div>  </div>\n<style>\n  .a {\n    color: red;\n  }\n</style>\n<script>\n// 注释\nrun();\n</script>\n</div>";
+        assert_eq!(
+            minify_html(html),
+            "<div><p>a b c</p><pre><code>fn main() {\n    x\n}</code></pre><div class=\"message-content\" data-markdown>  缩进\n\n  <div>保留</div>  </div><style> .a { color: red; } </style><script>\n// 注释\nrun();\n</script></div>"
+        );
+    }
+
+    #[test]
+    fn test_render_minified_transcript() {
+        let messages: Vec<Message> = (0..50)
+            .flat_map(|i| {
+                [
+                    format!(r#"{{"role":"user","content":"第 {} 个问题\n  - 保留缩进"}}"#, i),
+                    format!(r#"{{"role":"assistant","content":[{{"type":"text","text":"回答 {}"}},{{"type":"tool_use","id":"t{}","name":"Bash","input":{{"command":"ls -la"}}}}]}}"#, i, i),
+                    format!(r#"{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"t{}","content":"total 8\n  drwxr-xr-x  2 user"}}]}}"#, i),
+                ]
+            })
+            .map(|line| serde_json::from_str(&line).unwrap())
+            .collect();
+
+        let plain = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        let options = TranscriptConfig {
+            minify_html: true,
+            ..Default::default()
+        };
+        let minified = render_to_html(&messages, "abc", &options);
+
+        assert!(minified.len() < plain.len());
+        assert!(minified.contains("第 0 个问题\n  - 保留缩进"));
+        assert!(minified.contains("total 8\n  drwxr-xr-x  2 user"));
+    }
+
+    #[test]
+    fn test_render_sidechain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.jsonl");
+        fs::write(
+            &path,
+            [
+                r#"{"type":"user","message":{"role":"user","content":"主线问题"}}"#,
+                r#"{"type":"user","isSidechain":true,"message":{"role":"user","content":"子任务"}}"#,
+                r#"{"type":"assistant","isSidechain":true,"message":{"role":"assistant","content":"子任务结果"}}"#,
+                r#"{"type":"assistant","message":{"role":"assistant","content":"主线回答"}}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let messages = parse_transcript(&path, &[]).unwrap();
+        let flags: Vec<bool> = messages.iter().map(|m| m.sidechain).collect();
+        assert_eq!(flags, [false, true, true, false]);
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert_eq!(html.matches(r#"<details class="sidechain""#).count(), 1);
+        assert!(html.contains("子代理（2 条消息）"));
+        assert!(html.contains("toggleSidechains()"));
+        let start = html.find(r#"<details class="sidechain""#).unwrap();
+        let end = html.find("</details>").unwrap();
+        assert!((start..end).contains(&html.find("子任务结果").unwrap()));
+        assert!(!(start..end).contains(&html.find("主线回答").unwrap()));
+
+        // 没有子代理消息时不显示折叠开关
+        let html = render_to_html(&messages[..1], "abc", &TranscriptConfig::default());
+        assert!(!html.contains(r#"class="sidechain-toggle""#));
+    }
+
+    #[test]
+    fn test_render_empty_transcript() {
+        let html = render_to_html(&[], "abc", &TranscriptConfig::default());
+        assert!(html.contains("该会话暂无消息记录"));
+
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::String("你好".to_string()),
+            sidechain: false,
+        }];
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(!html.contains("该会话暂无消息记录"));
+    }
+
+    #[test]
+    fn test_render_refresh() {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::String("进行中".to_string()),
+            sidechain: false,
+        }];
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(!html.contains("http-equiv=\"refresh\""));
+
+        let options = TranscriptConfig {
+            refresh_secs: 15,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains(r#"<meta http-equiv="refresh" content="15">"#));
+
+        // 过小的间隔按最小值处理
+        let options = TranscriptConfig {
+            refresh_secs: 1,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains(r#"content="5""#));
+    }
+
+    #[test]
+    fn test_render_parallel_tool_calls() {
+        let messages: Vec<Message> = [
+            r#"{"role":"assistant","content":[{"type":"tool_use","id":"a","name":"Read","input":{}},{"type":"tool_use","id":"b","name":"Grep","input":{}}]}"#,
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"b","content":"grep-out"},{"type":"tool_result","tool_use_id":"a","content":"read-out"}]}"#,
+            r#"{"role":"assistant","content":[{"type":"tool_use","id":"c","name":"Bash","input":{}}]}"#,
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"c","content":"bash-out"}]}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+
+        assert!(html.contains("并行工具调用 (2)"));
+        assert!(html.contains("并行工具结果 (2)"));
+        assert!(html.contains("📖 #1 Tool: Read"));
+        assert!(html.contains("🔍 #2 Tool: Grep"));
+        // 结果按调用顺序排列
+        assert!(html.find("read-out").unwrap() < html.find("grep-out").unwrap());
+        assert!(html.contains("↩️ #2 Grep"));
+
+        // 单个工具调用保持原样
+        assert!(html.contains("💻 Tool: Bash"));
+        assert_eq!(html.matches(r#"<div class="tool-group">"#).count(), 2);
+    }
+
+    #[test]
+    fn test_render_live_script() {
+        let messages: Vec<Message> = vec![serde_json::from_str(r#"{"role":"user","content":"hi"}"#).unwrap()];
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(!html.contains("EventSource"));
+
+        let options = TranscriptConfig {
+            live: true,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains("new EventSource(url)"));
+        assert!(html.contains("连接已断开，正在重连..."));
+        assert!(html.contains("delay = Math.min(delay * 2, maxDelay)"));
+
+        // 自包含页面不连接事件流
+        let options = TranscriptConfig {
+            live: true,
+            asset_mode: ASSET_MODE_INLINE.to_string(),
+            ..Default::default()
+        };
+        assert!(!render_to_html(&messages, "abc", &options).contains("EventSource"));
+    }
+
+    #[test]
+    fn test_render_dedupe_messages() {
+        let messages: Vec<Message> = [
+            r#"{"role":"user","content":"修一下"}"#,
+            r#"{"role":"assistant","content":[{"type":"text","text":"好的，马上修复"}]}"#,
+            r#"{"role":"assistant","content":[{"type":"text","text":"  好的，马上修复\n\n"}]}"#,
+            r#"{"role":"assistant","content":"好的，马上修复"}"#,
+            r#"{"role":"assistant","content":"已修复"}"#,
+            r#"{"role":"assistant","content":"好的，马上修复"}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert_eq!(html.matches("马上修复").count(), 4);
+        assert!(!html.contains(r#"class="repeat-badge""#));
+
+        let options = TranscriptConfig {
+            dedupe_messages: true,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        // 只合并连续的重复，间隔出现的不合并
+        assert_eq!(html.matches("马上修复").count(), 2);
+        assert!(html.contains(r#"<span class="repeat-badge" title="连续重复 3 次">×3</span>"#));
+        assert_eq!(html.matches(r#"class="repeat-badge""#).count(), 1);
+    }
+
+    #[test]
+    fn test_render_escapes_tool_names() {
+        let messages: Vec<Message> = [
+            r#"{"role":"assistant","content":[{"type":"tool_use","id":"a","name":"<img src=x onerror=alert(1)>","input":{}}]}"#,
+            r#"{"role":"assistant","content":[{"type":"tool_use","id":"b","name":"Evil\"><script>x()</script>","input":{}},{"type":"tool_use","id":"c","name":"Read","input":{}}]}"#,
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"b","content":"ok"},{"type":"tool_result","tool_use_id":"c","content":"ok"}]}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(!html.contains("<img src=x"));
+        assert!(!html.contains("<script>x()"));
+        assert!(html.contains("🔧 Tool: &lt;img src=x onerror=alert(1)&gt;</div>"));
+        assert!(html.contains("🔧 #1 Tool: Evil&quot;&gt;&lt;script&gt;x()&lt;/script&gt;</div>"));
+
+        assert_eq!(html_escape_attr("a\"b\nc"), "a&quot;b&#10;c");
+    }
+
+    #[test]
+    fn test_render_copy_buttons() {
+        let messages: Vec<Message> = vec![serde_json::from_str(r#"{"role":"assistant","content":"hi"}"#).unwrap()];
+
+        // 导出的自包含页面同样带有复制按钮
+        for asset_mode in ["cdn", ASSET_MODE_INLINE] {
+            let options = TranscriptConfig {
+                asset_mode: asset_mode.to_string(),
+                ..Default::default()
+            };
+            let html = render_to_html(&messages, "abc", &options);
+            assert!(html.contains("wrap.appendChild(copyButton(() => pre.innerText))"));
+            assert!(html.contains("button.textContent = '已复制'"));
+            assert!(html.contains(".code-wrap > .copy-btn"));
+        }
+    }
+
+    #[test]
+    fn test_copyable_text_skips_copy_buttons() {
+        // 取出脚本中的 copyableText，用最小的 DOM 桩在 node 中执行
+        let start = COPY_SCRIPT.find("function copyableText").unwrap();
+        let end = start + COPY_SCRIPT[start..].find("\n        }").unwrap() + "\n        }".len();
+        let function = &COPY_SCRIPT[start..end];
+
+        let script = format!(
+            r#"
+class El {{
+    constructor(className, text, children = []) {{
+        this.className = className;
+        this.text = text;
+        this.children = children;
+        children.forEach(child => child.parent = this);
+    }}
+    cloneNode() {{
+        return new El(this.className, this.text, this.children.map(child => child.cloneNode()));
+    }}
+    querySelectorAll(selector) {{
+        const found = [];
+        const visit = el => el.children.forEach(child => {{
+            if ('.' + child.className === selector) found.push(child);
+            visit(child);
+        }});
+        visit(this);
+        return found;
+    }}
+    remove() {{
+        this.parent.children = this.parent.children.filter(child => child !== this);
+    }}
+    get textContent() {{
+        return this.text + this.children.map(child => child.textContent).join('');
+    }}
+}}
+{function}
+const content = new El('message-content', '', [
+    new El('p', '运行下面的命令\n'),
+    new El('code-wrap', '', [new El('pre', 'cargo test'), new El('copy-btn', '复制')]),
+]);
+process.stdout.write(JSON.stringify([copyableText(content), content.textContent]));
+"#
+        );
+
+        let output = match std::process::Command::new("node").arg("-e").arg(&script).output() {
+            Ok(output) => output,
+            Err(_) => {
+                eprintln!("未安装 node，跳过复制脚本检查");
+                return;
+            }
+        };
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let [copied, original]: [String; 2] = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(copied, "运行下面的命令\ncargo test");
+        // 页面上的按钮不受影响
+        assert!(original.ends_with("复制"));
+    }
+
+    #[test]
+    fn test_render_blocks_whitelist() {
+        let messages: Vec<Message> = [
+            r#"{"role":"user","content":"请读取配置"}"#,
+            r#"{"role":"assistant","content":[{"type":"text","text":"好的"},{"type":"tool_use","id":"a","name":"Read","input":{"token":"secret-123"}}]}"#,
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"a","content":"result-out"}]}"#,
+            r#"{"role":"user","content":[{"type":"image","source":{"type":"base64","media_type":"image/png","data":"aGVsbG8="}}]}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let options = TranscriptConfig {
+            render_blocks: vec!["text".to_string(), "tool_result".to_string()],
+            show_system: true,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains("请读取配置"));
+        assert!(html.contains("好的"));
+        assert!(html.contains("result-out"));
+        assert!(!html.contains("secret-123"));
+        assert!(!html.contains("aGVsbG8="));
+        // 内容全部被过滤的消息不留空白气泡
+        assert_eq!(html.matches(r#"<div class="message user">"#).count(), 2);
+
+        // 默认展示全部类型
+        let html = render_to_html(&messages, "abc", &TranscriptConfig { show_system: true, ..Default::default() });
+        assert!(html.contains("secret-123"));
+        assert!(html.contains("aGVsbG8="));
+    }
+
+    #[test]
+    fn test_render_error_tool_result() {
+        let messages: Vec<Message> = [
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"a","content":"exit code 1","is_error":true}]}"#,
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"b","content":"ok-out"}]}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        let error = html.find(r#"<div class="tool-result error"><div class="tool-result-label">⚠️ 执行出错</div>"#).unwrap();
+        assert!(error < html.find("exit code 1").unwrap());
+        assert!(html.contains(r#"<div class="tool-result"><pre><code>ok-out"#));
+        assert_eq!(html.matches("⚠️ 执行出错").count(), 1);
+    }
+
+    #[test]
+    fn test_render_without_thinking() {
+        let messages: Vec<Message> = [
+            r#"{"role":"assistant","content":[{"type":"thinking","thinking":"先读文件"},{"type":"tool_use","id":"a","name":"Read","input":{}},{"type":"thinking","thinking":"再搜索"},{"type":"tool_use","id":"b","name":"Grep","input":{}}]}"#,
+            r#"{"role":"user","content":[{"type":"tool_result","tool_use_id":"a","content":"read-out"},{"type":"tool_result","tool_use_id":"b","content":"grep-out"}]}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(html.contains("先读文件"));
+
+        let options = TranscriptConfig {
+            show_thinking: false,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(!html.contains("先读文件"));
+        assert!(!html.contains("再搜索"));
+        // 工具调用与结果的配对不受影响
+        assert!(html.contains("并行工具调用 (2)"));
+        assert!(html.contains("↩️ #1 Read"));
+        assert!(html.contains("↩️ #2 Grep"));
+    }
+
+    #[test]
+    fn test_render_inline_asset_mode() {
+        let messages = vec![Message {
+            role: "assistant".to_string(),
+            content: MessageContent::String(
+                "**完成** <script>x</script> [link](javascript:alert(1))".to_string(),
+            ),
+            sidechain: false,
+        }];
+        let options = TranscriptConfig {
+            asset_mode: ASSET_MODE_INLINE.to_string(),
+            ..Default::default()
+        };
+
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains("<strong>完成</strong>"));
+        assert!(html.contains("&lt;script&gt;x&lt;/script&gt;"));
+        assert!(html.contains(r##"<a href="#">link</a>"##));
+        assert!(!html.contains("data-markdown"));
+        assert!(!html.contains("cdn.jsdelivr.net"));
+        assert!(!html.contains("marked.use"));
+    }
+
+    #[test]
+    fn test_content_block_dispatch_on_type() {
+        let unknown: ContentBlock = serde_json::from_str(r#"{"type":"image","source":{}}"#).unwrap();
+        assert!(matches!(unknown, ContentBlock::Other(raw) if raw["type"] == "image"));
+        let untyped: ContentBlock = serde_json::from_str(r#"{"title":"附件"}"#).unwrap();
+        assert!(matches!(untyped, ContentBlock::Other(_)));
+
+        // 已知类型格式错误时报错，而不是被当作未知内容块吞掉
+        assert!(serde_json::from_str::<ContentBlock>(r#"{"type":"text"}"#).is_err());
+        assert!(serde_json::from_str::<ContentBlock>(r#"{"type":"tool_use","id":"t1","input":{}}"#).is_err());
+        assert!(serde_json::from_str::<Message>(r#"{"role":"assistant","content":[{"type":"text","text":1}]}"#).is_err());
+
+        // 序列化保持原样：已知类型带 type 字段，未知内容块输出原始 JSON
+        let text: ContentBlock = serde_json::from_str(r#"{"type":"text","text":"hi"}"#).unwrap();
+        assert_eq!(serde_json::to_string(&text).unwrap(), r#"{"type":"text","text":"hi"}"#);
+        let other = ContentBlock::Other(serde_json::json!({"type": "image"}));
+        assert_eq!(serde_json::to_string(&other).unwrap(), r#"{"type":"image"}"#);
+    }
+
+    #[test]
+    fn test_tool_use_accessors() {
+        let edit: ContentBlock = serde_json::from_str(
+            r#"{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/main.rs","old_string":"a","new_string":"b","replace_all":true}}"#,
+        )
+        .unwrap();
+
+        let tool = edit.as_tool_use().unwrap();
+        assert_eq!(tool.id, "t1");
+        assert_eq!(tool.name, "Edit");
+        assert_eq!(tool.input["old_string"], "a");
+        assert_eq!(edit.tool_name(), Some("Edit"));
+        assert_eq!(edit.tool_input_field("file_path").and_then(|v| v.as_str()), Some("src/main.rs"));
+        assert_eq!(edit.tool_input_field("replace_all").and_then(|v| v.as_bool()), Some(true));
+        assert!(edit.tool_input_field("missing").is_none());
+
+        // 其他内容块没有工具信息
+        let text: ContentBlock = serde_json::from_str(r#"{"type":"text","text":"完成"}"#).unwrap();
+        assert!(text.as_tool_use().is_none());
+        assert!(text.tool_name().is_none());
+        assert!(text.tool_input_field("file_path").is_none());
+
+        // input 不是对象时取不到字段
+        let raw: ContentBlock = serde_json::from_str(r#"{"type":"tool_use","id":"t2","name":"Bash","input":"ls"}"#).unwrap();
+        assert_eq!(raw.tool_name(), Some("Bash"));
+        assert!(raw.tool_input_field("command").is_none());
+    }
+
+    #[test]
+    fn test_tool_summary() {
+        let bash: ContentBlock = serde_json::from_str(
+            r#"{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls","description":"列出 <src> 目录"}}"#,
+        )
+        .unwrap();
+        assert_eq!(tool_summary(&bash), r#"<span class="tool-summary"> · 列出 &lt;src&gt; 目录</span>"#);
+
+        let read: ContentBlock = serde_json::from_str(
+            r#"{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"a.rs","description":" "}}"#,
+        )
+        .unwrap();
+        assert_eq!(tool_summary(&read), "");
+    }
+
+    #[test]
+    fn test_tool_icon() {
+        assert_eq!(tool_icon("Read"), "📖");
+        assert_eq!(tool_icon("Edit"), "✏️");
+        assert_eq!(tool_icon("WebFetch"), "🌐");
+        assert_eq!(tool_icon("mcp__github__create_issue"), "🔌");
+        assert_eq!(tool_icon("read"), "🔧");
+        assert_eq!(tool_icon("Unknown"), "🔧");
+    }
+
+    #[test]
+    fn test_markdown_script_falls_back_without_marked() {
+        let messages = vec![Message {
+            role: "assistant".to_string(),
+            content: MessageContent::String("**完成**\n第二行".to_string()),
+            sidechain: false,
+        }];
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(html.contains("**完成**\n第二行"));
+        assert!(html.contains("typeof marked === 'undefined'"));
+        assert!(html.contains("Markdown 渲染不可用"));
+        assert!(html.contains("typeof hljs !== 'undefined'"));
+    }
+
+    #[test]
+    fn test_render_gfm_tables_and_task_lists() {
+        let messages = vec![Message {
+            role: "assistant".to_string(),
+            content: MessageContent::String(
+                "| 列 | 值 |\n| --- | --- |\n| a | 1 |\n\n- [x] 完成\n- [ ] 待办".to_string(),
+            ),
+            sidechain: false,
+        }];
+
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(html.contains("gfm: true"));
+        assert!(html.contains(".message-content table"));
+
+        let options = TranscriptConfig {
+            asset_mode: ASSET_MODE_INLINE.to_string(),
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains("<table>"));
+        assert!(html.contains("<th>列</th>"));
+        assert!(html.contains(r#"type="checkbox""#));
+    }
+
+    #[test]
+    fn test_render_role_labels_and_colors() {
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: MessageContent::String("你好".to_string()),
+                sidechain: false,
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: MessageContent::String("你好！".to_string()),
+                sidechain: false,
+            },
+        ];
+
+        // 默认与原行为一致
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(html.contains(r#"<div class="message-role">user</div>"#));
+        assert!(html.contains(r#"<div class="message-role">assistant</div>"#));
+        assert!(!html.contains(".message.user { border-left-color"));
+
+        let options = TranscriptConfig {
+            user_label: "我".to_string(),
+            assistant_label: "Claude".to_string(),
+            user_color: "#00897b".to_string(),
+            assistant_color: "red;}</style><script>".to_string(),
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains(r#"<div class="message user">
+                <div class="message-role">我</div>"#));
+        assert!(html.contains(r#"<div class="message-role">Claude</div>"#));
+        assert!(html.contains(".message.user { border-left-color: #00897b; }"));
+        // 不安全的颜色值被忽略
+        assert!(!html.contains(".message.assistant { border-left-color"));
+    }
+
+    #[test]
+    fn test_render_page_banner() {
+        let html = render_to_html(&[], "abc", &TranscriptConfig::default());
+        assert!(!html.contains(r#"<div class="page-banner">"#));
+
+        let options = TranscriptConfig {
+            page_banner: "内部资料，请勿外传 <b>".to_string(),
+            ..Default::default()
+        };
+        let html = render_to_html(&[], "abc", &options);
+        assert!(html.contains(
+            r#"<div class="page-banner">⚠️ 内部资料，请勿外传 &lt;b&gt;</div>"#
+        ));
+        assert!(!html.contains("<title>内部资料"));
+        assert!(!html.split("</title>").next().unwrap().contains("请勿外传"));
+    }
+
+    #[test]
+    fn test_split_system_segments() {
+        let text = "前文<system-reminder>提醒 A</system-reminder>中间<user-prompt-submit-hook>hook 输出</user-prompt-submit-hook>";
+        assert_eq!(
+            split_system_segments(text),
+            vec![
+                (None, "前文"),
+                (Some("system-reminder"), "提醒 A"),
+                (None, "中间"),
+                (Some("user-prompt-submit-hook"), "hook 输出"),
+            ]
+        );
+
+        // 未闭合的标签按普通文本处理
+        assert_eq!(split_system_segments("a<system-reminder>b"), vec![(None, "a<system-reminder>b")]);
+    }
+
+    #[test]
+    fn test_render_system_blocks() {
+        let json = r#"{"role":"user","content":[{"type":"text","text":"修一下 bug<system-reminder>今天是周五</system-reminder>"},{"type":"document","title":"附件"}]}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        let MessageContent::Array(blocks) = &message.content else {
+            panic!("应为内容块数组");
+        };
+        assert!(matches!(&blocks[1], ContentBlock::Other(raw) if raw["title"] == "附件"));
+
+        let messages = vec![message];
+
+        // 默认隐藏
+        let html = render_to_html(&messages, "abc", &TranscriptConfig::default());
+        assert!(html.contains("修一下 bug"));
+        assert!(!html.contains("今天是周五"));
+        assert!(!html.contains(r#"<div class="system-block">"#));
+
+        let options = TranscriptConfig {
+            show_system: true,
+            ..Default::default()
+        };
+        let html = render_to_html(&messages, "abc", &options);
+        assert!(html.contains("⚙️ 系统提示 · system-reminder</div><pre><code>今天是周五</code>"));
+        assert!(html.contains("⚙️ 系统提示 · document"));
+        assert!(html.contains("附件"));
+    }
+
+    #[test]
+    fn test_code_theme() {
+        let html = render_to_html(&[], "abc", &TranscriptConfig::default());
+        assert!(html.contains("/highlight.js/11.9.0/styles/github.min.css"));
+
+        let options = TranscriptConfig {
+            code_theme: "atom-one-dark".to_string(),
+            asset_mode: ASSET_MODE_LOCAL.to_string(),
+            ..Default::default()
+        };
+        let html = render_to_html(&[], "abc", &options);
+        assert!(html.contains(r#"href="/assets/vendor/styles/atom-one-dark.min.css""#));
+
+        assert_eq!(code_theme_name("base16/monokai"), "base16/monokai");
+        assert_eq!(code_theme_name("monokai.min.css"), "monokai");
+        assert_eq!(code_theme_name("../../etc/passwd"), DEFAULT_CODE_THEME);
+        assert_eq!(code_theme_name("x\"><script>"), DEFAULT_CODE_THEME);
+        assert_eq!(code_theme_name(""), DEFAULT_CODE_THEME);
+    }
+
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://example.com"));
+        assert!(is_safe_url("/assets/a.png"));
+        assert!(is_safe_url("#top"));
+        assert!(is_safe_url("docs/readme.md"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url("JavaScript:alert(1)"));
+        assert!(!is_safe_url("data:text/html,x"));
     }
 }
@@ -0,0 +1,84 @@
+//! 通用工具函数
+//!
+//! 字符串截断统一使用这里的函数，按字符（或字素簇）而不是字节处理，
+//! 避免在多字节 UTF-8 字符中间切断导致 panic
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 截断时追加的省略号
+pub const ELLIPSIS: &str = "…";
+
+/// 按字符数截断，超出 `max` 个字符时保留前 `max` 个并追加省略号
+pub fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => format!("{}{}", &s[..end], ELLIPSIS),
+        None => s.to_string(),
+    }
+}
+
+/// 按字素簇截断，超出 `max` 个时保留前 `max` 个并追加省略号
+///
+/// 与 [`truncate_chars`] 的区别是不会拆开组合 emoji（如 👨‍👩‍👧、🇨🇳）和带声调的字符
+pub fn truncate_graphemes(s: &str, max: usize) -> String {
+    match s.grapheme_indices(true).nth(max) {
+        Some((end, _)) => format!("{}{}", &s[..end], ELLIPSIS),
+        None => s.to_string(),
+    }
+}
+
+/// 前 `n` 个字符
+pub fn first_chars(s: &str, n: usize) -> &str {
+    match s.char_indices().nth(n) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+/// 后 `n` 个字符
+pub fn last_chars(s: &str, n: usize) -> &str {
+    if n == 0 {
+        return "";
+    }
+
+    match s.char_indices().rev().nth(n - 1) {
+        Some((start, _)) => &s[start..],
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("hello", 10), "hello");
+        assert_eq!(truncate_chars("hello", 5), "hello");
+        assert_eq!(truncate_chars("hello", 3), "hel…");
+        assert_eq!(truncate_chars("任务已经完成了", 4), "任务已经…");
+        assert_eq!(truncate_chars("🎉🎉🎉", 2), "🎉🎉…");
+        assert_eq!(truncate_chars("", 0), "");
+        assert_eq!(truncate_chars("abc", 0), "…");
+    }
+
+    #[test]
+    fn test_truncate_graphemes() {
+        // 家庭 emoji 由多个码点组成，按字符截断会拆开
+        let family = "👨‍👩‍👧";
+        let text = format!("{}{}完成", family, family);
+        assert_eq!(truncate_graphemes(&text, 1), format!("{}…", family));
+        assert_ne!(truncate_chars(&text, 1), format!("{}…", family));
+
+        assert_eq!(truncate_graphemes("🇨🇳中国", 2), "🇨🇳中…");
+        assert_eq!(truncate_graphemes("中国", 2), "中国");
+    }
+
+    #[test]
+    fn test_first_last_chars() {
+        assert_eq!(first_chars("微信号码abc", 2), "微信");
+        assert_eq!(first_chars("ab", 5), "ab");
+        assert_eq!(last_chars("微信号码abc", 4), "码abc");
+        assert_eq!(last_chars("ab", 5), "ab");
+        assert_eq!(last_chars("ab", 0), "");
+    }
+}
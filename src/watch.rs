@@ -0,0 +1,142 @@
+//! 监视进行中的会话
+//!
+//! 适用于启动时未开启远程模式、之后想接入远程监督的会话：轮询 transcript 文件，
+//! 文件在一段时间内不再变化且最后一条消息是助手的回复时，视为会话已停止，
+//! 发送链接卡片并等待回复
+
+use anyhow::Result;
+use colored::*;
+use std::time::{Duration, Instant};
+
+use crate::monitor::{IdleTracker, MAX_POLL_INTERVAL, modified};
+use crate::notify;
+use crate::output::{self, banner};
+use crate::server;
+use crate::transcript::{self, ContentBlock, Message, MessageContent};
+
+/// 监视会话，停止时发送链接卡片并等待回复
+///
+/// 收到回复后继续监视，直到会话再次停止；按 Ctrl+C 结束
+pub fn run(session_id: &str, idle_secs: u64, timeout: Option<u64>) -> Result<()> {
+    // 启动时就检查能否接收回复，避免会话停止后发出卡片才报错
+    notify::check_replies_supported()?;
+    let transcript_path = server::find_transcript(session_id)?;
+    let idle = Duration::from_secs(idle_secs.max(1));
+    let poll_interval = idle.min(MAX_POLL_INTERVAL);
+
+    banner("  👀 正在监视会话", Color::Cyan);
+    if !output::is_quiet() {
+        println!("  {} {}", "会话 ID:".dimmed(), session_id);
+        println!("  {} {}", "Transcript:".dimmed(), transcript_path.display());
+        println!("  {} {}s 无变化视为已停止", "判定:".dimmed(), idle.as_secs());
+        println!();
+    }
+
+    // 每段空闲只检查一次，避免同一次停止重复通知
+    let mut tracker = IdleTracker::new(modified(&transcript_path), Instant::now());
+
+    loop {
+        std::thread::sleep(poll_interval);
+
+        if !tracker.observe(modified(&transcript_path), Instant::now(), idle) {
+            continue;
+        }
+
+        // 出错只提示并继续监视，同一次修改不再重试，避免每次轮询都刷屏
+        let messages = match transcript::parse_transcript(&transcript_path, &[]) {
+            Ok(messages) => messages,
+            Err(e) => {
+                eprintln!("⚠️ 解析 transcript 失败，继续监视: {:#}", e);
+                continue;
+            }
+        };
+        if !is_stopped(&messages) {
+            continue;
+        }
+
+        println!("🛑 会话已停止，发送通知...");
+
+        match notify_and_wait(session_id, timeout) {
+            Ok(reply) => println!("{}", reply),
+            Err(e) => eprintln!("⚠️ 通知失败，继续监视: {:#}", e),
+        }
+    }
+}
+
+/// 发送链接卡片并等待回复，返回回复内容
+fn notify_and_wait(session_id: &str, timeout: Option<u64>) -> Result<String> {
+    let summary = notify::summary_from_transcript(session_id)?;
+    let reply = notify::send_link_and_wait(session_id.to_string(), summary, None, timeout)?;
+    Ok(reply.text)
+}
+
+/// 会话是否已停止：最后一条消息是助手的回复，且没有待执行的工具调用
+fn is_stopped(messages: &[Message]) -> bool {
+    let Some(last) = messages.last() else {
+        return false;
+    };
+
+    if last.role != "assistant" {
+        return false;
+    }
+
+    match &last.content {
+        MessageContent::String(text) => !text.trim().is_empty(),
+        MessageContent::Array(blocks) => matches!(
+            blocks.iter().rev().find(|b| !matches!(b, ContentBlock::Other(_))),
+            Some(ContentBlock::Text { .. })
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: MessageContent) -> Message {
+        Message {
+            role: role.to_string(),
+            content,
+            sidechain: false,
+        }
+    }
+
+    #[test]
+    fn test_is_stopped() {
+        assert!(!is_stopped(&[]));
+
+        let done = message("assistant", MessageContent::String("完成".to_string()));
+        assert!(is_stopped(&[done]));
+
+        let waiting_user = message("user", MessageContent::String("继续".to_string()));
+        assert!(!is_stopped(&[waiting_user]));
+
+        let tool_pending = message(
+            "assistant",
+            MessageContent::Array(vec![
+                ContentBlock::Text {
+                    text: "我来看看".to_string(),
+                },
+                ContentBlock::ToolUse {
+                    id: "t1".to_string(),
+                    name: "Read".to_string(),
+                    input: serde_json::json!({}),
+                },
+            ]),
+        );
+        assert!(!is_stopped(&[tool_pending]));
+
+        let text_last = message(
+            "assistant",
+            MessageContent::Array(vec![
+                ContentBlock::Thinking {
+                    thinking: "...".to_string(),
+                },
+                ContentBlock::Text {
+                    text: "都改好了".to_string(),
+                },
+            ]),
+        );
+        assert!(is_stopped(&[text_last]));
+    }
+}